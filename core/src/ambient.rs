@@ -0,0 +1,114 @@
+//! Room brightness from a laptop's ambient light sensor, read from the IIO
+//! device in sysfs or, when the kernel doesn't expose one directly, from
+//! iio-sensor-proxy on the system D-Bus.
+
+use crate::config::AmbientLight;
+use anyhow::{Context, Result};
+use std::path::Path;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+/// Images at or below this average luminance count as dark
+const DARK: f32 = 0.35;
+/// Images at or above this average luminance count as bright
+const BRIGHT: f32 = 0.55;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lighting {
+    Dim,
+    Normal,
+    Bright,
+}
+
+impl Lighting {
+    pub fn from_lux(lux: f64, config: &AmbientLight) -> Self {
+        if lux <= config.dim_lux {
+            Lighting::Dim
+        } else if lux >= config.bright_lux {
+            Lighting::Bright
+        } else {
+            Lighting::Normal
+        }
+    }
+
+    /// Whether an image with average `luminance` fits this lighting
+    pub fn suits(self, luminance: f32) -> bool {
+        match self {
+            Lighting::Dim => luminance <= DARK,
+            Lighting::Normal => true,
+            Lighting::Bright => luminance >= BRIGHT,
+        }
+    }
+}
+
+/// Current illuminance in lux
+pub async fn light_level() -> Result<f64> {
+    match from_sysfs() {
+        Ok(lux) => Ok(lux),
+        Err(_) => from_dbus().await,
+    }
+}
+
+fn from_sysfs() -> Result<f64> {
+    for entry in std::fs::read_dir("/sys/bus/iio/devices")?.flatten() {
+        let dir = entry.path();
+        if let Some(lux) = read_number(&dir.join("in_illuminance_input")) {
+            return Ok(lux);
+        }
+        if let Some(raw) = read_number(&dir.join("in_illuminance_raw")) {
+            let scale = read_number(&dir.join("in_illuminance_scale")).unwrap_or(1.0);
+            return Ok(raw * scale);
+        }
+    }
+    anyhow::bail!("No IIO light sensor")
+}
+
+fn read_number(path: &Path) -> Option<f64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+async fn from_dbus() -> Result<f64> {
+    let cmd = Command::new("busctl")
+        .args([
+            "--system",
+            "get-property",
+            "net.hadess.SensorProxy",
+            "/net/hadess/SensorProxy",
+            "net.hadess.SensorProxy",
+            "LightLevel",
+        ])
+        .output();
+    let output = match timeout(Duration::from_secs(2), cmd).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(e).context("Failed to execute busctl"),
+        Err(_) => anyhow::bail!("iio-sensor-proxy did not answer"),
+    };
+    if !output.status.success() {
+        anyhow::bail!("iio-sensor-proxy: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    // Printed as "d 123.000000"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .trim()
+        .strip_prefix("d ")
+        .and_then(|v| v.parse().ok())
+        .with_context(|| format!("Unexpected LightLevel: {}", stdout.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lighting() {
+        let config = AmbientLight::default();
+        assert_eq!(Lighting::from_lux(10.0, &config), Lighting::Dim);
+        assert_eq!(Lighting::from_lux(300.0, &config), Lighting::Normal);
+        assert_eq!(Lighting::from_lux(20000.0, &config), Lighting::Bright);
+
+        assert!(Lighting::Dim.suits(0.2));
+        assert!(!Lighting::Dim.suits(0.7));
+        assert!(Lighting::Bright.suits(0.7));
+    }
+}
@@ -0,0 +1,1306 @@
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use tracing::{info, warn};
+use crate::index::ImageStats;
+use crate::wallpaper::fnv1a;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    pub profiles: HashMap<String, Profile>,
+    pub auto_switch: AutoSwitch,
+    pub monitor_detection: MonitorDetection,
+    pub current_profile: String,
+    /// Minimum gap between applied wallpaper switches (0 = no limit)
+    #[serde(default)]
+    pub switch_cooldown_ms: u64,
+    /// Seconds before a wallpaper directory is read again in the background;
+    /// images added or removed show up in rotation within this time
+    #[serde(default = "default_cache_max_age_secs")]
+    pub cache_max_age_secs: u64,
+    /// Seconds to wait for `swww img` before reporting it as still loading
+    /// (0 = no limit); huge animated GIFs can take longer than the default
+    #[serde(default = "default_swww_timeout_secs")]
+    pub swww_timeout_secs: u64,
+    #[serde(default)]
+    pub seasons: Vec<SeasonRule>,
+    #[serde(default)]
+    pub weather: WeatherConfig,
+    #[serde(default)]
+    pub startup: Startup,
+    #[serde(default)]
+    pub per_workspace: PerWorkspace,
+    #[serde(default)]
+    pub sandbox: Sandbox,
+    /// Friendly names usable in profile monitor lists, mapped to a rule
+    /// such as `desc:DELL U2723QE .*` (see `profile::alias_matches`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub monitor_aliases: HashMap<String, String>,
+    /// Also accept requests over TCP on this address, e.g. "0.0.0.0:7979"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listen_tcp: Option<String>,
+    /// Shared secret TCP clients must send with every request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_token: Option<String>,
+    /// Additional transition presets, or replacements for the built-in
+    /// `smooth`, `snappy` and `cinematic`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub transition_presets: HashMap<String, TransitionPreset>,
+    #[serde(default)]
+    pub refresh_rate_fps: RefreshRateFps,
+    /// Transition used instead on monitors with variable refresh rate
+    /// enabled, where long animations can make some panels flicker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vrr_transition: Option<VrrTransition>,
+    #[serde(default)]
+    pub tint: Tint,
+    #[serde(default)]
+    pub ambient_light: AmbientLight,
+    #[serde(default)]
+    pub readability: Readability,
+    #[serde(default)]
+    pub handoff: Handoff,
+    #[serde(default)]
+    pub lock: SessionLock,
+    #[serde(default)]
+    pub maintenance: Maintenance,
+    /// Where `set --from-clipboard` saves images
+    #[serde(default = "default_clipboard_dir")]
+    pub clipboard_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default)]
+    pub monitors: Vec<String>,
+    pub wallpaper_dirs: Vec<PathBuf>,
+    pub transition: String,
+    pub transition_duration: u32,
+    /// `--transition-fps` for swww, its default (30) when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition_fps: Option<u32>,
+    /// `--transition-bezier` for swww, e.g. ".25,.1,.25,1"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition_bezier: Option<String>,
+    /// Named transition preset; replaces all the transition settings above
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition_preset: Option<String>,
+    /// Conditions on the whole monitor set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires: Option<MonitorRequirements>,
+    /// Conditions at least one connected monitor must satisfy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub any_monitor: Option<MonitorMatch>,
+    /// Outputs that always show the same image; every other output gets its
+    /// own, e.g. `[["DP-1", "DP-3"]]` for a matched pair around a center display
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sync_groups: Vec<Vec<String>>,
+    /// Filename suffixes reserved for some outputs, e.g. `vertical = ["DP-3"]`
+    /// sends `*_vertical.png` only to DP-3 and gives DP-3 nothing else.
+    /// Outputs without a route get the images without a routed suffix.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub suffix_routes: HashMap<String, Vec<String>>,
+    /// Directories some outputs take their images from instead of
+    /// `wallpaper_dirs`, e.g. `DP-3 = ["~/Pictures/Vertical"]` for a
+    /// portrait screen. Each such output gets its own pick on every switch.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub output_dirs: HashMap<String, Vec<PathBuf>>,
+    /// Named sub-collections; `swww-manager channel NAME` rotates through
+    /// only that channel's directories until cleared or the profile changes
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub channels: HashMap<String, Vec<PathBuf>>,
+    /// Also rotate through matched sets: subdirectories with one image per
+    /// output named after its connector (`set01/DP-1.png`, `set01/eDP-1.png`),
+    /// always applied together
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sets: bool,
+    /// Give swww a tone-mapped 8-bit sRGB copy of 16-bit and HDR PNGs
+    /// (made with ImageMagick) so they don't look washed out
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tonemap_hdr: bool,
+    /// Only use images at most this bright (0.0 - 1.0), e.g. for OLED
+    /// screens. Like the other image filters it applies once images have
+    /// been analyzed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_luminance: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_luminance: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_contrast: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_busyness: Option<f32>,
+    /// Skip images where recognized text covers more than this share
+    /// (0.0 - 1.0); needs tesseract and the "content-filters" feature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_text: Option<f32>,
+    /// Skip images with faces; needs facedetect and the "content-filters"
+    /// feature
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip_faces: bool,
+    /// Procedural wallpapers, one per pattern rendered each day and rotated
+    /// along with the images in `wallpaper_dirs` (which may be empty)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub generators: Vec<Pattern>,
+    /// Colors for the generators from dark to light, e.g.
+    /// `["#11111b", "#45475a"]`; a muted dark palette when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palette: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pattern {
+    /// Smooth cloudy noise, like Perlin noise
+    Noise,
+    /// Low-poly triangle mesh shaded top to bottom
+    Triangles,
+    /// Diagonal bands of varying width
+    Stripes,
+}
+
+impl Profile {
+    pub fn has_conditions(&self) -> bool {
+        self.requires.is_some() || self.any_monitor.is_some()
+    }
+
+    /// Whether selection depends on analyzed image stats
+    pub fn filters_stats(&self) -> bool {
+        self.max_luminance.is_some()
+            || self.min_luminance.is_some()
+            || self.min_contrast.is_some()
+            || self.max_busyness.is_some()
+    }
+
+    /// Whether selection depends on text or face detection
+    pub fn filters_content(&self) -> bool {
+        self.max_text.is_some() || self.skip_faces
+    }
+
+    /// Whether an image's detected text and faces are acceptable; images
+    /// not scanned yet aren't
+    pub fn allows_content(&self, text: Option<f32>, faces: Option<u32>) -> bool {
+        self.max_text.is_none_or(|max| text.is_some_and(|t| t <= max))
+            && (!self.skip_faces || faces == Some(0))
+    }
+
+    pub fn allows(&self, stats: &ImageStats) -> bool {
+        self.max_luminance.is_none_or(|max| stats.luminance <= max)
+            && self.min_luminance.is_none_or(|min| stats.luminance >= min)
+            && self.min_contrast.is_none_or(|min| stats.contrast >= min)
+            && self.max_busyness.is_none_or(|max| stats.busyness <= max)
+    }
+}
+
+/// Transition type, duration, fps and easing curve under one name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransitionPreset {
+    pub transition: String,
+    pub duration: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fps: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bezier: Option<String>,
+}
+
+/// Transition fps following each monitor's refresh rate, used for
+/// profiles (and presets) that don't set `transition_fps`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RefreshRateFps {
+    pub enabled: bool,
+    pub min: u32,
+    pub max: u32,
+}
+
+impl Default for RefreshRateFps {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min: 30,
+            max: 144,
+        }
+    }
+}
+
+impl RefreshRateFps {
+    pub fn fps_for(&self, refresh_rate: f32) -> u32 {
+        (refresh_rate.round() as u32).clamp(self.min, self.max)
+    }
+}
+
+/// Transition settings replaced on VRR monitors; unset ones keep the
+/// profile's value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VrrTransition {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transition: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fps: Option<u32>,
+}
+
+/// Built-in presets as (name, transition, duration, fps, bezier)
+const TRANSITION_PRESETS: &[(&str, &str, u32, u32, &str)] = &[
+    ("smooth", "fade", 2, 60, ".25,.1,.25,1"),
+    ("snappy", "wipe", 1, 120, ".2,.9,.3,1"),
+    ("cinematic", "grow", 4, 60, ".65,0,.35,1"),
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorRequirements {
+    pub min_total_width: Option<i32>,
+    pub min_total_height: Option<i32>,
+    pub min_count: Option<usize>,
+    pub max_count: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorMatch {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub min_width: Option<i32>,
+    pub min_height: Option<i32>,
+    pub scale: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoSwitch {
+    pub enabled: bool,
+    pub interval: u64,
+    pub mode: SwitchMode,
+    /// Show wallpapers added since the last rotation before anything else
+    #[serde(default)]
+    pub prefer_new: bool,
+    /// Pick the next wallpaper right after a switch and read it into the
+    /// page cache so the following switch starts faster
+    #[serde(default = "default_preload")]
+    pub preload: bool,
+    /// In sequential mode, monitor i (sorted by name) shows image index + i
+    /// instead of every monitor showing the same image
+    #[serde(default)]
+    pub per_monitor_offset: bool,
+}
+
+fn default_preload() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SwitchMode {
+    Random,
+    Sequential,
+    /// Same wallpaper all day, derived from the date and profile name
+    Daily,
+}
+
+/// Calendar period that adds directories or tags to every profile's pool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonRule {
+    pub name: String,
+    /// Inclusive start date, "MM-DD"
+    pub from: String,
+    /// Inclusive end date, "MM-DD"; may wrap past the new year
+    pub to: String,
+    #[serde(default)]
+    pub dirs: Vec<PathBuf>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl SeasonRule {
+    pub fn is_active(&self, date: NaiveDate) -> bool {
+        let (Some(from), Some(to)) = (parse_month_day(&self.from), parse_month_day(&self.to)) else {
+            warn!("Season '{}' has an invalid date range ({} - {})", self.name, self.from, self.to);
+            return false;
+        };
+        let today = (date.month(), date.day());
+        if from <= to {
+            from <= today && today <= to
+        } else {
+            today >= from || today <= to
+        }
+    }
+}
+
+fn parse_month_day(s: &str) -> Option<(u32, u32)> {
+    let (month, day) = s.trim().split_once('-')?;
+    let month: u32 = month.parse().ok()?;
+    let day: u32 = day.parse().ok()?;
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((month, day))
+}
+
+/// Whether `program` is an executable file somewhere on `PATH`
+fn installed(program: &str) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path)
+            .any(|dir| std::fs::metadata(dir.join(program)).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0))
+    })
+}
+
+/// Open-Meteo lookup whose condition ("rain", "snow", "clear", "night", ...)
+/// is used as a preferred tag during selection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeatherConfig {
+    pub enabled: bool,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub refresh_minutes: u64,
+    /// Chance (0.0 - 1.0) of picking from images matching the weather
+    pub bias: f64,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            latitude: 0.0,
+            longitude: 0.0,
+            refresh_minutes: 30,
+            bias: 0.75,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Startup {
+    /// Re-apply the last shown wallpaper when the server starts
+    pub restore_wallpaper: bool,
+    /// How long to wait for Hyprland and swww-daemon before giving up
+    pub wait_timeout_secs: u64,
+}
+
+impl Default for Startup {
+    fn default() -> Self {
+        Self {
+            restore_wallpaper: false,
+            wait_timeout_secs: 30,
+        }
+    }
+}
+
+/// Warm tint applied to wallpapers set during the evening, to match a
+/// night-light setup. Needs ImageMagick; tinted copies are cached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tint {
+    pub enabled: bool,
+    /// Start of the tinted period, "HH:MM"
+    pub from: String,
+    /// End of the tinted period, "HH:MM"; may wrap past midnight
+    pub to: String,
+    /// 0.0 (none) - 1.0 (strongest)
+    pub strength: f32,
+}
+
+impl Default for Tint {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            from: "20:00".to_string(),
+            to: "07:00".to_string(),
+            strength: 0.3,
+        }
+    }
+}
+
+impl Tint {
+    /// Strength to apply at `time`, None outside the tinted period
+    pub fn strength_at(&self, time: NaiveTime) -> Option<f32> {
+        if !self.enabled || self.strength <= 0.0 {
+            return None;
+        }
+        let from = NaiveTime::parse_from_str(&self.from, "%H:%M").ok()?;
+        let to = NaiveTime::parse_from_str(&self.to, "%H:%M").ok()?;
+        let active = if from <= to {
+            from <= time && time < to
+        } else {
+            time >= from || time < to
+        };
+        active.then_some(self.strength.min(1.0))
+    }
+}
+
+/// Prefer darker wallpapers in a dim room and brighter ones in daylight,
+/// going by a laptop's ambient light sensor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AmbientLight {
+    pub enabled: bool,
+    /// At or below this many lux the room counts as dim
+    pub dim_lux: f64,
+    /// At or above this many lux it counts as daylight
+    pub bright_lux: f64,
+    /// Chance (0.0 - 1.0) of picking from images matching the light
+    pub bias: f64,
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dim_lux: 50.0,
+            bright_lux: 1000.0,
+            bias: 0.75,
+        }
+    }
+}
+
+/// What to do while the logind session is locked. Read when the server
+/// starts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionLock {
+    pub enabled: bool,
+    /// Shown while locked; the previous wallpaper comes back on unlock
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallpaper: Option<PathBuf>,
+    /// No automatic switches while locked
+    pub pause: bool,
+}
+
+impl Default for SessionLock {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            wallpaper: None,
+            pause: true,
+        }
+    }
+}
+
+/// Publish the current wallpaper for greeters and lock screens (greetd,
+/// hyprlock) that can't ask the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Handoff {
+    pub enabled: bool,
+    /// Text file holding the absolute path of the wallpaper
+    pub file: PathBuf,
+    /// Symlink to the wallpaper, for programs that take an image path
+    pub link: PathBuf,
+}
+
+impl Default for Handoff {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            file: PathBuf::from("~/.cache/swww-manager/wallpaper"),
+            link: PathBuf::from("~/.cache/swww-manager/wallpaper-image"),
+        }
+    }
+}
+
+/// `value` as a TOML item for [`Config::save_key`], `None` for an unset
+/// optional value. Tables are implicit, so ones holding only subtables get
+/// no header of their own.
+fn toml_item(value: &serde_json::Value) -> Option<toml_edit::Item> {
+    use serde_json::Value;
+    match value {
+        Value::Null => None,
+        Value::Object(map) => {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            for (key, value) in map {
+                if let Some(item) = toml_item(value) {
+                    table.insert(key, item);
+                }
+            }
+            Some(toml_edit::Item::Table(table))
+        }
+        Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+            let tables = items.iter().filter_map(|i| toml_item(i)?.into_table().ok());
+            Some(toml_edit::Item::ArrayOfTables(tables.collect()))
+        }
+        _ => toml_value(value).map(toml_edit::Item::Value),
+    }
+}
+
+fn toml_value(value: &serde_json::Value) -> Option<toml_edit::Value> {
+    use serde_json::Value;
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64()?.into(),
+        },
+        Value::String(s) => s.as_str().into(),
+        Value::Array(items) => items.iter().filter_map(toml_value).collect::<toml_edit::Array>().into(),
+        Value::Object(map) => map
+            .iter()
+            .filter_map(|(k, v)| Some((k.as_str(), toml_value(v)?)))
+            .collect::<toml_edit::InlineTable>()
+            .into(),
+    })
+}
+
+/// Running `swww clear-cache`, as swww keeps a copy of every image it has
+/// shown. The server restarts maintenance when this changes on reload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Maintenance {
+    pub enabled: bool,
+    /// Hours between clears (0 = only when over `max_cache_mb`)
+    pub clear_every_hours: u64,
+    /// Clear once swww's cache directory is larger than this (0 = no limit)
+    pub max_cache_mb: u64,
+    /// Minutes between checks of the cache size and the schedule
+    pub check_minutes: u64,
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            clear_every_hours: 168,
+            max_cache_mb: 500,
+            check_minutes: 30,
+        }
+    }
+}
+
+/// Avoid wallpapers that are busy where desktop widgets sit, so clocks and
+/// conky stay readable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Readability {
+    pub areas: Vec<Area>,
+    /// Highest edge strength (0.0 - 1.0) allowed in any area
+    pub max_clutter: f32,
+}
+
+impl Default for Readability {
+    fn default() -> Self {
+        Self {
+            areas: Vec::new(),
+            max_clutter: 0.15,
+        }
+    }
+}
+
+/// Bumped when the way clutter is measured changes
+const CLUTTER_VERSION: u32 = 2;
+
+impl Readability {
+    pub fn enabled(&self) -> bool {
+        !self.areas.is_empty()
+    }
+
+    /// Identifies the areas and how they are measured, so images are
+    /// measured again when either changes
+    pub fn key(&self) -> u64 {
+        let areas = self.areas.iter().flat_map(|a| [a.x, a.y, a.width, a.height]).flat_map(f32::to_le_bytes);
+        fnv1a(CLUTTER_VERSION.to_le_bytes().into_iter().chain(areas))
+    }
+
+    /// Whether an image is calm enough in the widget areas, `None` when it
+    /// wasn't measured for the current areas yet
+    pub fn verdict(&self, stats: &ImageStats) -> Option<bool> {
+        if !self.enabled() {
+            return Some(true);
+        }
+        stats.clutter.filter(|c| c.areas == self.key()).map(|c| c.value <= self.max_clutter)
+    }
+}
+
+/// Rectangle as fractions (0.0 - 1.0) of the image's width and height,
+/// measured from the top left
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Area {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Give every Hyprland workspace its own wallpaper, shown on whichever
+/// monitor the workspace is active on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerWorkspace {
+    pub enabled: bool,
+    /// Re-apply a workspace's wallpaper on the monitor it is moved to
+    pub follow_moves: bool,
+}
+
+impl Default for PerWorkspace {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            follow_moves: true,
+        }
+    }
+}
+
+/// Restrict the server's filesystem access and syscalls once it has
+/// started (Linux Landlock and seccomp)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sandbox {
+    pub enabled: bool,
+    /// Extra paths the server may read and write besides the config,
+    /// cache, state, runtime and wallpaper directories
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allow_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorDetection {
+    pub enabled: bool,
+    /// Profile to use when no profile matches the connected monitors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_profile: Option<String>,
+    /// Quiet period after the last hotplug event before detecting
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+    /// Maximum time spent waiting for the monitor list to settle
+    #[serde(default = "default_stabilize_ms")]
+    pub stabilize_ms: u64,
+    /// Identical consecutive readings required to consider it settled
+    #[serde(default = "default_samples")]
+    pub samples: usize,
+    /// When the profile stays the same, only set a wallpaper on newly
+    /// connected outputs instead of re-transitioning every display
+    #[serde(default)]
+    pub minimal_disruption: bool,
+    /// Give up on a Hyprland IPC request after this long
+    #[serde(default = "default_ipc_timeout_ms")]
+    pub ipc_timeout_ms: u64,
+    /// Create and save a profile (`auto_<monitors>`) for monitor sets that
+    /// only the wildcard or fallback profile matches
+    #[serde(default)]
+    pub auto_profile: bool,
+    /// Ask with a desktop notification before switching to a detected
+    /// profile instead of switching right away
+    #[serde(default)]
+    pub ask_before_switch: bool,
+}
+
+fn default_cache_max_age_secs() -> u64 {
+    60
+}
+
+fn default_swww_timeout_secs() -> u64 {
+    6
+}
+
+fn default_clipboard_dir() -> PathBuf {
+    PathBuf::from("~/Pictures/Wallpapers/clipboard")
+}
+
+fn default_debounce_ms() -> u64 {
+    900
+}
+
+fn default_stabilize_ms() -> u64 {
+    1200
+}
+
+fn default_samples() -> usize {
+    3
+}
+
+fn default_ipc_timeout_ms() -> u64 {
+    2000
+}
+
+impl Default for MonitorDetection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fallback_profile: None,
+            debounce_ms: default_debounce_ms(),
+            stabilize_ms: default_stabilize_ms(),
+            samples: default_samples(),
+            minimal_disruption: false,
+            ipc_timeout_ms: default_ipc_timeout_ms(),
+            auto_profile: false,
+            ask_before_switch: false,
+        }
+    }
+}
+
+/// One resolved setting and where its value came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectiveValue {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub source: String,
+}
+
+impl Config {
+    pub fn default_path() -> Option<String> {
+        dirs::config_dir().map(|p| {
+            p.join("swww-manager/config.toml")
+                .to_string_lossy()
+                .to_string()
+        })
+    }
+
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let path = path
+            .map(PathBuf::from)
+            .or_else(|| dirs::config_dir().map(|p| p.join("swww-manager/config.toml")))
+            .context("Could not determine config path")?;
+
+        if !path.exists() {
+            info!("Config file not found, using defaults");
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config: {:?}", path))?;
+        
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config: {:?}", path))
+    }
+
+    pub fn save(&self, path: Option<&Path>) -> Result<()> {
+        let path = path
+            .map(PathBuf::from)
+            .or_else(|| dirs::config_dir().map(|p| p.join("swww-manager/config.toml")))
+            .context("Could not determine config path")?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = toml::to_string_pretty(self)?;
+        fs::write(&path, content)?;
+        info!("Config saved to {:?}", path);
+        Ok(())
+    }
+
+    /// Write this config's value of the dotted `key` into the file, leaving
+    /// the rest of it, comments included, as it is. Without a file the whole
+    /// config is saved.
+    pub fn save_key(&self, path: Option<&Path>, key: &str) -> Result<()> {
+        let path = path
+            .map(PathBuf::from)
+            .or_else(|| dirs::config_dir().map(|p| p.join("swww-manager/config.toml")))
+            .context("Could not determine config path")?;
+        if !path.exists() {
+            return self.save(Some(&path));
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config: {:?}", path))?;
+        let mut doc: toml_edit::DocumentMut = content.parse()
+            .with_context(|| format!("Failed to parse config: {:?}", path))?;
+        let value = self.get_key(key)?;
+
+        let (parents, leaf) = key.rsplit_once('.').unwrap_or(("", key));
+        let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+        for part in parents.split('.').filter(|p| !p.is_empty()) {
+            table = table
+                .entry(part)
+                .or_insert_with(|| {
+                    let mut section = toml_edit::Table::new();
+                    section.set_implicit(true);
+                    section.into()
+                })
+                .as_table_like_mut()
+                .with_context(|| format!("{} is not a section in {:?}", part, path))?;
+        }
+        match toml_item(&value) {
+            Some(mut item) => {
+                // Keep the old value's surrounding whitespace and comment
+                if let (Some(old), Some(new)) = (table.get(leaf).and_then(|i| i.as_value()), item.as_value_mut()) {
+                    *new.decor_mut() = old.decor().clone();
+                }
+                table.insert(leaf, item);
+            }
+            None => {
+                table.remove(leaf);
+            }
+        }
+
+        fs::write(&path, doc.to_string())?;
+        info!("Config saved to {:?}", path);
+        Ok(())
+    }
+
+    /// Every setting of the loaded config, annotated with the file that set
+    /// it or `default` when it was filled in
+    pub fn effective(path: Option<&str>) -> Result<Vec<EffectiveValue>> {
+        let path = path
+            .map(PathBuf::from)
+            .or_else(|| dirs::config_dir().map(|p| p.join("swww-manager/config.toml")))
+            .context("Could not determine config path")?;
+
+        let config = Self::load(path.to_str())?;
+        let file: Option<toml::Value> = match fs::read_to_string(&path) {
+            Ok(content) => Some(toml::from_str(&content)
+                .with_context(|| format!("Failed to parse config: {:?}", path))?),
+            Err(_) => None,
+        };
+
+        let mut values = Vec::new();
+        let source = path.display().to_string();
+        flatten("", serde_json::to_value(&config)?, file.as_ref(), &source, &mut values);
+        Ok(values)
+    }
+
+    /// Value at a dotted key such as `auto_switch.interval`; the whole
+    /// config when `key` is empty
+    pub fn get_key(&self, key: &str) -> Result<serde_json::Value> {
+        let mut value = serde_json::to_value(self)?;
+        for part in key.split('.').filter(|p| !p.is_empty()) {
+            value = value
+                .get_mut(part)
+                .map(serde_json::Value::take)
+                .with_context(|| format!("Unknown config key: {}", key))?;
+        }
+        Ok(value)
+    }
+
+    /// Set an existing dotted key from its string form. Strings are taken
+    /// verbatim, anything else is parsed as JSON. The result must still
+    /// deserialize and validate.
+    pub fn set_key(&mut self, key: &str, raw: &str) -> Result<()> {
+        let unknown = || format!("Unknown config key: {}", key);
+        let (parent, leaf) = key.rsplit_once('.').unwrap_or(("", key));
+
+        let mut root = serde_json::to_value(&*self)?;
+        let mut section = &mut root;
+        for part in parent.split('.').filter(|p| !p.is_empty()) {
+            section = section.get_mut(part).with_context(unknown)?;
+        }
+        // Unset optional keys are not serialized at all
+        let slot = section
+            .as_object_mut()
+            .with_context(unknown)?
+            .entry(leaf)
+            .or_insert(serde_json::Value::Null);
+        if slot.is_object() {
+            anyhow::bail!("{} is a section, set one of its keys instead", key);
+        }
+
+        *slot = match slot {
+            serde_json::Value::String(_) => serde_json::Value::String(raw.to_string()),
+            _ => serde_json::from_str(raw)
+                .unwrap_or_else(|_| serde_json::Value::String(raw.to_string())),
+        };
+
+        let updated: Config = serde_json::from_value(root)
+            .with_context(|| format!("Invalid value for {}: {}", key, raw))?;
+        updated.get_key(key).with_context(unknown)?;
+        updated.validate()?;
+        *self = updated;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !self.profiles.is_empty() && !self.profiles.contains_key(&self.current_profile) {
+            anyhow::bail!("Profile not found: {}", self.current_profile);
+        }
+        if let Some(fallback) = &self.monitor_detection.fallback_profile
+            && !self.profiles.contains_key(fallback) {
+                anyhow::bail!("Fallback profile not found: {}", fallback);
+            }
+        for (alias, rule) in &self.monitor_aliases {
+            if let Some((_, pattern)) = rule.split_once(':')
+                && let Err(e) = regex::Regex::new(pattern) {
+                    anyhow::bail!("Monitor alias {}: invalid pattern: {}", alias, e);
+                }
+        }
+        if let Some(addr) = &self.listen_tcp {
+            addr.parse::<std::net::SocketAddr>()
+                .with_context(|| format!("Invalid listen_tcp address: {}", addr))?;
+            if self.remote_token.as_deref().unwrap_or("").len() < 16 {
+                anyhow::bail!("listen_tcp requires a remote_token of at least 16 characters");
+            }
+        }
+        for bound in [&self.tint.from, &self.tint.to] {
+            if NaiveTime::parse_from_str(bound, "%H:%M").is_err() {
+                anyhow::bail!("tint: invalid time {:?}, expected HH:MM", bound);
+            }
+        }
+        if !(0.0..=1.0).contains(&self.tint.strength) {
+            anyhow::bail!("tint: strength must be between 0.0 and 1.0");
+        }
+        let light = &self.ambient_light;
+        if light.dim_lux < 0.0 || light.dim_lux >= light.bright_lux {
+            anyhow::bail!("ambient_light: dim_lux must be at least 0 and below bright_lux");
+        }
+        if !(0.0..=1.0).contains(&light.bias) {
+            anyhow::bail!("ambient_light: bias must be between 0.0 and 1.0");
+        }
+        for area in &self.readability.areas {
+            let inside = |pos: f32, len: f32| pos >= 0.0 && len > 0.0 && pos + len <= 1.0;
+            if !inside(area.x, area.width) || !inside(area.y, area.height) {
+                anyhow::bail!("readability: area {:?} must lie within 0.0 - 1.0", area);
+            }
+        }
+        let fps = &self.refresh_rate_fps;
+        if fps.min == 0 || fps.min > fps.max {
+            anyhow::bail!("refresh_rate_fps: min must be at least 1 and not above max ({} - {})", fps.min, fps.max);
+        }
+        for (name, profile) in &self.profiles {
+            if let Some(preset) = &profile.transition_preset
+                && self.transition_preset(preset).is_none() {
+                    anyhow::bail!("Profile {}: unknown transition preset {}", name, preset);
+                }
+            if profile.filters_content() && !cfg!(feature = "content-filters") {
+                anyhow::bail!("Profile {}: max_text/skip_faces need a build with the \"content-filters\" feature", name);
+            }
+            for (used, program) in [(profile.max_text.is_some(), "tesseract"), (profile.skip_faces, "facedetect")] {
+                if used && !installed(program) {
+                    anyhow::bail!("Profile {}: {} is not installed", name, program);
+                }
+            }
+            let limits = [
+                profile.max_luminance, profile.min_luminance, profile.min_contrast, profile.max_busyness, profile.max_text,
+            ];
+            if limits.into_iter().flatten().any(|v| !(0.0..=1.0).contains(&v)) {
+                anyhow::bail!("Profile {}: image filters must be between 0.0 and 1.0", name);
+            }
+            for color in &profile.palette {
+                crate::generate::parse_color(color).with_context(|| format!("Profile {}: palette", name))?;
+            }
+        }
+        for season in &self.seasons {
+            for bound in [&season.from, &season.to] {
+                if parse_month_day(bound).is_none() {
+                    anyhow::bail!("Season {}: invalid date {:?}, expected MM-DD", season.name, bound);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn active_seasons(&self, date: NaiveDate) -> impl Iterator<Item = &SeasonRule> {
+        self.seasons.iter().filter(move |s| s.is_active(date))
+    }
+
+    /// `profile` with the directories of today's seasons merged in
+    pub fn apply_seasons(&self, profile: &Profile) -> Profile {
+        let today = chrono::Local::now().date_naive();
+        let mut profile = profile.clone();
+        for season in self.active_seasons(today) {
+            for dir in &season.dirs {
+                if !profile.wallpaper_dirs.contains(dir) {
+                    profile.wallpaper_dirs.push(dir.clone());
+                }
+            }
+        }
+        profile
+    }
+
+    /// Preset `name` from the config, falling back to the built-in ones
+    pub fn transition_preset(&self, name: &str) -> Option<TransitionPreset> {
+        if let Some(preset) = self.transition_presets.get(name) {
+            return Some(preset.clone());
+        }
+        TRANSITION_PRESETS
+            .iter()
+            .find(|(builtin, ..)| *builtin == name)
+            .map(|&(_, transition, duration, fps, bezier)| TransitionPreset {
+                transition: transition.to_string(),
+                duration,
+                fps: Some(fps),
+                bezier: Some(bezier.to_string()),
+            })
+    }
+
+    pub fn transition_preset_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = TRANSITION_PRESETS
+            .iter()
+            .map(|(name, ..)| name.to_string())
+            .chain(self.transition_presets.keys().cloned())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// This config with `mode` in place of the configured switch mode
+    pub fn with_switch_mode(&self, mode: Option<SwitchMode>) -> Cow<'_, Config> {
+        match mode {
+            Some(mode) => {
+                let mut config = self.clone();
+                config.auto_switch.mode = mode;
+                Cow::Owned(config)
+            }
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    /// Overwrite the transition settings of `profile` with preset `name`
+    pub fn apply_transition_preset(&self, profile: &mut Profile, name: &str) -> Result<()> {
+        let preset = self.transition_preset(name).with_context(|| {
+            format!("Unknown transition preset: {} (available: {})", name, self.transition_preset_names().join(", "))
+        })?;
+        profile.transition = preset.transition;
+        profile.transition_duration = preset.duration;
+        profile.transition_fps = preset.fps;
+        profile.transition_bezier = preset.bezier;
+        Ok(())
+    }
+
+    pub fn generate_example() -> Result<()> {
+        let config = Self::default();
+        let path = dirs::config_dir()
+            .map(|p| p.join("swww-manager/config.toml"))
+            .context("Could not determine config path")?;
+        
+        config.save(Some(&path))?;
+        
+        println!("\nExample configuration:");
+        println!("{}", toml::to_string_pretty(&config)?);
+        
+        Ok(())
+    }
+}
+
+fn flatten(
+    prefix: &str,
+    value: serde_json::Value,
+    file: Option<&toml::Value>,
+    source: &str,
+    out: &mut Vec<EffectiveValue>,
+) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                let key = if prefix.is_empty() { k.clone() } else { format!("{}.{}", prefix, k) };
+                flatten(&key, v, file.and_then(|f| f.get(&k)), source, out);
+            }
+        }
+        // Unset optional values
+        serde_json::Value::Null => {}
+        value => out.push(EffectiveValue {
+            key: prefix.to_string(),
+            value,
+            source: if file.is_some() { source.to_string() } else { "default".to_string() },
+        }),
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut profiles = HashMap::new();
+        
+        // Default profile
+        profiles.insert(
+            "default".to_string(),
+            Profile {
+                monitors: vec!["*".to_string()],
+                wallpaper_dirs: vec![
+                    dirs::home_dir()
+                        .unwrap_or_default()
+                        .join("Pictures/Wallpapers")
+                ],
+                transition: "wipe".to_string(),
+                transition_duration: 2,
+                ..Default::default()
+            },
+        );
+
+        // Example dual monitor profile
+        profiles.insert(
+            "dual_monitor".to_string(),
+            Profile {
+                monitors: vec!["DP-1".to_string(), "HDMI-A-1".to_string()],
+                wallpaper_dirs: vec![
+                    dirs::home_dir()
+                        .unwrap_or_default()
+                        .join("Pictures/Wallpapers/Dual")
+                ],
+                transition: "fade".to_string(),
+                transition_duration: 3,
+                ..Default::default()
+            },
+        );
+
+        // Example laptop profile
+        profiles.insert(
+            "laptop".to_string(),
+            Profile {
+                monitors: vec!["eDP-1".to_string()],
+                wallpaper_dirs: vec![
+                    dirs::home_dir()
+                        .unwrap_or_default()
+                        .join("Pictures/Wallpapers/Laptop")
+                ],
+                transition: "simple".to_string(),
+                transition_duration: 1,
+                ..Default::default()
+            },
+        );
+
+        Self {
+            profiles,
+            auto_switch: AutoSwitch {
+                enabled: false,
+                interval: 300,
+                mode: SwitchMode::Random,
+                prefer_new: false,
+                preload: true,
+                per_monitor_offset: false,
+            },
+            monitor_detection: MonitorDetection::default(),
+            current_profile: "default".to_string(),
+            switch_cooldown_ms: 0,
+            cache_max_age_secs: default_cache_max_age_secs(),
+            swww_timeout_secs: default_swww_timeout_secs(),
+            seasons: Vec::new(),
+            weather: WeatherConfig::default(),
+            startup: Startup::default(),
+            per_workspace: PerWorkspace::default(),
+            sandbox: Sandbox::default(),
+            monitor_aliases: HashMap::new(),
+            listen_tcp: None,
+            remote_token: None,
+            transition_presets: HashMap::new(),
+            refresh_rate_fps: RefreshRateFps::default(),
+            vrr_transition: None,
+            tint: Tint::default(),
+            ambient_light: AmbientLight::default(),
+            readability: Readability::default(),
+            handoff: Handoff::default(),
+            lock: SessionLock::default(),
+            maintenance: Maintenance::default(),
+            clipboard_dir: default_clipboard_dir(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn season(from: &str, to: &str) -> SeasonRule {
+        SeasonRule {
+            name: "test".to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            dirs: Vec::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_season_ranges() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+
+        let october = season("10-01", "10-31");
+        assert!(october.is_active(date(10, 15)));
+        assert!(!october.is_active(date(11, 1)));
+
+        let winter = season("12-01", "02-28");
+        assert!(winter.is_active(date(12, 24)));
+        assert!(winter.is_active(date(1, 10)));
+        assert!(!winter.is_active(date(3, 1)));
+
+        assert!(!season("13-01", "12-31").is_active(date(6, 1)));
+    }
+
+    #[test]
+    fn test_effective_sources() {
+        let file: toml::Value = toml::from_str("[auto_switch]\ninterval = 60\n").unwrap();
+        let value = serde_json::json!({ "auto_switch": { "interval": 60, "enabled": false } });
+        let mut out = Vec::new();
+        flatten("", value, Some(&file), "config.toml", &mut out);
+
+        let source = |key: &str| out.iter().find(|v| v.key == key).unwrap().source.clone();
+        assert_eq!(source("auto_switch.interval"), "config.toml");
+        assert_eq!(source("auto_switch.enabled"), "default");
+    }
+
+    #[test]
+    fn test_tint_period() {
+        let at = |s| NaiveTime::parse_from_str(s, "%H:%M").unwrap();
+        let mut tint = Tint { enabled: true, ..Default::default() };
+        assert_eq!(tint.strength_at(at("21:30")), Some(0.3));
+        assert_eq!(tint.strength_at(at("06:59")), Some(0.3));
+        assert_eq!(tint.strength_at(at("07:00")), None);
+        assert_eq!(tint.strength_at(at("12:00")), None);
+
+        tint.from = "13:00".to_string();
+        tint.to = "15:00".to_string();
+        assert_eq!(tint.strength_at(at("14:00")), Some(0.3));
+        assert_eq!(tint.strength_at(at("21:30")), None);
+    }
+
+    #[test]
+    fn test_transition_presets() {
+        let mut config = Config::default();
+        config.transition_presets.insert("smooth".to_string(), TransitionPreset {
+            transition: "outer".to_string(),
+            duration: 3,
+            fps: None,
+            bezier: None,
+        });
+
+        let mut profile = Profile::default();
+        config.apply_transition_preset(&mut profile, "cinematic").unwrap();
+        assert_eq!((profile.transition.as_str(), profile.transition_duration), ("grow", 4));
+        assert_eq!(profile.transition_fps, Some(60));
+
+        config.apply_transition_preset(&mut profile, "smooth").unwrap();
+        assert_eq!(profile.transition, "outer");
+        assert_eq!(profile.transition_bezier, None);
+        assert!(config.apply_transition_preset(&mut profile, "bouncy").is_err());
+
+        config.profiles.get_mut("default").unwrap().transition_preset = Some("bouncy".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_set_key() {
+        let mut config = Config::default();
+        let name = config.current_profile.clone();
+
+        config.set_key("auto_switch.interval", "600").unwrap();
+        assert_eq!(config.auto_switch.interval, 600);
+
+        config.set_key(&format!("profiles.{}.transition", name), "fade").unwrap();
+        assert_eq!(config.profiles[&name].transition, "fade");
+
+        assert!(config.set_key("auto_switch.interval", "soon").is_err());
+        assert!(config.set_key("auto_switch.nope", "1").is_err());
+        assert!(config.set_key("current_profile", "missing").is_err());
+
+        config.set_key("monitor_detection.fallback_profile", &name).unwrap();
+        assert_eq!(config.monitor_detection.fallback_profile.as_deref(), Some(name.as_str()));
+        assert_eq!(config.get_key("auto_switch.interval").unwrap(), 600);
+    }
+
+    #[test]
+    fn test_save_key_keeps_comments() {
+        let path = std::env::temp_dir().join(format!("swww-manager-config-{}.toml", std::process::id()));
+        let mut config = Config::default();
+        fs::write(&path, "# My settings\n[auto_switch]\ninterval = 300 # five minutes\n").unwrap();
+
+        config.auto_switch.interval = 600;
+        config.save_key(Some(&path), "auto_switch.interval").unwrap();
+        config.monitor_detection.fallback_profile = Some(config.current_profile.clone());
+        config.save_key(Some(&path), "monitor_detection.fallback_profile").unwrap();
+        let profile = config.profiles[&config.current_profile].clone();
+        let profile_transition = profile.transition.clone();
+        config.profiles.insert("auto_DP-1".into(), profile);
+        config.save_key(Some(&path), "profiles.auto_DP-1").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(content.starts_with("# My settings\n"));
+        assert!(content.contains("interval = 600 # five minutes"));
+        let saved: toml::Value = toml::from_str(&content).unwrap();
+        assert_eq!(saved["monitor_detection"]["fallback_profile"].as_str(), Some(config.current_profile.as_str()));
+        assert_eq!(saved["profiles"]["auto_DP-1"]["transition"].as_str(), Some(profile_transition.as_str()));
+    }
+}
@@ -0,0 +1,119 @@
+//! Text and face detection behind the `max_text` and `skip_faces` profile
+//! filters, done by external programs (tesseract, facedetect) only for
+//! images a profile using them rotates through, and cached in the index.
+
+use crate::convert::{in_batches, ToolError};
+use crate::index::WallpaperIndex;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "content-filters")]
+use {anyhow::Context, tokio::process::Command, tokio::time::{timeout, Duration}};
+
+/// Lowest tesseract confidence (0 - 100) for a word to count as text
+#[cfg(any(feature = "content-filters", test))]
+const MIN_CONFIDENCE: f32 = 60.0;
+
+/// Detect text and/or faces in the images among `files` not scanned for
+/// them yet. Returns how many were scanned.
+pub async fn scan(files: &[PathBuf], text: bool, faces: bool) -> usize {
+    let index = WallpaperIndex::load();
+    let pending_text: Vec<&PathBuf> = files
+        .iter()
+        .filter(|f| text && f.is_file() && index.text(f).is_none())
+        .collect();
+    let pending_faces: Vec<&PathBuf> = files
+        .iter()
+        .filter(|f| faces && f.is_file() && index.faces(f).is_none())
+        .collect();
+    drop(index);
+
+    in_batches(&pending_text, text_coverage, |index, file, coverage| index.set_text(file, coverage)).await
+        + in_batches(&pending_faces, face_count, |index, file, count| index.set_faces(file, count)).await
+}
+
+#[cfg(not(feature = "content-filters"))]
+async fn text_coverage(_path: &Path) -> Result<f32> {
+    Err(ToolError::Disabled("text detection", "content-filters").into())
+}
+
+/// Share of the image covered by words tesseract is confident about
+#[cfg(feature = "content-filters")]
+async fn text_coverage(path: &Path) -> Result<f32> {
+    let cmd = Command::new("tesseract").arg(path).args(["stdout", "tsv"]).output();
+    let output = match timeout(Duration::from_secs(120), cmd).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => return Err(ToolError::NotInstalled("tesseract").into()),
+        Ok(Err(e)) => return Err(e).context("Failed to execute tesseract"),
+        Err(_) => anyhow::bail!("tesseract timed out"),
+    };
+    if !output.status.success() {
+        anyhow::bail!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    parse_tsv(&String::from_utf8_lossy(&output.stdout)).context("Unexpected tesseract output")
+}
+
+/// Sum the boxes of confident words (level 5) relative to the page box
+/// (level 1) in tesseract's TSV output
+#[cfg(any(feature = "content-filters", test))]
+fn parse_tsv(tsv: &str) -> Option<f32> {
+    let mut page = None;
+    let mut words = 0.0;
+    for line in tsv.lines().skip(1) {
+        // level page block par line word left top width height conf text
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 12 {
+            continue;
+        }
+        let area = fields[8].parse::<f32>().ok()? * fields[9].parse::<f32>().ok()?;
+        match fields[0] {
+            "1" => page = Some(area),
+            "5" if !fields[11].trim().is_empty() && fields[10].parse::<f32>().ok()? >= MIN_CONFIDENCE => {
+                words += area
+            }
+            _ => {}
+        }
+    }
+    page.filter(|p| *p > 0.0).map(|p| (words / p).min(1.0))
+}
+
+#[cfg(not(feature = "content-filters"))]
+async fn face_count(_path: &Path) -> Result<u32> {
+    Err(ToolError::Disabled("face detection", "content-filters").into())
+}
+
+/// Faces found by facedetect, which prints one "x y w h" line per face
+#[cfg(feature = "content-filters")]
+async fn face_count(path: &Path) -> Result<u32> {
+    let cmd = Command::new("facedetect").arg(path).output();
+    let output = match timeout(Duration::from_secs(120), cmd).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => return Err(ToolError::NotInstalled("facedetect").into()),
+        Ok(Err(e)) => return Err(e).context("Failed to execute facedetect"),
+        Err(_) => anyhow::bail!("facedetect timed out"),
+    };
+    // Exit status 2 means no faces
+    if !output.status.success() && output.status.code() != Some(2) {
+        anyhow::bail!("facedetect failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let faces = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.split_whitespace().count() == 4)
+        .count();
+    Ok(faces as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_coverage() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   1\t1\t0\t0\t0\t0\t0\t0\t100\t100\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t10\t10\t20\t10\t91.5\tHello\n\
+                   5\t1\t1\t1\t1\t2\t40\t10\t20\t10\t12.0\t~~\n\
+                   5\t1\t1\t1\t1\t3\t70\t10\t20\t10\t95.0\t \n";
+        assert_eq!(parse_tsv(tsv), Some(0.02));
+        assert_eq!(parse_tsv("level\n"), None);
+    }
+}
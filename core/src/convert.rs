@@ -0,0 +1,352 @@
+//! Converted copies of wallpapers, made with ImageMagick right before an
+//! image is handed to swww and kept in `~/.cache/swww-manager/converted`.
+//! A copy is reused as long as the source file and the conversion stay the
+//! same; when ImageMagick fails the original is used.
+
+use crate::config::{Area, Profile, Readability};
+use crate::gc;
+use crate::index::{Clutter, ImageStats, WallpaperIndex};
+use crate::wallpaper::fnv1a;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use thiserror::Error;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+use tracing::{debug, warn};
+
+/// Bring 16-bit and HDR images into the 8-bit sRGB range swww shows
+const TONEMAP: &[&str] = &["-colorspace", "sRGB", "-auto-level", "-depth", "8"];
+
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("swww-manager/converted"))
+}
+
+/// Share of blue and green taken away at full tint strength
+const TINT_BLUE: f32 = 0.6;
+const TINT_GREEN: f32 = 0.25;
+
+/// The file swww should show for `path`: a converted copy when one of the
+/// profile's conversions or a warm `tint` applies to it, otherwise `path`
+/// itself. Animated GIFs are always left alone.
+pub async fn prepare(path: &Path, profile: &Profile, tint: Option<f32>) -> PathBuf {
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("gif")) {
+        return path.to_path_buf();
+    }
+
+    let mut args: Vec<String> = Vec::new();
+    if profile.tonemap_hdr && is_hdr(path) {
+        args.extend(TONEMAP.iter().map(|a| a.to_string()));
+    }
+    if let Some(strength) = tint {
+        args.extend(tint_args(strength));
+    }
+    if args.is_empty() {
+        return path.to_path_buf();
+    }
+
+    match convert(path, &args).await {
+        Ok(copy) => copy,
+        Err(e) => {
+            warn!("Failed to convert {:?}, using the original: {}", path, e);
+            path.to_path_buf()
+        }
+    }
+}
+
+/// Scale blue and green down, like a night light lowering the color
+/// temperature
+fn tint_args(strength: f32) -> Vec<String> {
+    let blue = format!("{:.2}", 1.0 - strength * TINT_BLUE);
+    let green = format!("{:.2}", 1.0 - strength * TINT_GREEN);
+    ["-channel", "B", "-evaluate", "multiply", &blue, "-channel", "G", "-evaluate", "multiply", &green, "+channel"]
+        .map(String::from)
+        .to_vec()
+}
+
+/// 16-bit PNGs and PNGs with HDR signalling (a `cICP` chunk)
+pub fn is_hdr(path: &Path) -> bool {
+    let mut head = Vec::new();
+    match fs::File::open(path) {
+        Ok(file) => file.take(64 * 1024).read_to_end(&mut head).is_ok() && png_is_hdr(&head),
+        Err(_) => false,
+    }
+}
+
+fn png_is_hdr(data: &[u8]) -> bool {
+    if !data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return false;
+    }
+    let mut pos = 8;
+    while let Some(header) = data.get(pos..pos + 8) {
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        match &header[4..] {
+            // Bit depth follows the 4-byte width and height
+            b"IHDR" if data.get(pos + 16) == Some(&16) => return true,
+            b"cICP" => return true,
+            // Color chunks all come before the image data
+            b"IDAT" | b"IEND" => return false,
+            _ => {}
+        }
+        pos += len + 12;
+    }
+    false
+}
+
+async fn convert(path: &Path, args: &[String]) -> Result<PathBuf> {
+    let meta = fs::metadata(path)?;
+    let modified = meta.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let key = format!("{}|{}|{}|{}", path.display(), meta.len(), modified, args.join(" "));
+
+    let dir = cache_dir().context("Could not determine cache path")?;
+    let target = dir.join(format!("{:016x}.png", fnv1a(key.bytes())));
+    if target.exists() {
+        return Ok(target);
+    }
+    fs::create_dir_all(&dir)?;
+
+    let partial = target.with_extension("part.png");
+    let mut full = vec![path.to_string_lossy().to_string()];
+    full.extend(args.iter().cloned());
+    full.push(partial.to_string_lossy().to_string());
+    let output = magick(&full).await?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&partial);
+        anyhow::bail!("ImageMagick failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    fs::rename(&partial, &target)?;
+    gc::record_source(&target, path)?;
+    debug!("Converted {:?} to {:?}", path, target);
+    Ok(target)
+}
+
+/// Longer side of the grayscale copy images are measured on
+const MEASURE_SIZE: f32 = 128.0;
+
+/// Brightness, contrast and busy-ness of the first frame of `path`,
+/// measured on a small grayscale copy and its edges, plus the clutter in
+/// the widget areas of `readability`
+pub async fn measure(path: &Path, readability: &Readability) -> Result<ImageStats> {
+    let frame = format!("{}[0]", path.display());
+    let (width, height) = measure_size(&frame).await?;
+    let size = format!("{}x{}!", width, height);
+    let mut args: Vec<String> = [
+        &frame, "-colorspace", "Gray", "-resize", &size,
+        "(", "+clone", "-edge", "1", ")",
+    ]
+    .map(String::from)
+    .into();
+    for area in &readability.areas {
+        args.extend(["(", "-clone", "1", "-crop", &crop_geometry(area, width, height), "+repage", ")"].map(String::from));
+    }
+    args.extend(["-format", "%[fx:mean] %[fx:standard_deviation]\n", "info:"].map(String::from));
+
+    let output = magick(&args).await?;
+    if !output.status.success() {
+        anyhow::bail!("ImageMagick failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut stats = parse_stats(&stdout)
+        .with_context(|| format!("Unexpected ImageMagick output for {:?}: {}", path, stdout.trim()))?;
+    if readability.enabled() {
+        stats.clutter = Some(Clutter { areas: readability.key(), value: parse_clutter(&stdout) });
+    }
+    Ok(stats)
+}
+
+/// Size of the copy `frame` is measured on: no more than [`MEASURE_SIZE`]
+/// on its longer side and with its aspect ratio, so widget areas cover the
+/// same part of it as on screen
+async fn measure_size(frame: &str) -> Result<(u32, u32)> {
+    let args = ["-ping", frame, "-format", "%w %h", "info:"].map(String::from);
+    let output = magick(&args).await?;
+    if !output.status.success() {
+        anyhow::bail!("ImageMagick failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut dims = stdout.split_whitespace().map(|v| v.parse::<f32>().ok());
+    let (Some(Some(width)), Some(Some(height))) = (dims.next(), dims.next()) else {
+        anyhow::bail!("Unexpected ImageMagick output for {}: {}", frame, stdout.trim());
+    };
+    Ok(fit(width, height))
+}
+
+/// `width` x `height` scaled to [`MEASURE_SIZE`] on the longer side, at
+/// least one pixel each way
+fn fit(width: f32, height: f32) -> (u32, u32) {
+    let scale = MEASURE_SIZE / width.max(height).max(1.0);
+    (((width * scale).round() as u32).max(1), ((height * scale).round() as u32).max(1))
+}
+
+/// `area` in pixels of the measured copy, `width` x `height`, at least one
+/// pixel
+fn crop_geometry(area: &Area, width: u32, height: u32) -> String {
+    let px = |fraction: f32, side: u32| (fraction * side as f32).round() as u32;
+    format!(
+        "{}x{}+{}+{}",
+        px(area.width, width).max(1),
+        px(area.height, height).max(1),
+        px(area.x, width),
+        px(area.y, height)
+    )
+}
+
+/// Lines of "mean deviation": the image, then its edges, then the edges
+/// of each widget area
+fn parse_stats(output: &str) -> Option<ImageStats> {
+    let mut values = output.split_whitespace().map(|v| v.parse::<f32>().ok());
+    let luminance = values.next()??;
+    let contrast = values.next()??;
+    let busyness = values.next()??;
+    Some(ImageStats { luminance, contrast, busyness, clutter: None })
+}
+
+/// Mean edge strength of the busiest widget area
+fn parse_clutter(output: &str) -> f32 {
+    output
+        .lines()
+        .skip(2)
+        .filter_map(|line| line.split_whitespace().next()?.parse::<f32>().ok())
+        .fold(0.0, f32::max)
+}
+
+/// Measure the images among `files` the index has no stats for yet, or
+/// none for the current widget areas. Returns how many were measured.
+pub async fn analyze(files: &[PathBuf], readability: &Readability) -> usize {
+    let index = WallpaperIndex::load();
+    let key = readability.enabled().then(|| readability.key());
+    let pending: Vec<&PathBuf> = files
+        .iter()
+        .filter(|f| {
+            f.is_file()
+                && index.stats(f).is_none_or(|s| key.is_some_and(|k| s.clutter.is_none_or(|c| c.areas != k)))
+        })
+        .collect();
+    drop(index);
+
+    in_batches(&pending, |file| measure(file, readability), |index, file, stats| {
+        index.set_stats(file, stats)
+    })
+    .await
+}
+
+/// A helper program the analysis needs and cannot run at all, so trying
+/// the next image is pointless
+#[derive(Debug, Error)]
+pub enum ToolError {
+    #[error("{0} is not installed")]
+    NotInstalled(&'static str),
+    #[error("built without {0} (feature \"{1}\")")]
+    Disabled(&'static str, &'static str),
+}
+
+impl ToolError {
+    /// The missing tool behind `error`, if that's what went wrong
+    pub fn find(error: &anyhow::Error) -> Option<&ToolError> {
+        error.chain().find_map(|cause| cause.downcast_ref())
+    }
+}
+
+/// Run `measure` on each of `pending`, storing the results in small
+/// batches into a freshly loaded index so switches recorded meanwhile
+/// aren't overwritten. Stops when a helper program is missing.
+pub(crate) async fn in_batches<'a, T, F>(
+    pending: &[&'a PathBuf],
+    measure: impl Fn(&'a Path) -> F,
+    store: impl Fn(&mut WallpaperIndex, &Path, T),
+) -> usize
+where
+    F: Future<Output = Result<T>>,
+{
+    let mut analyzed = 0;
+    for batch in pending.chunks(16) {
+        let mut results = Vec::new();
+        for file in batch {
+            match measure(file).await {
+                Ok(value) => results.push((*file, value)),
+                Err(e) if ToolError::find(&e).is_some() => {
+                    warn!("Cannot analyze wallpapers: {:#}", e);
+                    return analyzed;
+                }
+                Err(e) => debug!("Failed to analyze {:?}: {}", file, e),
+            }
+        }
+
+        let count = results.len();
+        let stored = WallpaperIndex::update(|index| {
+            for (file, value) in results {
+                store(index, file, value);
+            }
+        });
+        if let Err(e) = stored {
+            warn!("Failed to save wallpaper index: {}", e);
+            return analyzed;
+        }
+        analyzed += count;
+    }
+    if analyzed > 0 {
+        debug!("Analyzed {} wallpaper(s)", analyzed);
+    }
+    analyzed
+}
+
+/// Run ImageMagick 7 (`magick`), or 6 (`convert`) when that's all there is
+pub(crate) async fn magick(args: &[String]) -> Result<std::process::Output> {
+    for program in ["magick", "convert"] {
+        let cmd = Command::new(program).args(args).output();
+        match timeout(Duration::from_secs(60), cmd).await {
+            Ok(Ok(output)) => return Ok(output),
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Ok(Err(e)) => return Err(e).with_context(|| format!("Failed to execute {}", program)),
+            Err(_) => anyhow::bail!("{} timed out", program),
+        }
+    }
+    Err(ToolError::NotInstalled("ImageMagick (magick or convert)").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(depth: u8, extra: &[u8; 4]) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.extend(13u32.to_be_bytes());
+        data.extend(b"IHDR");
+        data.extend(1u32.to_be_bytes());
+        data.extend(1u32.to_be_bytes());
+        data.extend([depth, 2, 0, 0, 0]);
+        data.extend([0; 4]);
+        data.extend(4u32.to_be_bytes());
+        data.extend(extra);
+        data.extend([0; 8]);
+        data
+    }
+
+    #[test]
+    fn test_png_is_hdr() {
+        assert!(!png_is_hdr(&png(8, b"IDAT")));
+        assert!(png_is_hdr(&png(16, b"IDAT")));
+        assert!(png_is_hdr(&png(8, b"cICP")));
+        assert!(!png_is_hdr(b"GIF89a"));
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        let stats = parse_stats("0.25 0.125\n0.0625 0.5\n").unwrap();
+        assert_eq!(stats, ImageStats { luminance: 0.25, contrast: 0.125, busyness: 0.0625, clutter: None });
+        assert!(parse_stats("0.25 0.125\n").is_none());
+        assert!(parse_stats("magick: no images").is_none());
+    }
+
+    #[test]
+    fn test_clutter_areas() {
+        assert_eq!(parse_clutter("0.5 0.1\n0.2 0.1\n0.05 0.01\n0.3 0.1\n"), 0.3);
+        let area = Area { x: 0.75, y: 0.0, width: 0.25, height: 0.5 };
+        assert_eq!(crop_geometry(&area, 128, 128), "32x64+96+0");
+        // A 16:9 image keeps its shape, so the area covers the same part
+        assert_eq!(fit(3840.0, 2160.0), (128, 72));
+        assert_eq!(crop_geometry(&area, 128, 72), "32x36+96+0");
+    }
+}
@@ -0,0 +1,123 @@
+//! Cleaning up after wallpapers that were deleted or changed: their index
+//! rows, thumbnails and converted (tinted, tone-mapped) copies. Each cached
+//! copy has a `.source` file naming the image it was made from.
+
+use crate::index::WallpaperIndex;
+use crate::protocol::GcReport;
+use crate::{convert, thumbnail};
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Unfinished copies older than this were abandoned
+const PARTIAL_MAX_AGE: Duration = Duration::from_secs(3600);
+
+/// Note next to `copy` which version of `image` it was made from
+pub(crate) fn record_source(copy: &Path, image: &Path) -> Result<()> {
+    fs::write(copy.with_extension("source"), source(image)?)?;
+    Ok(())
+}
+
+/// Path, size and modification time of `image`; a cached copy of it is
+/// current while these are unchanged
+fn source(image: &Path) -> Result<String> {
+    let meta = fs::metadata(image)?;
+    let modified = meta.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    Ok(format!("{}\n{}\n{}", image.display(), meta.len(), modified))
+}
+
+/// Whether the copy whose `.source` file holds `recorded` is out of date
+fn outdated(recorded: &str) -> bool {
+    let image = recorded.lines().next().unwrap_or_default();
+    image.is_empty() || source(Path::new(image)).map_or(true, |current| current != recorded)
+}
+
+/// Files in `dir` that belong to no current image: copies of deleted or
+/// changed images, copies made before sources were recorded, abandoned
+/// partial copies, and notes left without their copy
+fn stale_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let now = SystemTime::now();
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|file| {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            if name.ends_with(".part.png") {
+                return fs::metadata(file)
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() > PARTIAL_MAX_AGE);
+            }
+            let copy_outdated = || fs::read_to_string(file.with_extension("source")).map_or(true, |s| outdated(&s));
+            match file.extension().and_then(|e| e.to_str()) {
+                Some("png") => copy_outdated(),
+                Some("source" | "colors") => !file.with_extension("png").exists() || copy_outdated(),
+                _ => false,
+            }
+        })
+        .collect()
+}
+
+/// Remove index rows, thumbnails and converted copies of images that no
+/// longer exist or have changed; with `dry_run` only report them. The
+/// space is what the removed files take up on disk.
+pub fn collect(dry_run: bool) -> Result<GcReport> {
+    let prune = |index: &mut WallpaperIndex| index.prune_missing();
+    let pruned = if dry_run { prune(&mut WallpaperIndex::load()) } else { WallpaperIndex::update(prune)? };
+
+    let mut report = GcReport {
+        index_entries: pruned.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+        ..GcReport::default()
+    };
+    for dir in [thumbnail::cache_dir(), convert::cache_dir()].into_iter().flatten() {
+        for file in stale_files(&dir) {
+            report.cache_files += 1;
+            report.bytes += fs::metadata(&file).map(|m| m.len()).unwrap_or(0);
+            if !dry_run {
+                fs::remove_file(&file)?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_files() {
+        let dir = std::env::temp_dir().join(format!("swww-manager-gc-{}", std::process::id()));
+        let cache = dir.join("cache");
+        fs::create_dir_all(&cache).unwrap();
+        let image = dir.join("a.png");
+        fs::write(&image, "a").unwrap();
+
+        // Current copy with its palette
+        fs::write(cache.join("1.png"), "").unwrap();
+        fs::write(cache.join("1.colors"), "#000000").unwrap();
+        record_source(&cache.join("1.png"), &image).unwrap();
+        // Copy of an image since deleted, and one without a source
+        let gone = dir.join("b.png");
+        fs::write(&gone, "b").unwrap();
+        fs::write(cache.join("2.png"), "").unwrap();
+        record_source(&cache.join("2.png"), &gone).unwrap();
+        fs::remove_file(&gone).unwrap();
+        fs::write(cache.join("3.png"), "").unwrap();
+        // Palette left without its thumbnail, and a copy still being made
+        fs::write(cache.join("4.colors"), "#000000").unwrap();
+        fs::write(cache.join("5.part.png"), "").unwrap();
+
+        let mut stale = stale_files(&cache);
+        stale.sort();
+        assert_eq!(stale, [cache.join("2.png"), cache.join("2.source"), cache.join("3.png"), cache.join("4.colors")]);
+
+        // A changed image outdates its copy
+        fs::write(&image, "changed").unwrap();
+        assert_eq!(stale_files(&cache).len(), 7);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,319 @@
+//! Wallpapers made with ImageMagick instead of taken from a collection:
+//! a solid color or a top-to-bottom gradient on demand, and profiles'
+//! procedural patterns, rendered once a day. Everything is kept in
+//! `~/.cache/swww-manager/generated` and shown like any other image.
+
+use crate::config::{Pattern, Profile};
+use crate::convert::magick;
+use crate::wallpaper::fnv1a;
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Size used when the monitors can't be asked for theirs
+pub const DEFAULT_SIZE: (u32, u32) = (3840, 2160);
+
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("swww-manager/generated"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fill {
+    /// One `#rrggbb` color
+    Color(String),
+    /// From the first color at the top to the second at the bottom
+    Gradient(String, String),
+}
+
+impl Fill {
+    pub fn color(spec: &str) -> Result<Self> {
+        Ok(Self::Color(parse_color(spec)?))
+    }
+
+    /// Two colors separated by a colon, e.g. `#000:#333`
+    pub fn gradient(spec: &str) -> Result<Self> {
+        let (top, bottom) = spec.split_once(':')
+            .with_context(|| format!("Expected two colors like '#000:#333', got '{}'", spec))?;
+        Ok(Self::Gradient(parse_color(top)?, parse_color(bottom)?))
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Self::Color(color) => format!("color-{}", &color[1..]),
+            Self::Gradient(top, bottom) => format!("gradient-{}-{}", &top[1..], &bottom[1..]),
+        }
+    }
+
+    fn pseudo_image(&self) -> String {
+        match self {
+            Self::Color(color) => format!("xc:{}", color),
+            Self::Gradient(top, bottom) => format!("gradient:{}-{}", top, bottom),
+        }
+    }
+}
+
+/// `#rgb` or `#rrggbb`, with or without the `#`, as lowercase `#rrggbb`
+pub(crate) fn parse_color(spec: &str) -> Result<String> {
+    let hex = spec.trim().trim_start_matches('#');
+    let valid = hex.chars().all(|c| c.is_ascii_hexdigit());
+    let hex = match hex.len() {
+        3 if valid => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 if valid => hex.to_string(),
+        _ => anyhow::bail!("Invalid color '{}', expected #rgb or #rrggbb", spec),
+    };
+    Ok(format!("#{}", hex.to_ascii_lowercase()))
+}
+
+/// Image for `fill` at `width`x`height`, rendered once and reused after
+pub async fn render(fill: &Fill, width: u32, height: u32) -> Result<PathBuf> {
+    let dir = cache_dir().context("Could not determine cache path")?;
+    let target = dir.join(format!("{}-{}x{}.png", fill.name(), width, height));
+    if target.exists() {
+        return Ok(target);
+    }
+    fs::create_dir_all(&dir)?;
+
+    draw(&["-size".to_string(), format!("{}x{}", width, height), fill.pseudo_image()], &target).await?;
+    debug!("Generated {:?}", target);
+    Ok(target)
+}
+
+/// Run ImageMagick with `args` and the output file, which only appears
+/// once it is complete
+async fn draw(args: &[String], target: &Path) -> Result<()> {
+    let partial = target.with_extension("part.png");
+    let mut full = args.to_vec();
+    full.push(partial.to_string_lossy().to_string());
+    let output = magick(&full).await?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&partial);
+        anyhow::bail!("ImageMagick failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    fs::rename(&partial, target)?;
+    Ok(())
+}
+
+const DEFAULT_PALETTE: &[&str] = &["#11111b", "#1e1e2e", "#313244", "#45475a"];
+
+impl Pattern {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Noise => "noise",
+            Self::Triangles => "triangles",
+            Self::Stripes => "stripes",
+        }
+    }
+}
+
+/// Where the profile's procedural wallpapers go, `None` without
+/// generators. Profiles with the same generators and palette share it.
+pub fn daily_dir(profile: &Profile) -> Option<PathBuf> {
+    if profile.generators.is_empty() {
+        return None;
+    }
+    let key = format!("{:?}|{}", profile.generators, profile.palette.join(","));
+    cache_dir().map(|d| d.join(format!("daily-{:016x}", fnv1a(key.bytes()))))
+}
+
+/// Render today's image for each of the profile's generators, seeded by
+/// the date, and remove earlier days' images. Returns whether anything new
+/// was rendered.
+pub async fn daily(profile: &Profile) -> bool {
+    let Some(dir) = daily_dir(profile) else {
+        return false;
+    };
+    if let Err(e) = fs::create_dir_all(&dir) {
+        warn!("Failed to create {:?}: {}", dir, e);
+        return false;
+    }
+    let today = chrono::Local::now().date_naive().to_string();
+    let palette: Vec<[u8; 3]> = if profile.palette.is_empty() {
+        DEFAULT_PALETTE.iter().filter_map(|c| rgb(c)).collect()
+    } else {
+        profile.palette.iter().filter_map(|c| parse_color(c).ok()).filter_map(|c| rgb(&c)).collect()
+    };
+
+    let mut rendered = false;
+    for &pattern in &profile.generators {
+        let target = dir.join(format!("{}-{}.png", pattern.name(), today));
+        if target.exists() {
+            continue;
+        }
+        let seed = fnv1a(format!("{}|{}|{}", today, pattern.name(), dir.display()).bytes());
+        match render_pattern(pattern, &palette, seed, &target).await {
+            Ok(()) => {
+                debug!("Generated {:?}", target);
+                rendered = true;
+            }
+            Err(e) => warn!("Failed to generate {} wallpaper: {}", pattern.name(), e),
+        }
+    }
+    if rendered {
+        prune(&dir, &today);
+    }
+    rendered
+}
+
+fn prune(dir: &Path, today: &str) {
+    let suffix = format!("-{}.png", today);
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        if !entry.file_name().to_string_lossy().ends_with(&suffix) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+async fn render_pattern(pattern: Pattern, palette: &[[u8; 3]], seed: u64, target: &Path) -> Result<()> {
+    let (width, height) = DEFAULT_SIZE;
+    let size = format!("{}x{}", width, height);
+    let background = hex(mix(palette, 0.0));
+
+    let args: Vec<String> = match pattern {
+        Pattern::Noise => {
+            // Blurred random pixels blown up make smooth blobs; the palette
+            // is then laid over their brightness
+            let mut args: Vec<String> = [
+                "-seed", &(seed % (1 << 31)).to_string(),
+                "-size", &format!("{}x{}", width / 24, height / 24),
+                "xc:", "+noise", "Random", "-colorspace", "gray",
+                "-blur", "0x2", "-auto-level", "-resize", &format!("{}!", size),
+                "(",
+            ].map(String::from).to_vec();
+            args.extend(palette.iter().map(|&c| format!("xc:{}", hex(c))));
+            args.extend(["+append", "-filter", "triangle", "-resize", "256x1!", ")", "-clut"].map(String::from));
+            args
+        }
+        Pattern::Triangles | Pattern::Stripes => {
+            let mvg = match pattern {
+                Pattern::Triangles => triangles(palette, seed, width, height),
+                _ => stripes(palette, seed, width, height),
+            };
+            let file = target.with_extension("mvg");
+            fs::write(&file, mvg)?;
+            let args = ["-size", &size, &format!("xc:{}", background), "-draw", &format!("@{}", file.display())];
+            let result = draw(&args.map(String::from), target).await;
+            let _ = fs::remove_file(&file);
+            return result;
+        }
+    };
+    draw(&args, target).await
+}
+
+/// Jittered grid split into triangles, shaded from the palette's start at
+/// the top to its end at the bottom
+fn triangles(palette: &[[u8; 3]], seed: u64, width: u32, height: u32) -> String {
+    const COLS: usize = 16;
+    const ROWS: usize = 9;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (cell_w, cell_h) = (width as f32 / COLS as f32, height as f32 / ROWS as f32);
+
+    let mut points = vec![[0.0f32; 2]; (COLS + 1) * (ROWS + 1)];
+    for row in 0..=ROWS {
+        for col in 0..=COLS {
+            // Points on the border stay on it
+            let jitter = |rng: &mut StdRng, edge: bool| if edge { 0.0 } else { rng.random_range(-0.35..0.35) };
+            let dx = jitter(&mut rng, col == 0 || col == COLS);
+            let dy = jitter(&mut rng, row == 0 || row == ROWS);
+            points[row * (COLS + 1) + col] = [(col as f32 + dx) * cell_w, (row as f32 + dy) * cell_h];
+        }
+    }
+
+    let mut mvg = String::new();
+    for row in 0..ROWS {
+        for col in 0..COLS {
+            let at = |r: usize, c: usize| points[r * (COLS + 1) + c];
+            let (a, b, c, d) = (at(row, col), at(row, col + 1), at(row + 1, col), at(row + 1, col + 1));
+            let halves = if rng.random::<bool>() { [[a, b, d], [a, d, c]] } else { [[a, b, c], [b, d, c]] };
+            for triangle in halves {
+                let center = triangle.iter().map(|p| p[1]).sum::<f32>() / 3.0 / height as f32;
+                let shade = (center * 0.8 + rng.random::<f32>() * 0.2).clamp(0.0, 1.0);
+                let color = hex(mix(palette, shade));
+                let _ = write!(mvg, "fill '{0}' stroke '{0}' polygon", color);
+                for [x, y] in triangle {
+                    let _ = write!(mvg, " {:.1},{:.1}", x, y);
+                }
+                mvg.push('\n');
+            }
+        }
+    }
+    mvg
+}
+
+/// Bands of varying width and palette color at one angle across the image
+fn stripes(palette: &[[u8; 3]], seed: u64, width: u32, height: u32) -> String {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let angle: f32 = rng.random_range(-60.0..60.0);
+    let reach = (width as f32).hypot(height as f32) / 2.0;
+
+    let mut mvg = format!("push graphic-context\ntranslate {},{}\nrotate {:.1}\n", width / 2, height / 2, angle);
+    let mut x = -reach;
+    while x < reach {
+        let band = rng.random_range(0.02..0.12) * reach;
+        let color = hex(mix(palette, rng.random()));
+        let _ = writeln!(mvg, "fill '{}' rectangle {:.1},{:.1} {:.1},{:.1}", color, x, -reach, x + band, reach);
+        x += band;
+    }
+    mvg.push_str("pop graphic-context\n");
+    mvg
+}
+
+fn rgb(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Color at `t` (0.0 - 1.0) along the palette, blending neighbours
+fn mix(palette: &[[u8; 3]], t: f32) -> [u8; 3] {
+    match palette {
+        [] => [0, 0, 0],
+        [only] => *only,
+        _ => {
+            let pos = t.clamp(0.0, 1.0) * (palette.len() - 1) as f32;
+            let i = (pos as usize).min(palette.len() - 2);
+            let f = pos - i as f32;
+            let (a, b) = (palette[i], palette[i + 1]);
+            std::array::from_fn(|c| (a[c] as f32 + (b[c] as f32 - a[c] as f32) * f).round() as u8)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(Fill::color("#1E1E2E").unwrap(), Fill::Color("#1e1e2e".into()));
+        assert_eq!(
+            Fill::gradient("#000:333").unwrap(),
+            Fill::Gradient("#000000".into(), "#333333".into())
+        );
+        assert_eq!(Fill::gradient("#000:#333").unwrap().name(), "gradient-000000-333333");
+        assert!(Fill::color("#12345").is_err());
+        assert!(Fill::color("#ggg").is_err());
+        assert!(Fill::gradient("#000").is_err());
+    }
+
+    #[test]
+    fn test_patterns() {
+        let palette = [[0, 0, 0], [100, 200, 50]];
+        assert_eq!(mix(&palette, 0.5), [50, 100, 25]);
+        assert_eq!(mix(&palette, 1.0), [100, 200, 50]);
+        assert_eq!(rgb("#1e1e2e"), Some([30, 30, 46]));
+
+        let mesh = triangles(&palette, 7, 1600, 900);
+        assert_eq!(mesh, triangles(&palette, 7, 1600, 900));
+        assert_ne!(mesh, triangles(&palette, 8, 1600, 900));
+        assert_eq!(mesh.lines().count(), 16 * 9 * 2);
+        assert!(stripes(&palette, 7, 1600, 900).starts_with("push graphic-context\n"));
+    }
+}
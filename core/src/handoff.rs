@@ -0,0 +1,72 @@
+//! Hands the current wallpaper to programs outside the session, such as a
+//! greetd greeter or hyprlock, through files at fixed paths: one holding
+//! the image's path and a symlink to the image itself.
+
+use crate::config::Handoff;
+use crate::wallpaper::set_images;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn expand(path: &Path) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).into_owned())
+}
+
+/// Publish `wallpaper`; for a matched set, its first image. Both files are
+/// replaced atomically, so a greeter never reads a partial path.
+pub fn write(handoff: &Handoff, wallpaper: &Path) -> Result<()> {
+    let image = match wallpaper.is_dir().then(|| set_images(wallpaper)).flatten() {
+        Some(mut images) => {
+            images.sort();
+            images.swap_remove(0).1
+        }
+        None => wallpaper.to_path_buf(),
+    };
+    let image = image.canonicalize().unwrap_or(image);
+
+    let file = expand(&handoff.file);
+    let partial = file.with_extension("part");
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&partial, format!("{}\n", image.display()))
+        .and_then(|_| fs::rename(&partial, &file))
+        .with_context(|| format!("Failed to write {:?}", file))?;
+
+    let link = expand(&handoff.link);
+    let partial = link.with_extension("part");
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let _ = fs::remove_file(&partial);
+    std::os::unix::fs::symlink(&image, &partial)
+        .and_then(|_| fs::rename(&partial, &link))
+        .with_context(|| format!("Failed to link {:?}", link))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_handoff() {
+        let dir = std::env::temp_dir().join(format!("swww-manager-handoff-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let image = dir.join("a.png");
+        fs::write(&image, b"png").unwrap();
+        let handoff = Handoff {
+            enabled: true,
+            file: dir.join("out/wallpaper"),
+            link: dir.join("out/wallpaper-image"),
+        };
+
+        write(&handoff, &image).unwrap();
+        let image = image.canonicalize().unwrap();
+        assert_eq!(fs::read_to_string(&handoff.file).unwrap(), format!("{}\n", image.display()));
+        assert_eq!(fs::read_link(&handoff.link).unwrap(), image);
+        // Replacing an existing link works too
+        write(&handoff, &image).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
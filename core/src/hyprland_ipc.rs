@@ -0,0 +1,548 @@
+use anyhow::{Context, Result};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Deserializer};
+use tokio::net::UnixStream;
+use tokio::io::{AsyncWriteExt, AsyncReadExt};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, timeout, Duration};
+use tracing::debug;
+
+const RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// Handle to Hyprland's request socket. Hyprland answers one request per
+/// connection and then closes it, so streams can't be kept open; instead
+/// clones share a queue that runs requests one at a time, which keeps bursts
+/// (hotplug storms, notifications) from opening many sockets at once.
+#[derive(Clone)]
+pub struct HyprlandIPC {
+    socket_path: PathBuf,
+    queue: Arc<Mutex<()>>,
+}
+
+static SHARED: OnceLock<HyprlandIPC> = OnceLock::new();
+static TIMEOUT_MS: AtomicU64 = AtomicU64::new(2000);
+
+#[derive(Debug, Error)]
+pub enum IpcError {
+    #[error("Failed to connect to Hyprland socket: {0}")]
+    Connect(std::io::Error),
+    #[error("Hyprland socket error: {0}")]
+    Io(std::io::Error),
+    #[error("Hyprland did not answer {command:?} within {timeout_ms}ms")]
+    Timeout { command: String, timeout_ms: u64 },
+}
+
+impl HyprlandIPC {
+    #[cfg(not(feature = "hyprland"))]
+    pub fn new() -> Result<Self> {
+        anyhow::bail!("Built without Hyprland support (feature \"hyprland\")")
+    }
+
+    #[cfg(feature = "hyprland")]
+    pub fn new() -> Result<Self> {
+        let his = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+            .context("HYPRLAND_INSTANCE_SIGNATURE not set. Are you running under Hyprland?")?;
+        
+        let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+            .unwrap_or_else(|_| format!("/run/user/{}", users::get_current_uid()));
+        
+        let socket_path = PathBuf::from(runtime_dir)
+            .join("hypr")
+            .join(his)
+            .join(".socket.sock");
+
+        if !socket_path.exists() {
+            anyhow::bail!("Hyprland socket not found at {:?}", socket_path);
+        }
+
+        Ok(Self::at(socket_path))
+    }
+
+    #[cfg(any(feature = "hyprland", test))]
+    fn at(socket_path: PathBuf) -> Self {
+        Self { socket_path, queue: Arc::default() }
+    }
+
+    /// Process-wide handle, created on first successful use. Until Hyprland
+    /// is reachable every call tries again, so callers started before the
+    /// compositor pick it up later.
+    pub fn shared() -> Result<Self> {
+        if let Some(ipc) = SHARED.get() {
+            return Ok(ipc.clone());
+        }
+        let ipc = Self::new()?;
+        Ok(SHARED.get_or_init(|| ipc).clone())
+    }
+
+    /// Time limit for every request made through any handle
+    pub fn set_timeout(timeout: Duration) {
+        TIMEOUT_MS.store(timeout.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub async fn dispatch(&self, command: &str) -> Result<String> {
+        let _turn = self.queue.lock().await;
+
+        let timeout_ms = TIMEOUT_MS.load(Ordering::Relaxed);
+        match timeout(Duration::from_millis(timeout_ms), self.exchange(command)).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(IpcError::Timeout {
+                command: command.to_string(),
+                timeout_ms,
+            }
+            .into()),
+        }
+    }
+
+    async fn exchange(&self, command: &str) -> Result<String, IpcError> {
+        let mut stream = match UnixStream::connect(&self.socket_path).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                // The socket is briefly unavailable while Hyprland is busy
+                // reconfiguring outputs, retry once before giving up
+                debug!("Hyprland socket connect failed ({}), retrying", e);
+                sleep(RECONNECT_DELAY).await;
+                UnixStream::connect(&self.socket_path)
+                    .await
+                    .map_err(IpcError::Connect)?
+            }
+        };
+
+        stream.write_all(command.as_bytes()).await.map_err(IpcError::Io)?;
+        stream.flush().await.map_err(IpcError::Io)?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.map_err(IpcError::Io)?;
+
+        Ok(response)
+    }
+
+    /// dispatch_json 
+    pub async fn dispatch_json(&self, command: &str) -> Result<String> {
+        let cmd = format!("j/{}", command);
+        self.dispatch(&cmd).await
+    }
+
+    /// Run several commands over one connection using Hyprland's `[[BATCH]]`
+    /// syntax, returning one reply per command
+    pub async fn batch(&self, commands: &[&str]) -> Result<Vec<String>> {
+        if let Some(cmd) = commands.iter().find(|c| c.contains(';')) {
+            anyhow::bail!("Cannot batch command containing ';': {}", cmd);
+        }
+        let response = self.dispatch(&format!("[[BATCH]]{}", commands.join(";"))).await?;
+        split_batch(&response, commands.len())
+    }
+
+    /// Monitors, workspaces and windows in a single round trip
+    pub async fn get_layout(&self) -> Result<Layout> {
+        let replies = self.batch(&["j/monitors", "j/workspaces", "j/clients"]).await?;
+        Ok(Layout {
+            monitors: parse(&replies[0], "monitors")?,
+            workspaces: parse(&replies[1], "workspaces")?,
+            clients: parse(&replies[2], "clients")?,
+        })
+    }
+
+    /// get_monitors
+    pub async fn get_monitors(&self) -> Result<Vec<Monitor>> {
+        let response = self.dispatch_json("monitors").await?;
+        parse(&response, "monitors")
+    }
+
+    /// activeworkspace
+    pub async fn get_active_workspace(&self) -> Result<Workspace> {
+        let response = self.dispatch_json("activeworkspace").await?;
+        parse(&response, "workspace")
+    }
+
+    /// workspaces
+    pub async fn get_workspaces(&self) -> Result<Vec<Workspace>> {
+        let response = self.dispatch_json("workspaces").await?;
+        parse(&response, "workspaces")
+    }
+
+    /// clients
+    pub async fn get_clients(&self) -> Result<Vec<Client>> {
+        let response = self.dispatch_json("clients").await?;
+        parse(&response, "clients")
+    }
+
+    /// version
+    pub async fn get_version(&self) -> Result<Version> {
+        let response = self.dispatch_json("version").await?;
+        parse(&response, "version")
+    }
+
+    /// notify
+    pub async fn notify(&self, icon: i32, duration_ms: u32, color: &str, message: &str) -> Result<()> {
+        let cmd = format!("notify {} {} {} {}", icon, duration_ms, color, message);
+        self.dispatch(&cmd).await?;
+        Ok(())
+    }
+    
+    /*
+    /// dispatch
+    pub async fn exec_dispatch(&self, dispatcher: &str, args: &str) -> Result<()> {
+        let cmd = format!("dispatch {} {}", dispatcher, args);
+        self.dispatch(&cmd).await?;
+        Ok(())
+    }
+    */
+}
+
+/// Hyprland terminates every reply of a batch with this separator
+const BATCH_SEPARATOR: &str = "\n\n\n";
+
+fn split_batch(response: &str, count: usize) -> Result<Vec<String>> {
+    let replies: Vec<String> = response
+        .split(BATCH_SEPARATOR)
+        .take(count)
+        .map(str::to_string)
+        .collect();
+    if replies.len() != count {
+        anyhow::bail!("Expected {} batch replies from Hyprland, got {}", count, replies.len());
+    }
+    Ok(replies)
+}
+
+fn parse<T: DeserializeOwned>(response: &str, what: &str) -> Result<T> {
+    serde_json::from_str(response)
+        .with_context(|| format!("Failed to parse {} JSON", what))
+}
+
+/// A monitor as reported by `hyprctl monitors`. Only `name` is required so
+/// fields added, dropped or renamed by other Hyprland releases don't break
+/// parsing; unknown fields are ignored.
+#[allow(non_snake_case, dead_code)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct Monitor {
+    #[serde(default)]
+    pub id: i32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub make: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub serial: String,
+    #[serde(default)]
+    pub width: i32,
+    #[serde(default)]
+    pub height: i32,
+    #[serde(default)]
+    pub refreshRate: f32,
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(default)]
+    pub activeWorkspace: WorkspaceBasic,
+    #[serde(default)]
+    pub reserved: Vec<i32>,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub transform: i32,
+    #[serde(default)]
+    pub focused: bool,
+    /// Assumed on when the field is missing
+    #[serde(default = "default_true")]
+    pub dpmsStatus: bool,
+    #[serde(default, deserialize_with = "flag")]
+    pub vrr: bool,
+    /// Reported since Hyprland 0.36 for outputs turned off in the config
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WorkspaceBasic {
+    #[serde(default)]
+    pub id: i32,
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Workspace {
+    pub id: i32,
+    pub name: String,
+    #[serde(default)]
+    pub monitor: String,
+    #[serde(default)]
+    pub windows: i32,
+    #[serde(default)]
+    pub hasfullscreen: bool,
+    #[serde(default)]
+    pub lastwindow: String,
+    #[serde(default)]
+    pub lastwindowtitle: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub monitors: Vec<Monitor>,
+    pub workspaces: Vec<Workspace>,
+    pub clients: Vec<Client>,
+}
+
+/// A window as reported by `hyprctl clients`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Client {
+    pub address: String,
+    #[serde(default)]
+    pub mapped: bool,
+    #[serde(default)]
+    pub hidden: bool,
+    pub workspace: WorkspaceBasic,
+    /// Monitor id; older releases reported the monitor name instead
+    #[serde(default, deserialize_with = "monitor_id")]
+    pub monitor: i32,
+    #[serde(default)]
+    pub class: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub pid: i32,
+    #[serde(default)]
+    pub floating: bool,
+    /// Fullscreen state: a bool before Hyprland 0.42, a mode number after
+    #[serde(default, deserialize_with = "flag")]
+    pub fullscreen: bool,
+}
+
+/// Build information from `hyprctl version`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Version {
+    pub branch: String,
+    pub commit: String,
+    /// Only present since Hyprland 0.41, `tag` is set on all releases
+    pub version: String,
+    pub tag: String,
+    pub dirty: bool,
+}
+
+impl Version {
+    /// Release number without the leading `v`, e.g. "0.45.2"
+    pub fn release(&self) -> &str {
+        if !self.version.is_empty() {
+            &self.version
+        } else {
+            self.tag.trim_start_matches('v')
+        }
+    }
+}
+
+fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Int(i64),
+    }
+    Ok(match Flag::deserialize(deserializer)? {
+        Flag::Bool(b) => b,
+        Flag::Int(n) => n != 0,
+    })
+}
+
+fn monitor_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Int(i32),
+        Other(IgnoredAny),
+    }
+    Ok(match Id::deserialize(deserializer)? {
+        Id::Int(n) => n,
+        Id::Other(_) => -1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore] // Only run in Hyprland environment
+    async fn test_get_monitors() {
+        let ipc = HyprlandIPC::new().unwrap();
+        let monitors = ipc.get_monitors().await.unwrap();
+        assert!(!monitors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_queue() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("swww-manager-ipc-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        // Fake Hyprland: echo the request after a delay and track how many
+        // connections are open at the same time
+        let open = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        {
+            let (open, peak) = (open.clone(), peak.clone());
+            tokio::spawn(async move {
+                loop {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    let (open, peak) = (open.clone(), peak.clone());
+                    tokio::spawn(async move {
+                        let now = open.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        let mut buf = [0u8; 64];
+                        let n = stream.read(&mut buf).await.unwrap();
+                        sleep(Duration::from_millis(5)).await;
+                        open.fetch_sub(1, Ordering::SeqCst);
+                        stream.write_all(&buf[..n]).await.unwrap();
+                    });
+                }
+            });
+        }
+
+        let ipc = HyprlandIPC::at(path.clone());
+        let replies = futures::future::join_all((0..8).map(|i| {
+            let ipc = ipc.clone();
+            async move { ipc.dispatch(&format!("req{}", i)).await.unwrap() }
+        }))
+        .await;
+
+        assert_eq!(replies[3], "req3");
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_timeout() {
+        use tokio::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("swww-manager-wedged-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        // Accept but never answer, like a wedged compositor
+        tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            sleep(Duration::from_secs(60)).await;
+        });
+
+        HyprlandIPC::set_timeout(Duration::from_millis(50));
+        let err = HyprlandIPC::at(path.clone()).dispatch("j/monitors").await.unwrap_err();
+        HyprlandIPC::set_timeout(Duration::from_millis(2000));
+        assert!(matches!(err.downcast_ref(), Some(IpcError::Timeout { timeout_ms: 50, .. })));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        use tokio::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("swww-manager-batch-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 128];
+            let n = stream.read(&mut buf).await.unwrap();
+            stream.write_all(b"[]\n\n\n{\"id\": 1, \"name\": \"1\"}\n\n\n").await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let ipc = HyprlandIPC::at(path.clone());
+        let replies = ipc.batch(&["j/monitors", "j/activeworkspace"]).await.unwrap();
+        assert_eq!(server.await.unwrap(), "[[BATCH]]j/monitors;j/activeworkspace");
+        assert_eq!(replies[0], "[]");
+        let workspace: Workspace = parse(&replies[1], "workspace").unwrap();
+        assert_eq!(workspace.id, 1);
+
+        assert!(ipc.batch(&["notify 1 1000 0 a;b"]).await.is_err());
+        assert!(split_batch("ok", 2).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_monitors_across_versions() {
+        let fixtures = [
+            ("0.25", include_str!("../tests/fixtures/hyprland/monitors-0.25.json")),
+            ("0.35", include_str!("../tests/fixtures/hyprland/monitors-0.35.json")),
+            ("0.45", include_str!("../tests/fixtures/hyprland/monitors-0.45.json")),
+        ];
+        for (version, json) in fixtures {
+            let monitors: Vec<Monitor> = parse(json, "monitors")
+                .unwrap_or_else(|e| panic!("Hyprland {}: {:#}", version, e));
+            assert_eq!(monitors[0].name, "DP-1", "Hyprland {}", version);
+            assert_eq!(monitors[0].width, 2560, "Hyprland {}", version);
+            assert_eq!(monitors[0].activeWorkspace.name, "1", "Hyprland {}", version);
+            assert!(monitors[0].dpmsStatus, "Hyprland {}", version);
+        }
+
+        // 0.25 predates `vrr`, 0.45 reports disabled outputs
+        let old: Vec<Monitor> = parse(fixtures[0].1, "monitors").unwrap();
+        assert!(!old[0].vrr);
+        let new: Vec<Monitor> = parse(fixtures[2].1, "monitors").unwrap();
+        assert!(new[1].disabled);
+
+        // Anything but the name may go missing
+        let minimal: Vec<Monitor> = parse(r#"[{"name": "HDMI-A-1", "someFutureField": {"a": 1}}]"#, "monitors").unwrap();
+        assert_eq!(minimal[0].name, "HDMI-A-1");
+        assert_eq!(minimal[0].scale, 1.0);
+        assert!(minimal[0].dpmsStatus);
+        assert!(parse::<Vec<Monitor>>(r#"[{"id": 0}]"#, "monitors").is_err());
+    }
+
+    #[test]
+    fn test_parse_workspaces() {
+        let active: Workspace = parse(include_str!("../tests/fixtures/hyprland/activeworkspace.json"), "workspace").unwrap();
+        assert_eq!(active.name, "2");
+        assert_eq!(active.monitor, "DP-1");
+        assert!(active.hasfullscreen);
+
+        let workspaces: Vec<Workspace> = parse(include_str!("../tests/fixtures/hyprland/workspaces.json"), "workspaces").unwrap();
+        assert_eq!(workspaces.len(), 3);
+        assert_eq!(workspaces[2].name, "special:scratch");
+        assert_eq!(workspaces[2].id, -98);
+    }
+
+    #[test]
+    fn test_parse_clients() {
+        let clients: Vec<Client> = parse(include_str!("../tests/fixtures/hyprland/clients.json"), "clients").unwrap();
+        assert_eq!(clients.len(), 2);
+        assert_eq!(clients[0].class, "kitty");
+        assert!(!clients[0].fullscreen);
+        assert!(clients[1].fullscreen);
+        assert_eq!(clients[1].workspace.name, "2");
+
+        // Before 0.42 fullscreen was a bool
+        let clients: Vec<Client> = parse(include_str!("../tests/fixtures/hyprland/clients-0.40.json"), "clients").unwrap();
+        assert!(clients[0].fullscreen);
+        assert_eq!(clients[0].monitor, 1);
+    }
+
+    #[test]
+    fn test_parse_version() {
+        let version: Version = parse(include_str!("../tests/fixtures/hyprland/version.json"), "version").unwrap();
+        assert_eq!(version.release(), "0.45.2");
+
+        let version: Version = parse(include_str!("../tests/fixtures/hyprland/version-0.35.json"), "version").unwrap();
+        assert_eq!(version.release(), "0.35.0");
+        assert!(version.version.is_empty());
+
+        assert!(parse::<Version>("not json", "version").is_err());
+    }
+}
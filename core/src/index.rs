@@ -0,0 +1,354 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Persistent record of every wallpaper the daemon has seen, shared by all
+/// client handlers through the file on disk. Changes go through
+/// [`WallpaperIndex::update`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct WallpaperIndex {
+    #[serde(default)]
+    pub dirs: HashSet<PathBuf>,
+    #[serde(default)]
+    pub entries: HashMap<PathBuf, IndexEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub first_seen: i64,
+    #[serde(default)]
+    pub last_shown: Option<i64>,
+    /// Discovered after its directory was first indexed
+    #[serde(default)]
+    pub fresh: bool,
+    #[serde(default)]
+    pub shown_count: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<ImageStats>,
+    /// Share of the image covered by recognized text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<f32>,
+    /// Number of faces detected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub faces: Option<u32>,
+    /// Comes up twice as often in random rotation
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub favorite: bool,
+    /// Never picked
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub blocked: bool,
+}
+
+/// Favorite and blocked wallpapers, the part of the index every pick needs
+#[derive(Debug, Clone, Default)]
+pub struct Marks {
+    pub favorites: HashSet<PathBuf>,
+    pub blocked: HashSet<PathBuf>,
+}
+
+impl Marks {
+    /// Mirror [`WallpaperIndex::set_favorite`] and [`WallpaperIndex::set_blocked`]
+    pub fn set(&mut self, path: &Path, favorite: Option<bool>, blocked: Option<bool>) {
+        for (set, value) in [(&mut self.favorites, favorite), (&mut self.blocked, blocked)] {
+            match value {
+                Some(true) => {
+                    set.insert(path.to_path_buf());
+                }
+                Some(false) => {
+                    set.remove(path);
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Measurements of an image used in selection, each 0.0 - 1.0
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImageStats {
+    /// Average brightness, 0.0 black - 1.0 white
+    pub luminance: f32,
+    /// Standard deviation of the brightness
+    pub contrast: f32,
+    /// Average edge strength; high for detailed, cluttered images
+    pub busyness: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clutter: Option<Clutter>,
+}
+
+/// Edge strength in the busiest of the configured widget areas
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Clutter {
+    /// `Readability::key` of the areas measured
+    pub areas: u64,
+    pub value: f32,
+}
+
+impl WallpaperIndex {
+    pub fn path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|p| p.join("swww-manager/index.json"))
+    }
+
+    /// The index as saved, empty when there is none or it can't be read
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else { return Self::default() };
+        Self::read(&path).unwrap_or_else(|e| {
+            warn!("{:#}", e);
+            Self::default()
+        })
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).with_context(|| format!("Unreadable index {:?}", path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read index {:?}", path)),
+        }
+    }
+
+    /// Load the index, apply `change` and save it, holding a lock on the
+    /// index throughout so concurrent updates from this or another process
+    /// don't undo each other. An index that can't be read is left alone
+    /// rather than replaced by an empty one.
+    pub fn update<R>(change: impl FnOnce(&mut Self) -> R) -> Result<R> {
+        Self::update_at(&Self::path().context("Could not determine cache path")?, change)
+    }
+
+    fn update_at<R>(path: &Path, change: impl FnOnce(&mut Self) -> R) -> Result<R> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lock = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_extension("lock"))
+            .context("Failed to open the index lock")?;
+        lock.lock().context("Failed to lock the index")?;
+
+        let mut index = Self::read(path)?;
+        let result = change(&mut index);
+        index.write(path)?;
+        Ok(result)
+    }
+
+    /// Replace the file at `path` in one step, so readers never see half
+    /// of it
+    fn write(&self, path: &Path) -> Result<()> {
+        let partial = path.with_extension("json.part");
+        fs::write(&partial, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write index: {:?}", partial))?;
+        fs::rename(&partial, path).with_context(|| format!("Failed to write index: {:?}", path))?;
+        Ok(())
+    }
+
+    /// Record the current contents of `dirs`. Files appearing in a directory
+    /// that was already indexed are flagged as fresh.
+    pub fn sync(&mut self, dirs: &[PathBuf], files: impl IntoIterator<Item = PathBuf>) -> usize {
+        let now = chrono::Utc::now().timestamp();
+        let mut discovered = 0;
+
+        for file in files {
+            if self.entries.contains_key(&file) {
+                continue;
+            }
+            let known_dir = file
+                .parent()
+                .map(|p| self.dirs.contains(p))
+                .unwrap_or(false);
+            if known_dir {
+                discovered += 1;
+            }
+            self.entries.insert(file, IndexEntry {
+                first_seen: now,
+                last_shown: None,
+                fresh: known_dir,
+                ..Default::default()
+            });
+        }
+
+        self.dirs.extend(dirs.iter().cloned());
+
+        if discovered > 0 {
+            info!("Discovered {} new wallpaper(s)", discovered);
+        }
+        discovered
+    }
+
+    /// Whether [`Self::sync`] would record anything for `dirs` and `files`
+    pub fn has_unseen(&self, dirs: &[PathBuf], files: impl IntoIterator<Item = PathBuf>) -> bool {
+        dirs.iter().any(|d| !self.dirs.contains(d)) || files.into_iter().any(|f| !self.entries.contains_key(&f))
+    }
+
+    /// Newest fresh wallpaper among `files` that has not been shown yet
+    pub fn newest_fresh(&self, files: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+        files
+            .into_iter()
+            .filter_map(|f| self.entries.get(&f).filter(|e| e.fresh && e.last_shown.is_none()).map(|e| (e.first_seen, f)))
+            .max_by_key(|(first_seen, _)| *first_seen)
+            .map(|(_, f)| f)
+    }
+
+    /// Most recently shown wallpaper among `files`
+    pub fn last_shown(&self, files: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+        files
+            .into_iter()
+            .filter_map(|f| self.entries.get(&f).and_then(|e| e.last_shown).map(|t| (t, f)))
+            .max_by_key(|(t, _)| *t)
+            .map(|(_, f)| f)
+    }
+
+    /// Most recently shown wallpaper overall
+    pub fn most_recent(&self) -> Option<PathBuf> {
+        self.entries
+            .iter()
+            .filter_map(|(f, e)| e.last_shown.map(|t| (f, t)))
+            .max_by_key(|(_, t)| *t)
+            .map(|(f, _)| f.clone())
+    }
+
+    /// Drop entries for files and directories that no longer exist,
+    /// returning the removed files
+    pub fn prune_missing(&mut self) -> Vec<PathBuf> {
+        let mut removed: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|f| !f.exists())
+            .cloned()
+            .collect();
+        removed.sort();
+        for file in &removed {
+            self.entries.remove(file);
+        }
+        self.dirs.retain(|d| d.exists());
+        removed
+    }
+
+    /// Size of the serialized index in bytes
+    pub fn size(&self) -> usize {
+        serde_json::to_string(self).map(|s| s.len()).unwrap_or(0)
+    }
+
+    pub fn mark_shown(&mut self, path: &Path) {
+        let now = chrono::Utc::now().timestamp();
+        let entry = self.entry(path);
+        entry.last_shown = Some(now);
+        entry.shown_count += 1;
+        entry.fresh = false;
+    }
+
+    pub fn stats(&self, path: &Path) -> Option<ImageStats> {
+        self.entries.get(path).and_then(|e| e.stats)
+    }
+
+    pub fn set_stats(&mut self, path: &Path, stats: ImageStats) {
+        self.entry(path).stats = Some(stats);
+    }
+
+    pub fn text(&self, path: &Path) -> Option<f32> {
+        self.entries.get(path).and_then(|e| e.text)
+    }
+
+    pub fn set_text(&mut self, path: &Path, coverage: f32) {
+        self.entry(path).text = Some(coverage);
+    }
+
+    pub fn faces(&self, path: &Path) -> Option<u32> {
+        self.entries.get(path).and_then(|e| e.faces)
+    }
+
+    pub fn set_faces(&mut self, path: &Path, faces: u32) {
+        self.entry(path).faces = Some(faces);
+    }
+
+    pub fn marks(&self) -> Marks {
+        let marked = |pick: fn(&IndexEntry) -> bool| {
+            self.entries.iter().filter(|(_, e)| pick(e)).map(|(p, _)| p.clone()).collect()
+        };
+        Marks { favorites: marked(|e| e.favorite), blocked: marked(|e| e.blocked) }
+    }
+
+    pub fn is_favorite(&self, path: &Path) -> bool {
+        self.entries.get(path).is_some_and(|e| e.favorite)
+    }
+
+    pub fn set_favorite(&mut self, path: &Path, favorite: bool) {
+        self.entry(path).favorite = favorite;
+    }
+
+    pub fn is_blocked(&self, path: &Path) -> bool {
+        self.entries.get(path).is_some_and(|e| e.blocked)
+    }
+
+    pub fn set_blocked(&mut self, path: &Path, blocked: bool) {
+        self.entry(path).blocked = blocked;
+    }
+
+    fn entry(&mut self, path: &Path) -> &mut IndexEntry {
+        self.entries.entry(path.to_path_buf()).or_insert_with(|| IndexEntry {
+            first_seen: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_files_are_fresh_only_in_known_dirs() {
+        let dir = PathBuf::from("/walls");
+        let mut index = WallpaperIndex::default();
+
+        let initial = vec![dir.join("a.png"), dir.join("b.png")];
+        assert!(index.has_unseen(std::slice::from_ref(&dir), initial.clone()));
+        assert_eq!(index.sync(std::slice::from_ref(&dir), initial.clone()), 0);
+        assert!(!index.has_unseen(std::slice::from_ref(&dir), initial.clone()));
+        assert_eq!(index.newest_fresh(initial.clone()), None);
+
+        let mut current = initial.clone();
+        current.push(dir.join("c.png"));
+        current.push(PathBuf::from("/other/d.png"));
+        assert_eq!(index.sync(std::slice::from_ref(&dir), current.clone()), 1);
+        assert_eq!(index.newest_fresh(current.clone()), Some(dir.join("c.png")));
+
+        index.mark_shown(&dir.join("c.png"));
+        assert_eq!(index.newest_fresh(current.clone()), None);
+
+        assert_eq!(index.prune_missing().len(), 4);
+        assert!(index.entries.is_empty());
+        assert!(index.dirs.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_updates_all_land() {
+        let dir = std::env::temp_dir().join(format!("swww-manager-index-{}", std::process::id()));
+        let path = dir.join("index.json");
+        let image = PathBuf::from("/walls/a.png");
+
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                let (path, image) = (path.clone(), image.clone());
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        WallpaperIndex::update_at(&path, |index| index.mark_shown(&image)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(WallpaperIndex::read(&path).unwrap().entries[&image].shown_count, 100);
+
+        // A damaged index is reported, not replaced
+        fs::write(&path, "{\"entries\": {").unwrap();
+        assert!(WallpaperIndex::update_at(&path, |index| index.mark_shown(&image)).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"entries\": {");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
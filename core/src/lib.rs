@@ -0,0 +1,24 @@
+//! Configuration, profile matching, wallpaper selection, the swww and
+//! Hyprland backends, and the daemon protocol types shared by the
+//! `swww-manager` binary and other tools talking to it.
+
+pub mod ambient;
+pub mod config;
+pub mod content;
+pub mod convert;
+pub mod gc;
+pub mod generate;
+pub mod handoff;
+pub mod profile;
+pub mod protocol;
+pub mod scan;
+pub mod thumbnail;
+pub mod hyprland_ipc;
+pub mod index;
+pub mod wallpaper;
+pub mod weather;
+
+pub use config::Config;
+pub use profile::ProfileManager;
+pub use wallpaper::WallpaperManager;
+pub use hyprland_ipc::HyprlandIPC;
@@ -0,0 +1,439 @@
+use crate::config::{Config, Profile};
+use crate::hyprland_ipc::Monitor;
+use crate::generate;
+use crate::protocol::{DetectionReport, DirectoryInfo, ProfileInfo, ProfileMatch};
+use crate::scan::ScanCache;
+use crate::wallpaper::expand_dirs;
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct ProfileManager {
+    config: Config,
+}
+
+#[allow(dead_code)]
+impl ProfileManager {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    pub fn current_profile(&self) -> Result<&Profile> {
+        self.config
+            .profiles
+            .get(&self.config.current_profile)
+            .context("Current profile not found")
+    }
+
+    /// Full name of the profile `name` abbreviates, see [`resolve_name`]
+    pub fn resolve(&self, name: &str) -> Result<String> {
+        resolve_name(self.config.profiles.keys().map(String::as_str), name).map(String::from)
+    }
+
+    pub fn switch_to(&mut self, name: &str) -> Result<()> {
+        if !self.config.profiles.contains_key(name) {
+            anyhow::bail!("Profile '{}' not found", name);
+        }
+
+        self.config.current_profile = name.to_string();
+        Ok(())
+    }
+
+    /// The profile matching `monitors`, not counting `fallback_profile`
+    pub fn detect_profile(&self, monitors: &[Monitor]) -> Result<Option<String>> {
+        Ok(self.evaluate(monitors).selected)
+    }
+
+    /// Evaluate every profile against `monitors`, recording why each one
+    /// did or didn't match. Without a match `fallback_profile` is selected,
+    /// as detection would switch to it.
+    pub fn explain(&self, monitors: &[Monitor]) -> DetectionReport {
+        let mut report = self.evaluate(monitors);
+        report.selected = report.selected.or_else(|| self.config.monitor_detection.fallback_profile.clone());
+        report
+    }
+
+    fn evaluate(&self, monitors: &[Monitor]) -> DetectionReport {
+        let monitor_set: HashSet<_> = monitors.iter().map(|m| &m.name).collect();
+
+        let mut names: Vec<_> = self.config.profiles.keys().collect();
+        names.sort();
+
+        let mut best_match = None;
+        let mut best_score = 0;
+        let mut fallback_match = None;
+        let mut results = Vec::new();
+
+        for name in names {
+            let profile = &self.config.profiles[name];
+            let mut reasons = Vec::new();
+            let wildcard = profile.monitors.len() == 1 && profile.monitors.contains(&"*".to_string());
+
+            // Handle wildcard profile as fallback
+            if wildcard && !profile.has_conditions() {
+                if fallback_match.is_none() {
+                    fallback_match = Some(name.clone());
+                }
+                reasons.push("wildcard fallback".to_string());
+                results.push(ProfileMatch { name: name.clone(), matched: true, score: 0, reasons });
+                continue;
+            }
+
+            let by_name = !wildcard && !profile.monitors.is_empty();
+            if by_name {
+                let resolved: Vec<String> = profile
+                    .monitors
+                    .iter()
+                    .map(|m| self.resolve_alias(m, monitors))
+                    .collect();
+                let profile_monitors: HashSet<_> = resolved.iter().collect();
+                let mut missing: Vec<_> = profile_monitors.difference(&monitor_set).map(|s| s.as_str()).collect();
+                let mut extra: Vec<_> = monitor_set.difference(&profile_monitors).map(|s| s.as_str()).collect();
+                missing.sort();
+                extra.sort();
+                if !missing.is_empty() {
+                    reasons.push(format!("missing monitor(s): {}", missing.join(", ")));
+                }
+                if !extra.is_empty() {
+                    reasons.push(format!("unexpected monitor(s): {}", extra.join(", ")));
+                }
+                if profile_monitors.len() != monitor_set.len() {
+                    reasons.push(format!(
+                        "count mismatch: expects {}, found {}",
+                        profile_monitors.len(),
+                        monitor_set.len()
+                    ));
+                }
+            } else if !profile.has_conditions() {
+                reasons.push("no monitor names or conditions configured".to_string());
+            }
+
+            reasons.extend(failed_conditions(profile, monitors));
+
+            if !reasons.is_empty() {
+                results.push(ProfileMatch { name: name.clone(), matched: false, score: 0, reasons });
+                continue;
+            }
+
+            // Name matches always outrank condition-only profiles
+            let mut score = if by_name { 2 + monitor_set.len() * 2 } else { 1 };
+            if by_name {
+                reasons.push(format!("exact name match ({} monitor(s))", monitor_set.len()));
+            }
+            if profile.has_conditions() {
+                score += 1;
+                reasons.push("conditions satisfied".to_string());
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_match = Some(name.clone());
+            }
+            results.push(ProfileMatch { name: name.clone(), matched: true, score, reasons });
+        }
+
+        // Return exact match first, fallback to wildcard if no exact match
+        DetectionReport {
+            monitors: monitors.iter().map(|m| m.name.clone()).collect(),
+            selected: best_match.or(fallback_match),
+            profiles: results,
+        }
+    }
+
+    /// Provisional profile for a monitor set that only the wildcard or
+    /// fallback profile would cover, e.g. `auto_DP-1_HDMI-A-1`. It copies
+    /// the profile that would have been used, listing the monitors by name.
+    pub fn auto_profile(&self, monitors: &[Monitor]) -> Option<(String, Profile)> {
+        if monitors.is_empty() {
+            return None;
+        }
+        let report = self.explain(monitors);
+        if report.profiles.iter().any(|p| p.matched && p.score > 0) {
+            return None;
+        }
+
+        let mut names = report.monitors;
+        names.sort();
+        let name = format!("auto_{}", names.join("_"));
+        if self.config.profiles.contains_key(&name) {
+            return None;
+        }
+
+        let base = report.selected.unwrap_or_else(|| self.config.current_profile.clone());
+        let profile = Profile {
+            monitors: names,
+            requires: None,
+            any_monitor: None,
+            sync_groups: Vec::new(),
+            output_dirs: HashMap::new(),
+            ..self.config.profiles.get(&base).cloned().unwrap_or_default()
+        };
+        Some((name, profile))
+    }
+
+    pub fn list(&self) {
+        println!("\nAvailable Profiles:");
+        println!("{}", "-".repeat(50));
+
+        for (name, profile) in &self.config.profiles {
+            let current = if name == &self.config.current_profile { "✓" } else { " " };
+            println!("[{}] {}", current, name);
+            println!("Monitors: {}", profile.monitors.join(", "));
+            println!("Wallpaper dirs: {}", profile.wallpaper_dirs.len());
+            println!("Transition: {} ({}s)", profile.transition, profile.transition_duration);
+            println!();
+        }
+    }
+
+    /// Every profile with its wallpaper counts as last read into `scans`;
+    /// only directories never read before are read now
+    pub async fn get_profile_list(&self, scans: &ScanCache) -> Vec<ProfileInfo> {
+        let max_age = Duration::from_secs(self.config.cache_max_age_secs);
+        let mut profiles = Vec::new();
+        for (name, profile) in &self.config.profiles {
+            let scan = scans.get(&expand_dirs(profile), max_age).await.unwrap_or_default();
+            // Generated images are made on demand, their directory isn't listed
+            let generated = generate::daily_dir(profile);
+            let directories: Vec<DirectoryInfo> = scan
+                .directories
+                .iter()
+                .filter(|d| generated.as_deref() != Some(Path::new(&d.path)))
+                .cloned()
+                .collect();
+
+            profiles.push(ProfileInfo {
+                name: name.clone(),
+                monitors: profile.monitors.clone(),
+                wallpaper_count: directories.iter().map(|d| d.wallpaper_count).sum(),
+                is_current: name == &self.config.current_profile,
+                transition: Some(profile.transition.clone()),
+                transition_duration: Some(profile.transition_duration),
+                directories,
+            });
+        }
+        profiles
+    }
+
+    pub fn update_config(&mut self, config: Config) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+}
+
+impl ProfileManager {
+    /// Connector name of the monitor matching alias `entry`, or `entry`
+    /// itself when it is not an alias or nothing connected matches it
+    fn resolve_alias(&self, entry: &str, monitors: &[Monitor]) -> String {
+        let Some(rule) = self.config.monitor_aliases.get(entry) else {
+            return entry.to_string();
+        };
+        monitors
+            .iter()
+            .find(|m| alias_matches(rule, m))
+            .map(|m| m.name.clone())
+            .unwrap_or_else(|| entry.to_string())
+    }
+}
+
+/// The one name in `names` that `query` stands for, ignoring case: the name
+/// itself, else the only name starting with it, else the only name holding
+/// its letters in order (`offi` or `ofc` for `office`). Several candidates
+/// at the first level that has any are an error listing them.
+pub fn resolve_name<'a>(names: impl IntoIterator<Item = &'a str>, query: &str) -> Result<&'a str> {
+    let names: Vec<&str> = names.into_iter().collect();
+    if let Some(exact) = names.iter().find(|n| **n == query) {
+        return Ok(exact);
+    }
+    let lower = query.to_lowercase();
+    let levels: [&dyn Fn(&str) -> bool; 3] = [
+        &|name| name == lower,
+        &|name| name.starts_with(&lower),
+        &|name| {
+            let mut chars = name.chars();
+            lower.chars().all(|q| chars.any(|c| c == q))
+        },
+    ];
+    for matches in levels {
+        let mut found: Vec<&str> = names.iter().copied().filter(|n| matches(&n.to_lowercase())).collect();
+        match found.len() {
+            0 => continue,
+            1 => return Ok(found[0]),
+            _ => {
+                found.sort_unstable();
+                anyhow::bail!("Profile '{}' is ambiguous: {}", query, found.join(", "));
+            }
+        }
+    }
+    anyhow::bail!("Profile '{}' not found", query)
+}
+
+/// `desc:`, `model:` and `name:` rules are regexes searched in that field,
+/// anything else must equal the connector name
+pub fn alias_matches(rule: &str, monitor: &Monitor) -> bool {
+    let (field, pattern) = match rule.split_once(':') {
+        Some(("desc", p)) => (&monitor.description, p),
+        Some(("model", p)) => (&monitor.model, p),
+        Some(("name", p)) => (&monitor.name, p),
+        _ => return monitor.name == rule,
+    };
+    regex::Regex::new(pattern)
+        .map(|re| re.is_match(field))
+        .unwrap_or(false)
+}
+
+fn failed_conditions(profile: &Profile, monitors: &[Monitor]) -> Vec<String> {
+    let mut failed = Vec::new();
+
+    if let Some(req) = &profile.requires {
+        let total_width: i32 = monitors.iter().map(|m| m.width).sum();
+        let total_height: i32 = monitors.iter().map(|m| m.height).sum();
+        if let Some(w) = req.min_total_width.filter(|w| total_width < *w) {
+            failed.push(format!("total width {} < {}", total_width, w));
+        }
+        if let Some(h) = req.min_total_height.filter(|h| total_height < *h) {
+            failed.push(format!("total height {} < {}", total_height, h));
+        }
+        if let Some(n) = req.min_count.filter(|n| monitors.len() < *n) {
+            failed.push(format!("{} monitor(s) < min_count {}", monitors.len(), n));
+        }
+        if let Some(n) = req.max_count.filter(|n| monitors.len() > *n) {
+            failed.push(format!("{} monitor(s) > max_count {}", monitors.len(), n));
+        }
+    }
+
+    if let Some(want) = &profile.any_monitor {
+        let found = monitors.iter().any(|m| {
+            want.width.is_none_or(|w| m.width == w)
+                && want.height.is_none_or(|h| m.height == h)
+                && want.min_width.is_none_or(|w| m.width >= w)
+                && want.min_height.is_none_or(|h| m.height >= h)
+                && want.scale.is_none_or(|s| (m.scale - s).abs() < 0.01)
+        });
+        if !found {
+            failed.push(format!("no monitor satisfies any_monitor {:?}", want));
+        }
+    }
+
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{MonitorMatch, MonitorRequirements};
+    use std::path::PathBuf;
+
+    fn monitor(name: &str, width: i32, height: i32) -> Monitor {
+        serde_json::from_value(serde_json::json!({
+            "id": 0, "name": name, "make": "", "model": "", "serial": "",
+            "width": width, "height": height, "refreshRate": 60.0, "x": 0, "y": 0,
+            "activeWorkspace": { "id": 1, "name": "1" }, "reserved": [0, 0, 0, 0],
+            "scale": 1.0, "transform": 0, "focused": false, "dpmsStatus": true, "vrr": false
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_name() {
+        let names = ["office", "offline", "laptop", "Gaming", "lap"];
+        assert_eq!(resolve_name(names, "lap").unwrap(), "lap");
+        assert_eq!(resolve_name(names, "offi").unwrap(), "office");
+        assert_eq!(resolve_name(names, "GAM").unwrap(), "Gaming");
+        assert_eq!(resolve_name(names, "lptp").unwrap(), "laptop");
+        let error = resolve_name(names, "off").unwrap_err().to_string();
+        assert_eq!(error, "Profile 'off' is ambiguous: office, offline");
+        assert!(resolve_name(names, "desk").is_err());
+    }
+
+    fn manager(profiles: Vec<(&str, Profile)>) -> ProfileManager {
+        ProfileManager::new(Config {
+            profiles: profiles.into_iter().map(|(n, p)| (n.to_string(), p)).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_detect_by_resolution_conditions() {
+        let pm = manager(vec![
+            ("default", Profile { monitors: vec!["*".into()], ..Default::default() }),
+            ("ultrawide", Profile {
+                any_monitor: Some(MonitorMatch { width: Some(3440), ..Default::default() }),
+                ..Default::default()
+            }),
+            ("wide_desk", Profile {
+                requires: Some(MonitorRequirements { min_total_width: Some(5120), ..Default::default() }),
+                ..Default::default()
+            }),
+            ("laptop", Profile { monitors: vec!["eDP-1".into()], ..Default::default() }),
+        ]);
+
+        let detect = |m: &[Monitor]| pm.detect_profile(m).unwrap();
+        assert_eq!(detect(&[monitor("DP-3", 3440, 1440)]).as_deref(), Some("ultrawide"));
+        assert_eq!(
+            detect(&[monitor("DP-1", 2560, 1440), monitor("DP-2", 2560, 1440)]).as_deref(),
+            Some("wide_desk")
+        );
+        assert_eq!(detect(&[monitor("eDP-1", 3440, 1440)]).as_deref(), Some("laptop"));
+        assert_eq!(detect(&[monitor("HDMI-A-1", 1920, 1080)]).as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn test_detect_with_monitor_alias() {
+        let mut pm = manager(vec![
+            ("default", Profile { monitors: vec!["*".into()], ..Default::default() }),
+            ("work", Profile { monitors: vec!["work_dell".into()], ..Default::default() }),
+        ]);
+        pm.config.monitor_aliases.insert("work_dell".into(), "desc:DELL U2723QE .*".into());
+
+        let mut dell = monitor("DP-7", 3840, 2160);
+        dell.description = "Dell Inc. DELL U2723QE 5KC0P83".into();
+        assert_eq!(pm.detect_profile(&[dell]).unwrap().as_deref(), Some("work"));
+        assert_eq!(pm.detect_profile(&[monitor("DP-7", 3840, 2160)]).unwrap().as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn test_explain_selects_fallback_profile() {
+        let mut pm = manager(vec![
+            ("laptop", Profile { monitors: vec!["eDP-1".into()], ..Default::default() }),
+            ("desk", Profile { monitors: vec!["DP-1".into()], ..Default::default() }),
+        ]);
+        pm.config.monitor_detection.fallback_profile = Some("desk".into());
+
+        let monitors = [monitor("HDMI-A-1", 1920, 1080)];
+        assert_eq!(pm.detect_profile(&monitors).unwrap(), None);
+        assert_eq!(pm.explain(&monitors).selected.as_deref(), Some("desk"));
+        assert_eq!(pm.explain(&[monitor("eDP-1", 1920, 1080)]).selected.as_deref(), Some("laptop"));
+    }
+
+    #[test]
+    fn test_auto_profile() {
+        let mut pm = manager(vec![
+            ("default", Profile {
+                monitors: vec!["*".into()],
+                wallpaper_dirs: vec!["/walls".into()],
+                transition: "fade".into(),
+                ..Default::default()
+            }),
+            ("laptop", Profile { monitors: vec!["eDP-1".into()], ..Default::default() }),
+        ]);
+
+        assert!(pm.auto_profile(&[monitor("eDP-1", 1920, 1080)]).is_none());
+
+        let (name, profile) = pm
+            .auto_profile(&[monitor("HDMI-A-1", 1920, 1080), monitor("DP-1", 2560, 1440)])
+            .unwrap();
+        assert_eq!(name, "auto_DP-1_HDMI-A-1");
+        assert_eq!(profile.monitors, ["DP-1", "HDMI-A-1"]);
+        assert_eq!(profile.transition, "fade");
+        assert_eq!(profile.wallpaper_dirs, [PathBuf::from("/walls")]);
+
+        // Once saved it matches by name instead of being created again
+        pm.config.profiles.insert(name, profile);
+        assert!(pm.auto_profile(&[monitor("DP-1", 2560, 1440), monitor("HDMI-A-1", 1920, 1080)]).is_none());
+    }
+}
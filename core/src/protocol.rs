@@ -0,0 +1,315 @@
+use crate::config::SwitchMode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub enum Request {
+    Switch {
+        profile: Option<String>,
+        /// Transition preset for this switch only
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        preset: Option<String>,
+        /// Switch mode for this switch only
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<SwitchMode>,
+    },
+    SwitchProfile { name: String },
+    DetectAndSwitchProfile,
+    ListProfiles,
+    GetStatus,
+    /// Keep the connection open and send a `Status` response, one per
+    /// line, now and whenever the status changes
+    WatchStatus,
+    SetAutoSwitch { enabled: bool },
+    Shutdown,
+    SetAutoSwitchInterval { interval: u64 },
+    ReloadConfig,
+    ExplainDetection,
+    GetVersion,
+    Restart,
+    /// Dotted key, or the whole configuration when `None`
+    GetConfig { key: Option<String> },
+    SetConfig { key: String, value: String },
+    SetLogLevel { level: String },
+    /// Limit rotation to a channel of the current profile, `None` clears it
+    SetChannel { name: Option<String> },
+    /// Re-apply the current wallpaper with a preset and/or individual
+    /// transition settings, without changing the config
+    TestTransition {
+        preset: Option<String>,
+        transition: Option<String>,
+        duration: Option<u32>,
+        fps: Option<u32>,
+        bezier: Option<String>,
+    },
+    /// Show the image at `path` on every output
+    SetWallpaper { path: String },
+    /// Show the image at 1-based `index` of the list `ListWallpapers`
+    /// returns for the active profile, as `contact-sheet` numbers it
+    SetWallpaperByIndex { index: usize },
+    /// Show the current profile's image whose file name best matches
+    /// `pattern`
+    SetWallpaperByMatch { pattern: String },
+    /// Go back to the wallpaper the current one replaced
+    PreviousWallpaper,
+    /// Remove what refers to wallpapers that no longer exist; with
+    /// `dry_run` only report it
+    Gc { dry_run: bool },
+    /// Images and matched sets of the current profile
+    ListWallpapers,
+    /// Change the marks of a wallpaper; `None` leaves a mark as it is
+    MarkWallpaper {
+        path: String,
+        favorite: Option<bool>,
+        blocked: Option<bool>,
+    },
+    /// Run `request` and wrap its response with the server-side duration
+    Timed { request: Box<Request> },
+    /// Carries the shared secret required on the TCP listener
+    Auth { token: String, request: Box<Request> },
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub enum Response {
+    Success { message: String },
+    Error { message: String },
+    ProfileList { profiles: Vec<ProfileInfo> },
+    Wallpapers { profile: String, wallpapers: Vec<WallpaperInfo> },
+    Status { status: StatusInfo },
+    DetectionReport { report: DetectionReport },
+    Version { version: VersionInfo },
+    Config { value: serde_json::Value },
+    Collected { report: GcReport },
+    Timed { elapsed_ms: u64, response: Box<Response> },
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub monitors: Vec<String>,
+    pub wallpaper_count: usize,
+    pub is_current: bool,
+    pub transition: Option<String>,
+    pub transition_duration: Option<u32>,
+    /// Each of `wallpaper_dirs` with whether it could be scanned
+    #[serde(default)]
+    pub directories: Vec<DirectoryInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DirectoryInfo {
+    pub path: String,
+    pub state: DirectoryState,
+    pub wallpaper_count: usize,
+    /// Images listed in the directory that can't be opened, such as
+    /// dangling symlinks or files on a share that went away
+    pub unreachable: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryState {
+    Ok,
+    Missing,
+    NotADirectory,
+    /// Exists but can't be listed, e.g. no permission or a stale mount
+    Unreadable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WallpaperInfo {
+    pub path: String,
+    pub favorite: bool,
+    pub blocked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct StatusInfo {
+    pub auto_switch_interval: Option<u64>,
+    pub current_profile: String,
+    pub current_wallpaper: Option<String>,
+    pub auto_switch_enabled: bool,
+    pub monitors: Vec<String>,
+    pub uptime_secs: u64,
+    #[serde(default)]
+    pub pid: Option<u32>,
+    #[serde(default)]
+    pub version: Option<VersionInfo>,
+    /// The daemon executable was replaced on disk since it started
+    #[serde(default)]
+    pub binary_updated: bool,
+    /// Unix time in milliseconds, identifies the daemon instance
+    #[serde(default)]
+    pub started_at: Option<i64>,
+    /// No compositor reachable; switches are queued until one appears
+    #[serde(default)]
+    pub offline: bool,
+    /// Channel rotation is currently limited to
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Unix time in seconds of the next automatic switch, while enabled
+    #[serde(default)]
+    pub next_switch_at: Option<i64>,
+    /// Image each output was given last, by connector name
+    #[serde(default)]
+    pub output_wallpapers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DetectionReport {
+    pub monitors: Vec<String>,
+    pub selected: Option<String>,
+    pub profiles: Vec<ProfileMatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ProfileMatch {
+    pub name: String,
+    pub matched: bool,
+    pub score: usize,
+    pub reasons: Vec<String>,
+}
+
+/// What `gc` removed, or would remove
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub struct GcReport {
+    /// Index rows of wallpapers that no longer exist
+    pub index_entries: Vec<String>,
+    /// Thumbnails and converted copies of deleted or changed images
+    pub cache_files: usize,
+    /// Entries in the daemon's wallpaper history
+    pub history_entries: usize,
+    /// Disk space taken by the removed files
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct VersionInfo {
+    pub version: String,
+    pub git_hash: String,
+}
+
+/// JSON Schema (draft 2020-12) of every request and response, for
+/// generating clients in other languages. Each message is one JSON value
+/// sent over the daemon's socket; the types are under `$defs`.
+pub fn schema() -> serde_json::Value {
+    let mut generator = schemars::generate::SchemaSettings::draft2020_12().into_generator();
+    let request = generator.subschema_for::<Request>();
+    let response = generator.subschema_for::<Response>();
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "swww-manager protocol",
+        "version": env!("CARGO_PKG_VERSION"),
+        "oneOf": [request, response],
+        "$defs": generator.take_definitions(true),
+    })
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: env!("SWWW_MANAGER_GIT_HASH").to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for VersionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.version, self.git_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn switch_mode() -> impl Strategy<Value = SwitchMode> {
+        prop_oneof![Just(SwitchMode::Random), Just(SwitchMode::Sequential), Just(SwitchMode::Daily)]
+    }
+
+    fn request() -> impl Strategy<Value = Request> {
+        let leaf = prop_oneof![
+            (proptest::option::of(".*"), proptest::option::of(".*"), proptest::option::of(switch_mode()))
+                .prop_map(|(profile, preset, mode)| Request::Switch { profile, preset, mode }),
+            ".*".prop_map(|name| Request::SwitchProfile { name }),
+            Just(Request::DetectAndSwitchProfile),
+            Just(Request::GetStatus),
+            Just(Request::WatchStatus),
+            any::<bool>().prop_map(|enabled| Request::SetAutoSwitch { enabled }),
+            any::<u64>().prop_map(|interval| Request::SetAutoSwitchInterval { interval }),
+            proptest::option::of(".*").prop_map(|key| Request::GetConfig { key }),
+            (".*", ".*").prop_map(|(key, value)| Request::SetConfig { key, value }),
+            proptest::option::of(".*").prop_map(|name| Request::SetChannel { name }),
+            ".*".prop_map(|path| Request::SetWallpaper { path }),
+            any::<usize>().prop_map(|index| Request::SetWallpaperByIndex { index }),
+            ".*".prop_map(|pattern| Request::SetWallpaperByMatch { pattern }),
+            Just(Request::PreviousWallpaper),
+            any::<bool>().prop_map(|dry_run| Request::Gc { dry_run }),
+            Just(Request::ListWallpapers),
+            (".*", proptest::option::of(any::<bool>()), proptest::option::of(any::<bool>()))
+                .prop_map(|(path, favorite, blocked)| Request::MarkWallpaper { path, favorite, blocked }),
+            (
+                proptest::option::of(".*"),
+                proptest::option::of(".*"),
+                proptest::option::of(any::<u32>()),
+                proptest::option::of(any::<u32>()),
+                proptest::option::of(".*"),
+            )
+                .prop_map(|(preset, transition, duration, fps, bezier)| Request::TestTransition {
+                    preset, transition, duration, fps, bezier,
+                }),
+        ];
+        leaf.prop_recursive(3, 8, 1, |inner| {
+            prop_oneof![
+                inner.clone().prop_map(|request| Request::Timed { request: Box::new(request) }),
+                (".*", inner).prop_map(|(token, request)| Request::Auth {
+                    token,
+                    request: Box::new(request),
+                }),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn garbage_bytes_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+            let _ = serde_json::from_slice::<Request>(&bytes);
+        }
+
+        #[test]
+        fn garbage_json_never_panics(text in "[\\[\\]{}\":,a-zA-Z0-9 ]*") {
+            let _ = serde_json::from_str::<Request>(&text);
+        }
+
+        #[test]
+        fn requests_round_trip(request in request()) {
+            let json = serde_json::to_string(&request).unwrap();
+            let parsed: Request = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn schema_covers_requests_and_responses() {
+        let schema = schema();
+        let defs = schema["$defs"].as_object().unwrap();
+        for name in ["Request", "Response", "StatusInfo", "VersionInfo"] {
+            assert!(defs.contains_key(name), "{} missing", name);
+        }
+        assert_eq!(schema["oneOf"][0]["$ref"], "#/$defs/Request");
+    }
+
+    #[test]
+    fn deeply_nested_request_is_rejected() {
+        let depth = 10_000;
+        let json = format!(
+            "{}\"GetStatus\"{}",
+            "{\"Timed\":{\"request\":".repeat(depth),
+            "}}".repeat(depth)
+        );
+        assert!(serde_json::from_str::<Request>(&json).is_err());
+    }
+}
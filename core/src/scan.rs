@@ -0,0 +1,498 @@
+//! Finding the images in wallpaper directories. Scans are cached per
+//! directory and shared by every clone of a [`ScanCache`], so rotation, the
+//! daemon's connections, other profiles and `list` read the same results
+//! instead of each globbing the directories again.
+
+use crate::protocol::{DirectoryInfo, DirectoryState};
+use anyhow::Result;
+use glob::glob;
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, warn};
+
+/// Image types swww can show, matched in lower and upper case
+pub const EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "png", "gif", "webp", "bmp"];
+
+#[derive(Debug, Default)]
+pub struct Scan {
+    /// Images of every directory, sorted and without duplicates
+    pub images: PathList,
+    /// Each scanned directory in the order given
+    pub directories: Vec<DirectoryInfo>,
+}
+
+impl Scan {
+    /// Log the directories that couldn't be scanned
+    pub fn warn_broken(&self) {
+        for dir in &self.directories {
+            match dir.state {
+                DirectoryState::Ok => {}
+                DirectoryState::Missing => warn!("Wallpaper directory does not exist: {:?}", dir.path),
+                state => warn!("Wallpaper directory can't be read ({:?}): {:?}", state, dir.path),
+            }
+        }
+    }
+}
+
+/// Images in `dirs`, sorted and without duplicates
+pub fn scan_dirs(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let scan = scan(dirs);
+    scan.warn_broken();
+    scan.images.to_vec()
+}
+
+/// Images in `dirs` along with each directory's state
+#[tracing::instrument(level = "debug")]
+pub fn scan(dirs: &[PathBuf]) -> Scan {
+    let mut scan = Scan::default();
+    for dir in dirs {
+        let (info, found) = scan_dir(dir);
+        scan.images.append(&found);
+        scan.directories.push(info);
+    }
+    scan.images.sort_dedup();
+    scan
+}
+
+fn scan_dir(dir: &Path) -> (DirectoryInfo, PathList) {
+    let state = match fs::read_dir(dir) {
+        Ok(_) => DirectoryState::Ok,
+        Err(e) if e.kind() == ErrorKind::NotFound => DirectoryState::Missing,
+        Err(e) if e.kind() == ErrorKind::NotADirectory => DirectoryState::NotADirectory,
+        Err(_) => DirectoryState::Unreadable,
+    };
+
+    let mut images = Vec::new();
+    if state == DirectoryState::Ok {
+        for ext in EXTENSIONS {
+            for ext in [ext.to_string(), ext.to_uppercase()] {
+                if let Ok(paths) = glob(&format!("{}/*.{}", dir.display(), ext)) {
+                    images.extend(paths.flatten());
+                }
+            }
+        }
+    }
+    // Dangling symlinks and files on a share that went away are still listed
+    let unreachable = images.iter().filter(|image| fs::metadata(image).is_err()).count();
+    let info = DirectoryInfo {
+        path: dir.to_string_lossy().to_string(),
+        state,
+        wallpaper_count: images.len() - unreachable,
+        unreachable,
+    };
+    let mut images: PathList = images.into_iter().collect();
+    images.sort_dedup();
+    (info, images)
+}
+
+/// Number of entries and newest modification time of a directory and its
+/// entries. Adding, removing, renaming or rewriting an image changes it, so
+/// an unchanged fingerprint means the directory needn't be globbed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Fingerprint {
+    entries: usize,
+    newest: Option<SystemTime>,
+}
+
+fn fingerprint(dir: &Path) -> Option<Fingerprint> {
+    let mut newest = fs::metadata(dir).and_then(|m| m.modified()).ok();
+    let mut entries = 0;
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        entries += 1;
+        newest = newest.max(entry.metadata().and_then(|m| m.modified()).ok());
+    }
+    Some(Fingerprint { entries, newest })
+}
+
+enum Read {
+    Unchanged,
+    Scanned { fingerprint: Option<Fingerprint>, info: DirectoryInfo, images: PathList },
+}
+
+fn read_dirs(jobs: Vec<(PathBuf, Option<Fingerprint>)>) -> Vec<(PathBuf, Read)> {
+    jobs.into_iter()
+        .map(|(dir, previous)| {
+            let fingerprint = fingerprint(&dir);
+            if fingerprint.is_some() && fingerprint == previous {
+                return (dir, Read::Unchanged);
+            }
+            let (info, images) = scan_dir(&dir);
+            (dir, Read::Scanned { fingerprint, info, images })
+        })
+        .collect()
+}
+
+/// Scans of single directories, shared by all clones. A profile's view
+/// is put together from the directories it lists, so a directory used by
+/// several profiles is read once for all of them.
+#[derive(Debug, Clone, Default)]
+pub struct ScanCache {
+    dirs: Arc<Mutex<HashMap<PathBuf, DirScan>>>,
+    /// Directories being read again in the background
+    refreshing: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Bumped whenever a directory's scan changes
+    version: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Clone)]
+struct DirScan {
+    at: Instant,
+    /// Version of the cache when this scan last changed
+    generation: u64,
+    fingerprint: Option<Fingerprint>,
+    info: DirectoryInfo,
+    images: PathList,
+}
+
+impl ScanCache {
+    /// View of `dirs`. Directories never read are read now, off the async
+    /// runtime; ones read more than `max_age` ago are served as they are
+    /// and read again in the background.
+    pub async fn get(&self, dirs: &[PathBuf], max_age: Duration) -> Result<Scan> {
+        self.load(dirs, max_age).await?;
+        Ok(self.view(dirs))
+    }
+
+    /// [`ScanCache::get`] without building the view
+    pub async fn load(&self, dirs: &[PathBuf], max_age: Duration) -> Result<()> {
+        let missing: Vec<PathBuf> = {
+            let cached = self.dirs.lock().unwrap();
+            dirs.iter().filter(|dir| !cached.contains_key(*dir)).cloned().collect()
+        };
+        self.refresh(&missing).await?;
+        self.refresh_stale(dirs, max_age);
+        Ok(())
+    }
+
+    /// Start reading the directories of `dirs` older than `max_age` again
+    /// in the background; [`ScanCache::version`] changes once they are in
+    pub fn refresh_stale(&self, dirs: &[PathBuf], max_age: Duration) {
+        let stale: Vec<PathBuf> = {
+            let cached = self.dirs.lock().unwrap();
+            let mut refreshing = self.refreshing.lock().unwrap();
+            dirs.iter()
+                .filter(|dir| cached.get(*dir).is_some_and(|c| c.at.elapsed() >= max_age))
+                .filter(|dir| refreshing.insert((*dir).clone()))
+                .cloned()
+                .collect()
+        };
+        if stale.is_empty() {
+            return;
+        }
+        let cache = self.clone();
+        tokio::spawn(async move {
+            debug!("Refreshing {} stale wallpaper directories", stale.len());
+            if let Err(e) = cache.refresh(&stale).await {
+                warn!("Failed to refresh wallpaper directories: {}", e);
+            }
+            let mut refreshing = cache.refreshing.lock().unwrap();
+            for dir in &stale {
+                refreshing.remove(dir);
+            }
+        });
+    }
+
+    /// Changes whenever the scan of some directory changes
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    /// Changes whenever the scan of one of `dirs` changes, but not for
+    /// other directories
+    pub fn generation(&self, dirs: &[PathBuf]) -> u64 {
+        let cached = self.dirs.lock().unwrap();
+        dirs.iter().filter_map(|dir| cached.get(dir)).map(|c| c.generation).max().unwrap_or_default()
+    }
+
+    /// Read `dirs` again off the async runtime. Directories whose
+    /// fingerprint hasn't changed keep their scan and don't bump the version.
+    pub async fn refresh(&self, dirs: &[PathBuf]) -> Result<()> {
+        let jobs = self.jobs(dirs);
+        if jobs.is_empty() {
+            return Ok(());
+        }
+        let read = tokio::task::spawn_blocking(move || read_dirs(jobs))
+            .await
+            .map_err(|e| anyhow::anyhow!("Join error when scanning wallpaper directories: {}", e))?;
+        self.store(read);
+        Ok(())
+    }
+
+    /// [`ScanCache::refresh`] on this thread
+    pub fn refresh_blocking(&self, dirs: &[PathBuf]) {
+        self.store(read_dirs(self.jobs(dirs)));
+    }
+
+    /// Each of `dirs` once, with the fingerprint of its last read
+    fn jobs(&self, dirs: &[PathBuf]) -> Vec<(PathBuf, Option<Fingerprint>)> {
+        let cached = self.dirs.lock().unwrap();
+        let mut jobs: Vec<_> = dirs.iter()
+            .map(|dir| (dir.clone(), cached.get(dir).and_then(|c| c.fingerprint)))
+            .collect();
+        jobs.sort();
+        jobs.dedup();
+        jobs
+    }
+
+    fn store(&self, read: Vec<(PathBuf, Read)>) {
+        let at = Instant::now();
+        let mut changed = false;
+        let mut cached = self.dirs.lock().unwrap();
+        // Only stored under the lock, so nothing else can take this one
+        let generation = self.version.load(Ordering::SeqCst) + 1;
+        for (dir, read) in read {
+            match read {
+                Read::Unchanged => {
+                    if let Some(entry) = cached.get_mut(&dir) {
+                        entry.at = at;
+                    }
+                }
+                Read::Scanned { fingerprint, info, images } => {
+                    // Directories without a fingerprint, such as missing
+                    // ones, are read every time but rarely change
+                    let generation = match cached.get(&dir) {
+                        Some(c) if c.info == info && c.images == images => c.generation,
+                        _ => {
+                            changed = true;
+                            generation
+                        }
+                    };
+                    cached.insert(dir, DirScan { at, generation, fingerprint, info, images });
+                }
+            }
+        }
+        if changed {
+            self.version.store(generation, Ordering::SeqCst);
+        }
+    }
+
+    /// Images and states of `dirs` as last read, images sorted and without
+    /// duplicates
+    pub fn view(&self, dirs: &[PathBuf]) -> Scan {
+        let cached = self.dirs.lock().unwrap();
+        let mut scan = Scan::default();
+        for entry in dirs.iter().filter_map(|dir| cached.get(dir)) {
+            scan.images.append(&entry.images);
+            scan.directories.push(entry.info.clone());
+        }
+        scan.images.sort_dedup();
+        scan
+    }
+}
+
+/// Paths kept as their directory and file name. Each directory is stored
+/// once and the file names share one buffer, so a collection of 100k images
+/// takes a fraction of the memory of as many `PathBuf`s.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathList {
+    dirs: Vec<PathBuf>,
+    /// File names back to back
+    names: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    dir: u32,
+    /// Range of the file name in `names`
+    start: u32,
+    end: u32,
+}
+
+impl PathList {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<PathBuf> {
+        self.entries.get(index).map(|entry| self.path(entry))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.entries.iter().map(|entry| self.path(entry))
+    }
+
+    pub fn to_vec(&self) -> Vec<PathBuf> {
+        self.iter().collect()
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        let (dir, name) = split(path);
+        let Some(dir) = self.dirs.iter().position(|d| d == dir) else {
+            return false;
+        };
+        self.entries.iter().any(|entry| entry.dir == dir as u32 && self.name(entry) == name)
+    }
+
+    pub fn push(&mut self, path: &Path) {
+        let (dir, name) = split(path);
+        let dir = self.intern(dir);
+        let start = self.names.len() as u32;
+        self.names.extend_from_slice(name.as_bytes());
+        self.entries.push(Entry { dir, start, end: self.names.len() as u32 });
+    }
+
+    /// Add all of `other`'s paths without building them
+    pub fn append(&mut self, other: &PathList) {
+        let dirs: Vec<u32> = other.dirs.iter().map(|dir| self.intern(dir)).collect();
+        let offset = self.names.len() as u32;
+        self.names.extend_from_slice(&other.names);
+        self.entries.extend(other.entries.iter().map(|entry| Entry {
+            dir: dirs[entry.dir as usize],
+            start: entry.start + offset,
+            end: entry.end + offset,
+        }));
+    }
+
+    /// Sort the way the full paths sort and drop duplicates
+    pub fn sort_dedup(&mut self) {
+        let mut entries = std::mem::take(&mut self.entries);
+        entries.sort_by(|a, b| self.compare(a, b));
+        entries.dedup_by(|a, b| self.compare(a, b) == CmpOrdering::Equal);
+        self.entries = entries;
+    }
+
+    fn compare(&self, a: &Entry, b: &Entry) -> CmpOrdering {
+        let components = |entry: &Entry| {
+            self.dirs[entry.dir as usize].components().chain(Path::new(self.name(entry)).components())
+        };
+        components(a).cmp(components(b))
+    }
+
+    fn intern(&mut self, dir: &Path) -> u32 {
+        // Paths mostly come grouped by directory
+        if let Some(i) = self.dirs.iter().rposition(|d| d == dir) {
+            return i as u32;
+        }
+        self.dirs.push(dir.to_path_buf());
+        (self.dirs.len() - 1) as u32
+    }
+
+    fn name(&self, entry: &Entry) -> &OsStr {
+        OsStr::from_bytes(&self.names[entry.start as usize..entry.end as usize])
+    }
+
+    fn path(&self, entry: &Entry) -> PathBuf {
+        self.dirs[entry.dir as usize].join(self.name(entry))
+    }
+}
+
+impl<P: AsRef<Path>> Extend<P> for PathList {
+    fn extend<I: IntoIterator<Item = P>>(&mut self, paths: I) {
+        for path in paths {
+            self.push(path.as_ref());
+        }
+    }
+}
+
+impl<P: AsRef<Path>> FromIterator<P> for PathList {
+    fn from_iter<I: IntoIterator<Item = P>>(paths: I) -> Self {
+        let mut list = PathList::default();
+        list.extend(paths);
+        list
+    }
+}
+
+fn split(path: &Path) -> (&Path, &OsStr) {
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => (dir, name),
+        _ => (Path::new(""), path.as_os_str()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scan_cache() {
+        let root = std::env::temp_dir().join(format!("swww-manager-scan-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("b.PNG"), b"").unwrap();
+        fs::write(root.join("a.jpg"), b"").unwrap();
+        fs::write(root.join("notes.txt"), b"").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join("gone.png"), root.join("link.png")).unwrap();
+        let dirs = vec![root.clone(), root.join("missing")];
+
+        let cache = ScanCache::default();
+        let fresh = Duration::from_secs(60);
+        let scan = cache.get(&dirs, fresh).await.unwrap();
+        assert_eq!(scan.images.to_vec()[..2], [root.join("a.jpg"), root.join("b.PNG")]);
+        assert_eq!(scan.directories[0].state, DirectoryState::Ok);
+        assert_eq!(scan.directories[1].state, DirectoryState::Missing);
+        #[cfg(unix)]
+        assert_eq!((scan.directories[0].wallpaper_count, scan.directories[0].unreachable), (2, 1));
+
+        // Another profile sharing the directory gets the scan already made,
+        // and only its own directory is read
+        fs::create_dir_all(root.join("more")).unwrap();
+        fs::write(root.join("more/d.png"), b"").unwrap();
+        fs::write(root.join("c.webp"), b"").unwrap();
+        let other = cache.clone().get(&[root.join("more"), root.clone()], fresh).await.unwrap();
+        assert!(other.images.contains(&root.join("more/d.png")));
+        assert!(!other.images.contains(&root.join("c.webp")));
+
+        // A stale scan is still served, and replaced in the background
+        let version = cache.version();
+        let stale = cache.get(&dirs, Duration::ZERO).await.unwrap();
+        assert!(!stale.images.contains(&root.join("c.webp")));
+        let refreshed = async {
+            while cache.version() == version {
+                tokio::task::yield_now().await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), refreshed).await.unwrap();
+        assert!(cache.get(&dirs, fresh).await.unwrap().images.contains(&root.join("c.webp")));
+
+        // Reading an unchanged directory again leaves the version alone
+        let version = cache.version();
+        cache.refresh(&dirs).await.unwrap();
+        assert_eq!(cache.version(), version);
+        fs::write(root.join("e.gif"), b"").unwrap();
+        cache.refresh_blocking(&dirs);
+        assert_ne!(cache.version(), version);
+        assert!(cache.view(&dirs).images.contains(&root.join("e.gif")));
+
+        // Only the changed directory's generation moves
+        let more = [root.join("more")];
+        let generation = cache.generation(&more);
+        fs::write(root.join("f.png"), b"").unwrap();
+        cache.refresh(&dirs).await.unwrap();
+        assert_eq!(cache.generation(&more), generation);
+        assert_eq!(cache.generation(&dirs), cache.version());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_path_list() {
+        let paths = ["/w/b/x.png", "/w/a.png", "/w/b/x.png", "/w/b.png", "/v/z.jpg"];
+        let mut list: PathList = paths.iter().collect();
+        let mut more = PathList::default();
+        more.push(Path::new("/w/a/y.gif"));
+        list.append(&more);
+        list.sort_dedup();
+
+        let mut expected: Vec<PathBuf> = paths.iter().chain(["/w/a/y.gif"].iter()).map(PathBuf::from).collect();
+        expected.sort();
+        expected.dedup();
+        assert_eq!(list.to_vec(), expected);
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.get(0), Some(PathBuf::from("/v/z.jpg")));
+        assert!(list.contains(Path::new("/w/b/x.png")));
+        assert!(!list.contains(Path::new("/w/x.png")));
+    }
+}
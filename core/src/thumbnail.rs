@@ -0,0 +1,110 @@
+//! Small previews of wallpapers and their main colors, made with
+//! ImageMagick and kept in `~/.cache/swww-manager/thumbnails` for widgets
+//! and pickers. A preview is reused until its image changes.
+
+use crate::convert::magick;
+use crate::gc;
+use crate::wallpaper::{fnv1a, set_images};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Longest side of a thumbnail in pixels
+const SIZE: u32 = 320;
+/// Number of colors in a palette
+const COLORS: usize = 5;
+
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("swww-manager/thumbnails"))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Thumbnail {
+    pub path: PathBuf,
+    /// Main colors as `#rrggbb`, most common first
+    pub palette: Vec<String>,
+}
+
+/// Preview of `wallpaper`; for a matched set, of its first image
+pub async fn thumbnail(wallpaper: &Path) -> Result<Thumbnail> {
+    let image = match wallpaper.is_dir().then(|| set_images(wallpaper)).flatten() {
+        Some(mut images) => {
+            images.sort();
+            images.swap_remove(0).1
+        }
+        None => wallpaper.to_path_buf(),
+    };
+    let meta = fs::metadata(&image).with_context(|| format!("Cannot read {:?}", image))?;
+    let modified = meta.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let key = format!("{}|{}|{}", image.display(), meta.len(), modified);
+
+    let dir = cache_dir().context("Could not determine cache path")?;
+    let path = dir.join(format!("{:016x}.png", fnv1a(key.bytes())));
+    let colors = path.with_extension("colors");
+    if let (true, Ok(saved)) = (path.exists(), fs::read_to_string(&colors)) {
+        return Ok(Thumbnail { path, palette: saved.lines().map(String::from).collect() });
+    }
+    fs::create_dir_all(&dir)?;
+
+    // One pass: write the thumbnail, then reduce it to its main colors
+    let partial = path.with_extension("part.png");
+    let args = [
+        format!("{}[0]", image.display()),
+        "-thumbnail".to_string(),
+        format!("{0}x{0}", SIZE),
+        "-write".to_string(),
+        partial.to_string_lossy().to_string(),
+        "+dither".to_string(),
+        "-colors".to_string(),
+        COLORS.to_string(),
+        "-depth".to_string(),
+        "8".to_string(),
+        "-format".to_string(),
+        "%c".to_string(),
+        "histogram:info:-".to_string(),
+    ];
+    let output = magick(&args).await?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&partial);
+        anyhow::bail!("ImageMagick failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let palette = parse_histogram(&String::from_utf8_lossy(&output.stdout));
+    fs::write(&colors, palette.join("\n"))?;
+    fs::rename(&partial, &path)?;
+    gc::record_source(&path, &image)?;
+    Ok(Thumbnail { path, palette })
+}
+
+/// Colors of a `histogram:info:` listing, most common first
+fn parse_histogram(output: &str) -> Vec<String> {
+    let mut counted: Vec<(u64, String)> = output
+        .lines()
+        .filter_map(|line| {
+            let (count, rest) = line.trim().split_once(':')?;
+            let hex = rest.split_whitespace().find(|w| w.starts_with('#'))?;
+            // 8-bit colors only, dropping any alpha
+            let hex = hex.get(..7).filter(|h| h[1..].chars().all(|c| c.is_ascii_hexdigit()))?;
+            Some((count.trim().parse().ok()?, hex.to_ascii_lowercase()))
+        })
+        .collect();
+    counted.sort_by_key(|&(count, _)| std::cmp::Reverse(count));
+    counted.into_iter().map(|(_, hex)| hex).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_histogram() {
+        let output = "\
+            1200: ( 30, 30, 46) #1E1E2E srgb(30,30,46)
+            45000: (137,180,250) #89B4FA srgb(137,180,250)
+            300: (  0,  0,  0,255) #000000FF srgba(0,0,0,1)
+        ";
+        assert_eq!(parse_histogram(output), ["#89b4fa", "#1e1e2e", "#000000"]);
+        assert!(parse_histogram("magick: no images").is_empty());
+    }
+}
@@ -0,0 +1,1433 @@
+use crate::ambient::Lighting;
+use crate::config::{Config, Handoff, Profile, SwitchMode};
+use crate::convert;
+use crate::generate;
+use crate::handoff;
+use crate::index::{Marks, WallpaperIndex};
+use crate::scan::{scan_dirs, PathList, ScanCache};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use thiserror::Error;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+use tokio::time::{timeout, Duration};
+
+#[derive(Clone)]
+pub struct WallpaperManager {
+    last_wallpaper: Option<PathBuf>,
+    /// Image each output was given last, shared with every clone
+    output_wallpapers: Arc<Mutex<BTreeMap<String, PathBuf>>>,
+    /// Wallpapers set, the current one first, shared with every clone
+    history: Arc<Mutex<VecDeque<PathBuf>>>,
+    /// Last wallpaper set with each profile's directories, shared with
+    /// every clone
+    shown: Arc<Mutex<HashMap<Vec<PathBuf>, PathBuf>>>,
+    sequential_index: usize,
+    /// Wallpapers of the profile in use
+    cache: Arc<Collection>,
+    /// Recently used profiles' wallpapers, most recent first, shared with
+    /// every clone
+    collections: Arc<Mutex<VecDeque<Arc<Collection>>>>,
+    weather_tags: Vec<String>,
+    upcoming: Option<PathBuf>,
+    /// Outputs connected at the last switch; sets missing one are skipped
+    connected: Vec<String>,
+    /// Transition settings that differ from the profile's per output
+    output_transitions: HashMap<String, OutputTransition>,
+    /// Evening tint strength for images set now
+    tint: Option<f32>,
+    lighting: Lighting,
+    handoff: Option<Handoff>,
+    /// Directory scans, shared with every clone and the profile listing
+    scans: ScanCache,
+    /// Favorites and blocked wallpapers, read from the index on first use
+    /// and shared with every clone
+    marks: Arc<Mutex<Option<Marks>>>,
+    /// How old a directory's scan may get before it is read again
+    cache_max_age: Duration,
+    /// How long to wait for `swww img`, `None` for as long as it takes
+    swww_timeout: Option<Duration>,
+}
+
+#[derive(Debug, Error)]
+pub enum SwwwError {
+    /// swww keeps loading the image after this and may still show it
+    #[error("swww is still loading the wallpaper after {}s (swww_timeout_secs)", .0.as_secs())]
+    TimedOut(Duration),
+    #[error("swww-daemon is not running, start it with swww-daemon (or swww init)")]
+    DaemonNotRunning,
+    #[error("swww cannot open {}: {detail}", .path.display())]
+    UnsupportedImage { path: PathBuf, detail: String },
+    #[error("Lost the connection to swww-daemon: {0}")]
+    Socket(String),
+    #[error("swww command failed: {0}")]
+    Failed(String),
+}
+
+impl SwwwError {
+    /// The swww error behind `error`, if it came from swww
+    pub fn find(error: &anyhow::Error) -> Option<&SwwwError> {
+        error.chain().find_map(|cause| cause.downcast_ref())
+    }
+
+    /// What went wrong going by swww's output for `swww img path`
+    fn classify(path: &Path, output: &str) -> SwwwError {
+        let lower = output.to_lowercase();
+        let detail = output.lines().rev().map(str::trim).find(|l| !l.is_empty()).unwrap_or("no output");
+        let detail = detail.strip_prefix("Error: ").unwrap_or(detail).trim_matches('"').to_string();
+        if lower.contains("daemon is running")
+            || lower.contains("daemon running")
+            || lower.contains("connection refused")
+            || (lower.contains("socket") && (lower.contains("not found") || lower.contains("no such file")))
+        {
+            SwwwError::DaemonNotRunning
+        } else if lower.contains("image") && ["unsupported", "format", "decod", "failed to read", "failed to open"]
+            .iter()
+            .any(|w| lower.contains(w))
+        {
+            SwwwError::UnsupportedImage { path: path.to_path_buf(), detail }
+        } else if ["socket", "broken pipe", "connection reset"].iter().any(|w| lower.contains(w)) {
+            SwwwError::Socket(detail)
+        } else {
+            SwwwError::Failed(detail)
+        }
+    }
+}
+
+/// Number of trailing lines of swww's output kept in the log
+const OUTPUT_TAIL: usize = 5;
+
+/// Last `OUTPUT_TAIL` lines of `output`
+fn tail(output: &str) -> String {
+    let lines: Vec<&str> = output.lines().filter(|l| !l.trim().is_empty()).collect();
+    lines[lines.len().saturating_sub(OUTPUT_TAIL)..].join(" | ")
+}
+
+/// Number of profiles whose wallpapers are kept after switching away
+const CACHED_COLLECTIONS: usize = 4;
+
+/// Number of wallpapers `step_back` can go back through
+const HISTORY_LEN: usize = 20;
+
+/// A profile's wallpapers, put together from the scans of its directories
+#[derive(Debug, Default)]
+struct Collection {
+    dirs: Vec<PathBuf>,
+    /// Whether matched sets were looked for
+    with_sets: bool,
+    /// [`ScanCache::generation`] of `dirs` it was built from
+    generation: u64,
+    /// Images and matched sets
+    wallpapers: PathList,
+    /// Matched set directories and the outputs each covers
+    sets: HashMap<PathBuf, Vec<String>>,
+}
+
+impl Collection {
+    fn new(dirs: Vec<PathBuf>, profile: &Profile, generation: u64, images: PathList, sets: Vec<(PathBuf, Vec<String>)>) -> Self {
+        let mut wallpapers = images;
+        wallpapers.extend(sets.iter().map(|(dir, _)| dir));
+        Self { dirs, with_sets: profile.sets, generation, wallpapers, sets: sets.into_iter().collect() }
+    }
+
+    fn is_for(&self, dirs: &[PathBuf], with_sets: bool) -> bool {
+        self.dirs == dirs && self.with_sets == with_sets
+    }
+}
+
+/// Per-output replacements for a profile's transition settings
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputTransition {
+    pub transition: Option<String>,
+    pub duration: Option<u32>,
+    pub fps: Option<u32>,
+}
+
+impl Default for WallpaperManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WallpaperManager {
+    pub fn new() -> Self {
+        Self {
+            last_wallpaper: None,
+            output_wallpapers: Arc::default(),
+            history: Arc::default(),
+            shown: Arc::default(),
+            sequential_index: 0,
+            cache: Arc::default(),
+            collections: Arc::default(),
+            weather_tags: Vec::new(),
+            upcoming: None,
+            connected: Vec::new(),
+            output_transitions: HashMap::new(),
+            tint: None,
+            lighting: Lighting::Normal,
+            handoff: None,
+            scans: ScanCache::default(),
+            marks: Arc::default(),
+            cache_max_age: Duration::from_secs(60),
+            swww_timeout: Some(Duration::from_secs(6)),
+        }
+    }
+
+    pub fn get_wallpaper(&mut self, profile: &Profile, config: &Config) -> Result<String> {
+        // `ensure_cache` and its background refresh keep the list current;
+        // an empty one isn't read again here on every switch
+        let dirs = expand_dirs(profile);
+        if self.cache.dirs != dirs {
+            self.use_cached(&dirs, profile);
+        }
+        if self.cache.wallpapers.is_empty() || self.cache.dirs != dirs {
+            anyhow::bail!("No wallpapers found in configured directories");
+        }
+
+        // The pool is kept as positions in the cached list; paths are only
+        // built to test them and for the one picked
+        let cache = self.cache.clone();
+        let list = &cache.wallpapers;
+        let marks = self.marks();
+        let connected = &self.connected;
+        let pool: Vec<usize> = list
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| {
+                !marks.blocked.contains(p)
+                    && cache.sets.get(p).is_none_or(|covered| {
+                        connected.is_empty() || connected.iter().all(|o| covered.contains(o))
+                    })
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        let by_stats = profile.filters_stats() || config.readability.enabled();
+        let index = if by_stats || profile.filters_content() || self.lighting != Lighting::Normal {
+            WallpaperIndex::load()
+        } else {
+            WallpaperIndex::default()
+        };
+
+        // Text and face filters keep images off screen during screen
+        // sharing, so only images scanned and found clean are picked
+        let pool = if profile.filters_content() {
+            let clean = narrow(list, &pool, |p| profile.allows_content(index.text(p), index.faces(p)));
+            if clean.is_empty() {
+                anyhow::bail!("No wallpaper has been scanned and passed max_text/skip_faces yet");
+            }
+            clean
+        } else {
+            pool
+        };
+
+        let pool = if profile.filters_stats() {
+            let pool = screened(list, pool, |p| index.stats(p).map(|s| profile.allows(&s)));
+            if pool.is_empty() {
+                anyhow::bail!("No wallpaper passes the profile's luminance, contrast and busyness limits");
+            }
+            pool
+        } else {
+            pool
+        };
+
+        let pool = if config.readability.enabled() {
+            let pool = screened(list, pool, |p| index.stats(p).and_then(|s| config.readability.verdict(&s)));
+            if pool.is_empty() {
+                anyhow::bail!("No wallpaper is calm enough in the readability areas (max_clutter)");
+            }
+            pool
+        } else {
+            pool
+        };
+
+        let tags: Vec<&String> = config
+            .active_seasons(chrono::Local::now().date_naive())
+            .flat_map(|s| &s.tags)
+            .collect();
+        let pool = prefer(list, pool, |p| tags.iter().any(|t| has_tag(p, t)));
+
+        // The wallpaper of the day must not change with a roll of the dice
+        let daily = config.auto_switch.mode == SwitchMode::Daily;
+        let pool = if !daily && rand::random::<f64>() < config.weather.bias {
+            prefer(list, pool, |p| self.weather_tags.iter().any(|t| has_tag(p, t)))
+        } else {
+            pool
+        };
+
+        let pool = if !daily && self.lighting != Lighting::Normal && rand::random::<f64>() < config.ambient_light.bias {
+            prefer(list, pool, |p| index.stats(p).is_some_and(|s| self.lighting.suits(s.luminance)))
+        } else {
+            pool
+        };
+
+        if pool.is_empty() {
+            anyhow::bail!("No wallpapers found in configured directories");
+        }
+        let path = |i: usize| list.get(i).expect("pool holds positions in the list");
+
+        // Newly added images go first, as long as everything above allows them
+        if config.auto_switch.prefer_new
+            && !daily
+            && let Some(fresh) = self.pick_fresh(&dirs, list, &pool) {
+                info!("Picking newly added wallpaper: {:?}", fresh);
+                return Ok(fresh.to_string_lossy().to_string());
+            }
+
+        // The preloaded pick only stands while the pool still allows it
+        if let Some(next) = self.upcoming.take() {
+            if pool.iter().any(|&i| path(i) == next) {
+                return Ok(next.to_string_lossy().to_string());
+            }
+            debug!("Discarding preloaded {:?}, no longer in the pool", next);
+        }
+
+        // if only one wallpaper, just return it
+        if pool.len() == 1 {
+            return Ok(path(pool[0]).to_string_lossy().to_string());
+        }
+
+        let chosen_path = match config.auto_switch.mode {
+            SwitchMode::Random => {
+                // Favorites are in the pool twice
+                let favorites = narrow(list, &pool, |p| marks.favorites.contains(p));
+                let weighted: Vec<usize> = pool.iter().chain(&favorites).copied().collect();
+                // use rand::random::<u32>() % len to avoid thread_rng/gen_range deprecation warnings
+                let mut attempts = 0;
+                loop {
+                    let idx = (rand::random::<u32>() as usize) % weighted.len();
+                    let cand = path(weighted[idx]);
+                    if self.last_wallpaper.as_ref().map(|p| p != &cand).unwrap_or(true) {
+                        break cand;
+                    }
+                    attempts += 1;
+                    if attempts >= 8 {
+                        break cand;
+                    }
+                }
+            }
+            SwitchMode::Sequential => {
+                // advance at least one slot; choose first index not equal to last_wallpaper,
+                // starting after it when it is in the list
+                let after_last = self.last_wallpaper.as_ref()
+                    .and_then(|last| pool.iter().position(|&i| path(i) == *last))
+                    .map(|i| i + 1);
+                let mut start = after_last.unwrap_or(self.sequential_index) % pool.len();
+                let mut found = None;
+                for _ in 0..pool.len() {
+                    let cand = path(pool[start]);
+                    if self.last_wallpaper.as_ref().map(|p| p != &cand).unwrap_or(true) {
+                        found = Some(cand);
+                        // next time start from next position
+                        self.sequential_index = (start + 1) % pool.len();
+                        break;
+                    }
+                    start = (start + 1) % pool.len();
+                }
+                // fallback to current index if nothing found (shouldn't happen)
+                found.unwrap_or_else(|| {
+                    let idx = self.sequential_index % pool.len();
+                    let wp = path(pool[idx]);
+                    self.sequential_index = (self.sequential_index + 1) % pool.len();
+                    wp
+                })
+            }
+            SwitchMode::Daily => {
+                let today = chrono::Local::now().date_naive();
+                let idx = daily_index(&today.to_string(), &config.current_profile, pool.len());
+                path(pool[idx])
+            }
+        };
+
+        Ok(chosen_path.to_string_lossy().to_string())
+    }
+
+    pub async fn set_wallpaper(&mut self, path: &str, profile: &Profile) -> Result<()> {
+        self.set_outputs(&[(Vec::new(), path.to_string())], profile).await
+    }
+
+    /// Apply `(outputs, image)` pairs, an empty output list meaning every
+    /// output. Outputs sharing an image go through one `swww img --outputs`
+    /// call and all calls are started together, so transitions on different
+    /// monitors begin at the same instant instead of rippling across them.
+    /// A matched set in place of an image gives each output its own file,
+    /// and outputs with different transition settings get separate calls.
+    pub async fn set_outputs(&mut self, assignments: &[(Vec<String>, String)], profile: &Profile) -> Result<()> {
+        let (expanded, prepared) = self.show(assignments, profile).await?;
+
+        let marked = WallpaperIndex::update(|index| {
+            for (_, path) in assignments {
+                index.mark_shown(Path::new(path));
+            }
+        });
+        if let Err(e) = marked {
+            warn!("Failed to save wallpaper index: {}", e);
+        }
+        if let Some((_, path)) = assignments.first() {
+            self.last_wallpaper = Some(PathBuf::from(path));
+            self.remember(Path::new(path));
+            self.shown.lock().unwrap().insert(expand_dirs(profile), PathBuf::from(path));
+        }
+        let mut shown = self.output_wallpapers.lock().unwrap();
+        for (outputs, path) in &expanded {
+            if outputs.is_empty() {
+                // Every output, as far as they are known
+                shown.clear();
+                shown.extend(self.connected.iter().map(|o| (o.clone(), PathBuf::from(path))));
+            }
+            shown.extend(outputs.iter().map(|o| (o.clone(), PathBuf::from(path))));
+        }
+        drop(shown);
+        if let (Some(handoff), Some((_, path))) = (&self.handoff, prepared.first())
+            && let Err(e) = handoff::write(handoff, Path::new(path)) {
+                warn!("Failed to hand off wallpaper: {}", e);
+            }
+        Ok(())
+    }
+
+    /// Put `path` on every output without recording it in the history, the
+    /// index or the handoff file, for images that only stand in for a
+    /// while like the lock wallpaper
+    pub async fn show_briefly(&self, path: &str, profile: &Profile) -> Result<()> {
+        self.show(&[(Vec::new(), path.to_string())], profile).await.map(|_| ())
+    }
+
+    /// Run swww for `assignments`, returning them with sets expanded and
+    /// the files actually shown
+    async fn show(&self, assignments: &[(Vec<String>, String)], profile: &Profile) -> Result<(Assignments, Assignments)> {
+        let expanded = expand_sets(assignments)?;
+        let mut prepared = expanded.clone();
+        for (_, path) in &mut prepared {
+            *path = convert::prepare(Path::new(path), profile, self.tint).await.to_string_lossy().to_string();
+        }
+        // Converted copies are shown, but errors name the source image
+        let mut grouped: Vec<(Vec<String>, &str, &str, OutputTransition)> = Vec::new();
+        for ((outputs, path), (_, source)) in prepared.iter().zip(&expanded) {
+            for (outputs, transition) in split_by_transition(outputs, &self.output_transitions) {
+                match grouped.iter_mut().find(|(o, p, _, t)| {
+                    p == path && *t == transition && !o.is_empty() && !outputs.is_empty()
+                }) {
+                    Some((o, _, _, _)) => o.extend(outputs),
+                    None => grouped.push((outputs, path, source, transition)),
+                }
+            }
+        }
+
+        let results = futures::future::join_all(grouped.iter().map(|(outputs, path, source, transition)| {
+            swww_img(path, Path::new(source), outputs, profile, transition, self.swww_timeout)
+        })).await;
+        for result in results {
+            result?;
+        }
+        Ok((expanded, prepared))
+    }
+
+    /// Apply a picked wallpaper, spreading consecutive images across
+    /// `outputs` when per-monitor offsets or sync groups are in effect. A
+    /// matched set gives each output its own image; sets are never spread.
+    pub async fn apply(&mut self, path: &str, profile: &Profile, config: &Config, outputs: &[String]) -> Result<()> {
+        let routed = !profile.suffix_routes.is_empty() && !outputs.is_empty();
+        let mut assignments = if self.cache.sets.contains_key(Path::new(path)) {
+            vec![(outputs.to_vec(), path.to_string())]
+        } else if spreads(config, profile) && (outputs.len() > 1 || routed) {
+            offset_assignments(
+                &self.cache.wallpapers,
+                |p| !self.cache.sets.contains_key(p),
+                matches!(config.auto_switch.mode, SwitchMode::Random),
+                Path::new(path),
+                outputs,
+                &profile.sync_groups,
+                &profile.suffix_routes,
+            )
+        } else {
+            vec![(Vec::new(), path.to_string())]
+        };
+        if !profile.output_dirs.is_empty() {
+            self.give_own_images(&mut assignments, outputs, profile, config).await?;
+        }
+        self.set_outputs(&assignments, profile).await
+    }
+
+    /// Take the outputs with their own directories out of `assignments`
+    /// and give each an image from those directories. Outputs whose
+    /// directories have no images keep what they were assigned.
+    async fn give_own_images(
+        &self,
+        assignments: &mut Vec<(Vec<String>, String)>,
+        outputs: &[String],
+        profile: &Profile,
+        config: &Config,
+    ) -> Result<()> {
+        let mut own = Vec::new();
+        for output in outputs {
+            let Some(dirs) = profile.output_dirs.get(output) else {
+                continue;
+            };
+            match self.pick_for_output(output, dirs, config).await? {
+                Some(image) => own.push((vec![output.clone()], image.to_string_lossy().to_string())),
+                None => warn!("No wallpapers for {} in {:?}", output, dirs),
+            }
+        }
+        reassign(assignments, outputs, own);
+        Ok(())
+    }
+
+    /// Next image for `output` out of `dirs` by the switch mode, avoiding
+    /// the one it shows now
+    async fn pick_for_output(&self, output: &str, dirs: &[PathBuf], config: &Config) -> Result<Option<PathBuf>> {
+        let dirs: Vec<PathBuf> = dirs.iter().map(|d| expand_dir(d)).collect();
+        let scan = self.scans.get(&dirs, self.cache_max_age).await?;
+        scan.warn_broken();
+        let marks = self.marks();
+        let images: Vec<usize> = (0..scan.images.len()).collect();
+        let images = narrow(&scan.images, &images, |p| !marks.blocked.contains(p));
+        if images.is_empty() {
+            return Ok(None);
+        }
+
+        let current = self.output_wallpapers.lock().unwrap().get(output).cloned();
+        let position = current.and_then(|c| images.iter().position(|&i| scan.images.get(i).is_some_and(|p| p == c)));
+        let i = match config.auto_switch.mode {
+            SwitchMode::Random => {
+                let i = (rand::random::<u32>() as usize) % images.len();
+                if Some(i) == position { (i + 1) % images.len() } else { i }
+            }
+            SwitchMode::Sequential => position.map_or(0, |i| (i + 1) % images.len()),
+            SwitchMode::Daily => {
+                let today = chrono::Local::now().date_naive();
+                daily_index(&today.to_string(), &format!("{}/{}", config.current_profile, output), images.len())
+            }
+        };
+        Ok(scan.images.get(images[i]))
+    }
+
+    /// Choose the wallpaper for the next `get_wallpaper` call now and read
+    /// it in the background, so a large image is already in the page cache
+    /// when swww loads it
+    pub fn prepare_next(&mut self, profile: &Profile, config: &Config) -> Result<()> {
+        let next = PathBuf::from(self.get_wallpaper(profile, config)?);
+        self.upcoming = Some(next.clone());
+
+        tokio::task::spawn_blocking(move || {
+            let start = std::time::Instant::now();
+            match std::fs::File::open(&next).and_then(|mut f| std::io::copy(&mut f, &mut std::io::sink())) {
+                Ok(bytes) => debug!("Preloaded {:?} ({} bytes in {:.3}s)", next, bytes, start.elapsed().as_secs_f64()),
+                Err(e) => debug!("Failed to preload {:?}: {}", next, e),
+            }
+        });
+        Ok(())
+    }
+
+    /// Forget the wallpaper `prepare_next` picked
+    pub fn discard_upcoming(&mut self) {
+        self.upcoming = None;
+    }
+
+    pub fn last_wallpaper(&self) -> Option<&PathBuf> {
+        self.last_wallpaper.as_ref()
+    }
+
+    pub fn set_last_wallpaper(&mut self, path: PathBuf) {
+        self.last_wallpaper = Some(path);
+    }
+
+    /// The last wallpaper any clone set with `profile`
+    pub fn shown(&self, profile: &Profile) -> Option<PathBuf> {
+        self.shown.lock().unwrap().get(&expand_dirs(profile)).cloned()
+    }
+
+    /// Wallpapers set, the current one first
+    pub fn history(&self) -> Vec<PathBuf> {
+        self.history.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Drop the current wallpaper from the history, making the one it
+    /// replaced current, and return it. `None` when there is nothing to go
+    /// back to.
+    pub fn step_back(&mut self) -> Option<PathBuf> {
+        let mut history = self.history.lock().unwrap();
+        if history.len() < 2 {
+            return None;
+        }
+        history.pop_front();
+        history.front().cloned()
+    }
+
+    /// Drop history entries whose file or directory no longer exists, or
+    /// with `dry_run` only count them
+    pub fn prune_history(&mut self, dry_run: bool) -> usize {
+        let mut history = self.history.lock().unwrap();
+        let before = history.len();
+        if dry_run {
+            return history.iter().filter(|p| !p.exists()).count();
+        }
+        history.retain(|p| p.exists());
+        before - history.len()
+    }
+
+    /// Record `path` as the current wallpaper, unless it already is
+    pub fn remember(&mut self, path: &Path) {
+        let mut history = self.history.lock().unwrap();
+        if history.front().map(PathBuf::as_path) != Some(path) {
+            history.push_front(path.to_path_buf());
+            history.truncate(HISTORY_LEN);
+        }
+    }
+
+    /// Favorites and blocked wallpapers as marked now
+    fn marks(&self) -> Marks {
+        self.marks.lock().unwrap().get_or_insert_with(|| WallpaperIndex::load().marks()).clone()
+    }
+
+    /// Keep the marks in step with a change just saved to the index
+    pub fn mark(&self, path: &Path, favorite: Option<bool>, blocked: Option<bool>) {
+        if let Some(marks) = self.marks.lock().unwrap().as_mut() {
+            marks.set(path, favorite, blocked);
+        }
+    }
+
+    /// Image each output was given last, where known
+    pub fn output_wallpapers(&self) -> BTreeMap<String, PathBuf> {
+        self.output_wallpapers.lock().unwrap().clone()
+    }
+
+    /// The cached wallpaper that was applied most recently, for restoring
+    /// after a restart
+    pub fn previously_shown(&self) -> Option<PathBuf> {
+        WallpaperIndex::load().last_shown(self.cache.wallpapers.iter())
+    }
+
+    pub fn set_weather_tags(&mut self, tags: Vec<String>) {
+        self.weather_tags = tags;
+    }
+
+    /// Outputs the next wallpaper is picked for, so matched sets that don't
+    /// cover all of them are left out
+    pub fn set_connected(&mut self, outputs: &[String]) {
+        self.connected = outputs.to_vec();
+    }
+
+    /// Where to publish applied wallpapers, `None` to stop
+    pub fn set_handoff(&mut self, handoff: Option<Handoff>) {
+        self.handoff = handoff;
+    }
+
+    pub fn set_lighting(&mut self, lighting: Lighting) {
+        self.lighting = lighting;
+    }
+
+    /// Images and matched sets of the current cache
+    pub fn wallpapers(&self) -> &PathList {
+        &self.cache.wallpapers
+    }
+
+    pub fn scans(&self) -> &ScanCache {
+        &self.scans
+    }
+
+    /// Take the settings that come from the config, again on every reload
+    pub fn configure(&mut self, config: &Config) {
+        self.cache_max_age = Duration::from_secs(config.cache_max_age_secs);
+        self.swww_timeout = (config.swww_timeout_secs > 0).then(|| Duration::from_secs(config.swww_timeout_secs));
+    }
+
+    pub fn set_tint(&mut self, strength: Option<f32>) {
+        self.tint = strength;
+    }
+
+    /// Transition settings to use instead of the profile's on some outputs
+    pub fn set_output_transitions(&mut self, transitions: HashMap<String, OutputTransition>) {
+        self.output_transitions = transitions;
+    }
+    
+    /// Re-read the profile directories; a no-op when none of them changed
+    pub fn refresh_cache(&mut self, profile: &Profile) -> Result<()> {
+        let dirs = expand_dirs(profile);
+        self.scans.refresh_blocking(&dirs);
+        if self.use_cached(&dirs, profile) {
+            return Ok(());
+        }
+        let generation = self.scans.generation(&dirs);
+        let sets = if profile.sets { scan_sets(&dirs) } else { Vec::new() };
+        let images = self.collect_wallpapers(&dirs)?;
+        self.keep(Collection::new(dirs, profile, generation, images, sets));
+        Ok(())
+    }
+
+    /// Switch to the kept wallpapers of `profile`, if there are any built
+    /// from `dirs` as they are scanned now
+    fn use_cached(&mut self, dirs: &[PathBuf], profile: &Profile) -> bool {
+        let generation = self.scans.generation(dirs);
+        let current = |c: &Collection| {
+            c.is_for(dirs, profile.sets) && c.generation == generation && !c.wallpapers.is_empty()
+        };
+        if current(&self.cache) {
+            return true;
+        }
+        let mut collections = self.collections.lock().unwrap();
+        let Some(found) = collections.iter().position(|c| current(c)).and_then(|i| collections.remove(i)) else {
+            return false;
+        };
+        debug!("Reusing the wallpapers of {:?}", dirs);
+        collections.push_front(found.clone());
+        self.cache = found;
+        true
+    }
+
+    /// Make `collection` the one in use, replacing an older one for the
+    /// same directories and dropping the least recently used beyond
+    /// [`CACHED_COLLECTIONS`]
+    fn keep(&mut self, collection: Collection) {
+        let collection = Arc::new(collection);
+        let mut collections = self.collections.lock().unwrap();
+        collections.retain(|c| !c.is_for(&collection.dirs, collection.with_sets));
+        collections.push_front(collection.clone());
+        collections.truncate(CACHED_COLLECTIONS);
+        self.cache = collection;
+    }
+
+    /// Bring the cache up to date without waiting on the disk, except for
+    /// directories that were never read. Directories older than the cache
+    /// age are read again in the background and picked up by a later call.
+    pub async fn ensure_cache(&mut self, profile: &Profile) -> Result<()> {
+        if generate::daily(profile).await {
+            return self.rescan(profile).await;
+        }
+        let dirs = expand_dirs(profile);
+        if self.use_cached(&dirs, profile) {
+            self.scans.refresh_stale(&dirs, self.cache_max_age);
+            return Ok(());
+        }
+        self.scans.load(&dirs, self.cache_max_age).await?;
+        self.store_scan(dirs, profile).await
+    }
+
+    /// Re-read the profile directories off the async runtime; the cache is
+    /// only rebuilt when one of them changed
+    pub async fn rescan(&mut self, profile: &Profile) -> Result<()> {
+        generate::daily(profile).await;
+        let dirs = expand_dirs(profile);
+        self.scans.refresh(&dirs).await?;
+        if self.use_cached(&dirs, profile) {
+            return Ok(());
+        }
+        self.store_scan(dirs, profile).await
+    }
+
+    /// Build and keep the wallpapers of `profile` from the scans of `dirs`
+    async fn store_scan(&mut self, dirs: Vec<PathBuf>, profile: &Profile) -> Result<()> {
+        // Taken before the view so a change made meanwhile isn't missed
+        let generation = self.scans.generation(&dirs);
+        let scan = self.scans.view(&dirs);
+        scan.warn_broken();
+        let sets = if profile.sets {
+            let dirs = dirs.clone();
+            tokio::task::spawn_blocking(move || scan_sets(&dirs))
+                .await
+                .map_err(|e| anyhow::anyhow!("Join error when collecting wallpapers: {}", e))?
+        } else {
+            Vec::new()
+        };
+
+        self.keep(Collection::new(dirs, profile, generation, scan.images, sets));
+        Ok(())
+    }
+
+    fn collect_wallpapers(&self, dirs: &[PathBuf]) -> Result<PathList> {
+        let scan = self.scans.view(dirs);
+        scan.warn_broken();
+        let wallpapers = scan.images;
+        info!("Found {} wallpapers", wallpapers.len());
+        Ok(wallpapers)
+    }
+
+    /// Newest image of `pool` added since its directory was first indexed
+    /// and not shown yet. The index is only written when `list` holds
+    /// images or `dirs` it hasn't recorded.
+    fn pick_fresh(&self, dirs: &[PathBuf], list: &PathList, pool: &[usize]) -> Option<PathBuf> {
+        let members = || pool.iter().filter_map(|&i| list.get(i));
+        let index = WallpaperIndex::load();
+        if !index.has_unseen(dirs, list.iter()) {
+            return index.newest_fresh(members());
+        }
+        let fresh = WallpaperIndex::update(|index| {
+            index.sync(dirs, list.iter());
+            index.newest_fresh(members())
+        });
+        fresh.unwrap_or_else(|e| {
+            warn!("Failed to save wallpaper index: {}", e);
+            None
+        })
+    }
+}
+
+/// Split `outputs` (empty meaning all) into lists sharing transition
+/// settings. All outputs stay one empty list when they agree.
+fn split_by_transition(
+    outputs: &[String],
+    transitions: &HashMap<String, OutputTransition>,
+) -> Vec<(Vec<String>, OutputTransition)> {
+    if transitions.is_empty() {
+        return vec![(outputs.to_vec(), OutputTransition::default())];
+    }
+    let mut targets = if outputs.is_empty() {
+        transitions.keys().cloned().collect()
+    } else {
+        outputs.to_vec()
+    };
+    targets.sort();
+
+    let mut split: Vec<(Vec<String>, OutputTransition)> = Vec::new();
+    for output in targets {
+        let transition = transitions.get(&output).cloned().unwrap_or_default();
+        match split.iter_mut().find(|(_, t)| *t == transition) {
+            Some((o, _)) => o.push(output),
+            None => split.push((vec![output], transition)),
+        }
+    }
+    if outputs.is_empty() && split.len() == 1 {
+        split[0].0.clear();
+    }
+    split
+}
+
+#[tracing::instrument(level = "debug", skip(profile))]
+async fn swww_img(
+    path: &str,
+    source: &Path,
+    outputs: &[String],
+    profile: &Profile,
+    overrides: &OutputTransition,
+    limit: Option<Duration>,
+) -> Result<()> {
+    if outputs.is_empty() {
+        info!("Setting wallpaper: {}", path);
+    } else {
+        info!("Setting wallpaper on {}: {}", outputs.join(","), path);
+    }
+
+    let mut cmd = Command::new("swww");
+    cmd.args([
+        "img",
+        path,
+        "--transition-type",
+        overrides.transition.as_ref().unwrap_or(&profile.transition),
+        "--transition-duration",
+        &overrides.duration.unwrap_or(profile.transition_duration).to_string(),
+    ]);
+    if let Some(fps) = overrides.fps.or(profile.transition_fps) {
+        cmd.args(["--transition-fps", &fps.to_string()]);
+    }
+    if let Some(bezier) = &profile.transition_bezier {
+        cmd.args(["--transition-bezier", bezier]);
+    }
+    if !outputs.is_empty() {
+        cmd.args(["--outputs", &outputs.join(",")]);
+    }
+
+    let output = match limit {
+        Some(limit) => timeout(limit, cmd.output()).await.map_err(|_| SwwwError::TimedOut(limit))?,
+        None => cmd.output().await,
+    };
+    let output = output.context("Failed to execute swww. Is swww daemon running? (swww init)")?;
+
+    if !output.status.success() {
+        // swww prints most errors to stderr, some to stdout
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let printed = if stderr.trim().is_empty() { stdout } else { stderr };
+        warn!("swww img {} exited with {}: {}", path, output.status, tail(&printed));
+        return Err(SwwwError::classify(source, &printed).into());
+    }
+    Ok(())
+}
+
+/// Whether switches for `profile` need to know the connected outputs
+pub fn per_monitor(config: &Config, profile: &Profile) -> bool {
+    profile.sets || !profile.output_dirs.is_empty() || spreads(config, profile)
+}
+
+/// Whether a picked image is spread across outputs as consecutive images
+/// rather than shown on all of them. Outputs with their own directories
+/// are taken out afterwards and don't make the others spread.
+fn spreads(config: &Config, profile: &Profile) -> bool {
+    !profile.sync_groups.is_empty()
+        || !profile.suffix_routes.is_empty()
+        || (matches!(config.auto_switch.mode, SwitchMode::Sequential) && config.auto_switch.per_monitor_offset)
+}
+
+/// Slot i gets the image i positions after `base` among the `usable` of
+/// `files`, where a slot is either a sync group or a single output outside
+/// any group. Slots are ordered by their first output name. With suffix
+/// routes, each slot only counts the images routed to its first output, so
+/// slots sharing a route still get different images. With `random`, the
+/// slots after the first of a route draw their own image instead, one
+/// another slot of the route doesn't have where possible.
+fn offset_assignments(
+    files: &PathList,
+    usable: impl Fn(&Path) -> bool,
+    random: bool,
+    base: &Path,
+    outputs: &[String],
+    groups: &[Vec<String>],
+    routes: &HashMap<String, Vec<String>>,
+) -> Vec<(Vec<String>, String)> {
+    let mut outputs = outputs.to_vec();
+    outputs.sort();
+
+    let mut slots: Vec<(Option<usize>, Vec<String>)> = Vec::new();
+    for output in outputs {
+        let group = groups.iter().position(|g| g.contains(&output));
+        match slots.iter_mut().find(|(g, _)| group.is_some() && *g == group) {
+            Some((_, members)) => members.push(output),
+            None => slots.push((group, vec![output])),
+        }
+    }
+
+    // Positions in `files` with each one's route
+    let candidates: Vec<(usize, Option<&str>)> = files
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| usable(f))
+        .map(|(i, f)| (i, route_of(routes, &f)))
+        .collect();
+    let Some(start) = candidates.iter().position(|&(i, _)| files.get(i).as_deref() == Some(base)) else {
+        let base = base.to_string_lossy().to_string();
+        return slots.into_iter().map(|(_, members)| (members, base.clone())).collect();
+    };
+    let rotated: Vec<(usize, Option<&str>)> = candidates[start..].iter().chain(&candidates[..start]).copied().collect();
+
+    let mut taken: HashMap<Vec<&str>, Vec<usize>> = HashMap::new();
+    slots
+        .into_iter()
+        .map(|(_, members)| {
+            let mut suffixes = routes_to(routes, &members[0]);
+            let mut pool: Vec<usize> = rotated
+                .iter()
+                .filter(|(_, route)| route.is_some_and(|s| suffixes.contains(&s)))
+                .map(|&(i, _)| i)
+                .collect();
+            if pool.is_empty() {
+                suffixes.clear();
+                pool = rotated.iter().filter(|(_, route)| route.is_none()).map(|&(i, _)| i).collect();
+            }
+            if pool.is_empty() {
+                pool = rotated.iter().map(|&(i, _)| i).collect();
+            }
+
+            let used = taken.entry(suffixes).or_default();
+            let image = if random && !used.is_empty() {
+                let free: Vec<usize> = pool.iter().copied().filter(|i| !used.contains(i)).collect();
+                let choices = if free.is_empty() { &pool } else { &free };
+                choices[(rand::random::<u32>() as usize) % choices.len()]
+            } else {
+                pool[used.len() % pool.len()]
+            };
+            used.push(image);
+            (members, files.get(image).unwrap_or_default().to_string_lossy().to_string())
+        })
+        .collect()
+}
+
+/// Move the outputs of `own` out of `assignments`, an empty output list
+/// there standing for all of `outputs`, and add `own`
+fn reassign(assignments: &mut Vec<(Vec<String>, String)>, outputs: &[String], own: Vec<(Vec<String>, String)>) {
+    if own.is_empty() {
+        return;
+    }
+    for (targets, _) in assignments.iter_mut() {
+        if targets.is_empty() {
+            *targets = outputs.to_vec();
+        }
+        targets.retain(|o| !own.iter().any(|(taken, _)| taken.contains(o)));
+    }
+    assignments.retain(|(targets, _)| !targets.is_empty());
+    assignments.extend(own);
+}
+
+/// Suffixes routed to `output`, sorted
+fn routes_to<'a>(routes: &'a HashMap<String, Vec<String>>, output: &str) -> Vec<&'a str> {
+    let mut suffixes: Vec<&str> = routes
+        .iter()
+        .filter(|(_, outputs)| outputs.iter().any(|o| o == output))
+        .map(|(suffix, _)| suffix.as_str())
+        .collect();
+    suffixes.sort();
+    suffixes
+}
+
+/// The routed suffix `path` ends in, e.g. `left` for `city_left.png`
+fn route_of<'a>(routes: &'a HashMap<String, Vec<String>>, path: &Path) -> Option<&'a str> {
+    let stem = path.file_stem()?.to_str()?.to_lowercase();
+    let (_, suffix) = stem.rsplit_once('_')?;
+    routes.keys().find(|key| key.eq_ignore_ascii_case(suffix)).map(String::as_str)
+}
+
+/// The positions in `pool` whose path in `list` passes `keep`
+fn narrow(list: &PathList, pool: &[usize], keep: impl Fn(&Path) -> bool) -> Vec<usize> {
+    pool.iter().copied().filter(|&i| list.get(i).is_some_and(|p| keep(&p))).collect()
+}
+
+/// [`narrow`] `pool` to the paths passing `keep`, unless none do
+fn prefer(list: &PathList, pool: Vec<usize>, keep: impl Fn(&Path) -> bool) -> Vec<usize> {
+    let preferred = narrow(list, &pool, keep);
+    if preferred.is_empty() { pool } else { preferred }
+}
+
+/// `pool` narrowed to the images `verdict` passes, or while none do, to
+/// those it has no verdict on yet (`None`). Rejected images are left out
+/// either way.
+fn screened(list: &PathList, pool: Vec<usize>, verdict: impl Fn(&Path) -> Option<bool>) -> Vec<usize> {
+    let passing = narrow(list, &pool, |p| verdict(p) == Some(true));
+    if passing.is_empty() { narrow(list, &pool, |p| verdict(p).is_none()) } else { passing }
+}
+
+/// Hash of date and profile so every machine picks the same index
+fn daily_index(date: &str, profile: &str, len: usize) -> usize {
+    let hash = fnv1a(date.bytes().chain([b'/']).chain(profile.bytes()));
+    (hash % len as u64) as usize
+}
+
+/// FNV-1a, stable across platforms and Rust versions
+pub(crate) fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// A wallpaper carries `tag` when any word of its path (directory names or
+/// file name split on punctuation) equals it, e.g. `spooky/pumpkin.png` or
+/// `pumpkin_spooky.png`.
+pub fn has_tag(path: &Path, tag: &str) -> bool {
+    path.iter().any(|component| {
+        component
+            .to_string_lossy()
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| word.eq_ignore_ascii_case(tag))
+    })
+}
+
+/// Wallpaper whose file name best matches `query`, ignoring case: an exact
+/// name (with or without extension) beats a prefix, a prefix beats a
+/// substring, and a substring beats the query's letters appearing in
+/// order. Ties go to the shorter name, then to the earlier wallpaper.
+pub fn find_by_name(wallpapers: impl IntoIterator<Item = PathBuf>, query: &str) -> Option<PathBuf> {
+    let query = query.to_lowercase();
+    wallpapers
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_lowercase();
+            let stem = path.file_stem()?.to_string_lossy().to_lowercase();
+            let rank = if name == query || stem == query {
+                0
+            } else if name.starts_with(&query) {
+                1
+            } else if name.contains(&query) {
+                2
+            } else if is_subsequence(&query, &name) {
+                3
+            } else {
+                return None;
+            };
+            Some(((rank, name.len()), path))
+        })
+        .min_by_key(|&(key, _)| key)
+        .map(|(_, path)| path)
+}
+
+fn is_subsequence(query: &str, name: &str) -> bool {
+    let mut chars = name.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+/// Connector names as Hyprland reports them (`DP-1`, `eDP-1`, `HDMI-A-1`)
+static CONNECTOR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(e?DP|HDMI-[A-Z]|DVI-[A-Z]|VGA|LVDS|DSI|Virtual|HEADLESS|WL|Unknown)-\d+$").unwrap()
+});
+
+/// Images of a matched set by output name, or None when `dir` isn't one:
+/// it must hold images and every one must be named after a connector
+pub fn set_images(dir: &Path) -> Option<Vec<(String, PathBuf)>> {
+    let images = scan_dirs(&[dir.to_path_buf()]);
+    if images.is_empty() {
+        return None;
+    }
+    images
+        .into_iter()
+        .map(|image| {
+            let output = image.file_stem()?.to_str()?.to_string();
+            CONNECTOR.is_match(&output).then_some((output, image))
+        })
+        .collect()
+}
+
+/// Matched set directories directly below `dirs`, with the outputs they cover
+pub fn scan_sets(dirs: &[PathBuf]) -> Vec<(PathBuf, Vec<String>)> {
+    let mut sets: Vec<_> = dirs
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|dir| {
+            let images = set_images(&dir)?;
+            Some((dir, images.into_iter().map(|(output, _)| output).collect()))
+        })
+        .collect();
+    sets.sort();
+    sets
+}
+
+/// Images, or matched sets, and the outputs to show them on
+type Assignments = Vec<(Vec<String>, String)>;
+
+/// Replace matched sets in `assignments` with one assignment per output.
+/// A set for every output (empty list) covers the outputs it has images for.
+fn expand_sets(assignments: &[(Vec<String>, String)]) -> Result<Assignments> {
+    let mut expanded = Vec::new();
+    for (outputs, path) in assignments {
+        let Some(images) = Path::new(path).is_dir().then(|| set_images(Path::new(path))).flatten() else {
+            expanded.push((outputs.clone(), path.clone()));
+            continue;
+        };
+
+        let before = expanded.len();
+        for (output, image) in images {
+            if outputs.is_empty() || outputs.iter().any(|o| o.eq_ignore_ascii_case(&output)) {
+                expanded.push((vec![output], image.to_string_lossy().to_string()));
+            }
+        }
+        if expanded.len() == before {
+            anyhow::bail!("Set {} has no image for {}", path, outputs.join(", "));
+        }
+    }
+    Ok(expanded)
+}
+
+/// The profile's directories, plus where its generated wallpapers go
+pub fn expand_dirs(profile: &Profile) -> Vec<PathBuf> {
+    profile
+        .wallpaper_dirs
+        .iter()
+        .map(|d| expand_dir(d))
+        .chain(generate::daily_dir(profile))
+        .collect()
+}
+
+/// `dir` with a leading `~` expanded
+pub fn expand_dir(dir: &Path) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(&dir.to_string_lossy()).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_by_name() {
+        let wallpapers: Vec<PathBuf> = ["/w/mt-fuji-dawn.jpg", "/w/fuji.png", "/w/Fjord_ice.jpg", "/w/forest.jpg"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let found = |query| find_by_name(wallpapers.iter().cloned(), query);
+        assert_eq!(found("FUJI"), Some(PathBuf::from("/w/fuji.png")));
+        assert_eq!(found("fuji-d"), Some(PathBuf::from("/w/mt-fuji-dawn.jpg")));
+        assert_eq!(found("fo"), Some(PathBuf::from("/w/forest.jpg")));
+        assert_eq!(found("fjice"), Some(PathBuf::from("/w/Fjord_ice.jpg")));
+        assert_eq!(found("xyz"), None);
+    }
+
+    #[test]
+    fn test_screened_keeps_rejected_out() {
+        let files: PathList = ["dark.png", "bright.png", "new.png"].into_iter().collect();
+        let verdict = |passes: bool| {
+            move |p: &Path| match p.to_str() {
+                Some("dark.png") => Some(passes),
+                Some("bright.png") => Some(false),
+                _ => None,
+            }
+        };
+        assert_eq!(screened(&files, vec![0, 1, 2], verdict(true)), [0]);
+        // Nothing passes: only the image not measured yet is left
+        assert_eq!(screened(&files, vec![0, 1, 2], verdict(false)), [2]);
+        assert!(screened(&files, vec![0, 1], verdict(false)).is_empty());
+    }
+
+    #[test]
+    fn test_offset_assignments_wrap() {
+        let files: PathList = ["a.png", "b.png", "c.png"].into_iter().collect();
+        let outputs = vec!["HDMI-A-1".to_string(), "DP-1".to_string()];
+
+        let assignments = offset_assignments(&files, |_| true, false, Path::new("c.png"), &outputs, &[], &HashMap::new());
+        assert_eq!(assignments, vec![
+            (vec!["DP-1".to_string()], "c.png".to_string()),
+            (vec!["HDMI-A-1".to_string()], "a.png".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_random_slots_draw_their_own_image() {
+        let files: PathList = ["a.png", "b.png", "c.png", "d.png"].into_iter().collect();
+        let outputs: Vec<String> = ["DP-1", "DP-2", "DP-3", "DP-4"].iter().map(|s| s.to_string()).collect();
+        let mut orders = std::collections::HashSet::new();
+        for _ in 0..20 {
+            let assignments = offset_assignments(&files, |_| true, true, Path::new("c.png"), &outputs, &[], &HashMap::new());
+            assert_eq!(assignments[0].1, "c.png");
+            let images: Vec<String> = assignments.into_iter().map(|(_, image)| image).collect();
+            let mut distinct = images.clone();
+            distinct.sort();
+            distinct.dedup();
+            assert_eq!(distinct.len(), 4);
+            orders.insert(images);
+        }
+        // Not just the neighbours of the pick in order
+        assert!(orders.len() > 1);
+    }
+
+    #[test]
+    fn test_sync_groups_share_an_image() {
+        let files: PathList = ["a.png", "b.png", "c.png"].into_iter().collect();
+        let outputs: Vec<String> = ["DP-1", "DP-2", "DP-3"].iter().map(|s| s.to_string()).collect();
+        let groups = vec![vec!["DP-1".to_string(), "DP-3".to_string()]];
+
+        let assignments = offset_assignments(&files, |_| true, false, Path::new("a.png"), &outputs, &groups, &HashMap::new());
+        assert_eq!(assignments, vec![
+            (vec!["DP-1".to_string(), "DP-3".to_string()], "a.png".to_string()),
+            (vec!["DP-2".to_string()], "b.png".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_swww_error_found_behind_context() {
+        let error = anyhow::Error::from(SwwwError::TimedOut(Duration::from_secs(6))).context("Failed to set wallpaper");
+        assert!(matches!(SwwwError::find(&error), Some(SwwwError::TimedOut(_))));
+        assert!(SwwwError::find(&anyhow::anyhow!("other")).is_none());
+    }
+
+    #[test]
+    fn test_swww_error_classified() {
+        let path = Path::new("/w/a.avif");
+        let classify = |output| SwwwError::classify(path, output);
+        assert!(matches!(
+            classify("Error: \"Socket file '/run/user/1000/wayland-1-swww-daemon.sock' not found. Make sure swww-daemon is running\""),
+            SwwwError::DaemonNotRunning
+        ));
+        assert!(matches!(classify("failed to connect: Connection refused (os error 111)"), SwwwError::DaemonNotRunning));
+        match classify("Error: failed to read image '/w/a.avif': The image format could not be determined\n") {
+            SwwwError::UnsupportedImage { detail, .. } => {
+                assert_eq!(detail, "failed to read image '/w/a.avif': The image format could not be determined")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(classify("Error: failed to write to socket: Broken pipe"), SwwwError::Socket(_)));
+        assert!(matches!(classify(""), SwwwError::Failed(d) if d == "no output"));
+        assert_eq!(tail("1\n2\n\n3\n4\n5\n6\n"), "2 | 3 | 4 | 5 | 6");
+    }
+
+    #[test]
+    fn test_reassign_own_outputs() {
+        let outputs: Vec<String> = ["DP-1", "DP-2", "DP-3"].iter().map(|s| s.to_string()).collect();
+        let mut assignments = vec![(Vec::new(), "a.png".to_string())];
+        reassign(&mut assignments, &outputs, vec![(vec!["DP-3".to_string()], "tall.png".to_string())]);
+        assert_eq!(assignments, vec![
+            (vec!["DP-1".to_string(), "DP-2".to_string()], "a.png".to_string()),
+            (vec!["DP-3".to_string()], "tall.png".to_string()),
+        ]);
+
+        let mut assignments = vec![(vec!["DP-3".to_string()], "b.png".to_string())];
+        reassign(&mut assignments, &outputs, vec![(vec!["DP-3".to_string()], "tall.png".to_string())]);
+        assert_eq!(assignments, vec![(vec!["DP-3".to_string()], "tall.png".to_string())]);
+    }
+
+    #[test]
+    fn test_suffix_routes() {
+        let files: PathList = ["a_left.png", "b.png", "c_vertical.png", "d.png", "e_left.png"].into_iter().collect();
+        let outputs: Vec<String> = ["DP-1", "DP-2", "DP-3", "HDMI-A-1"].iter().map(|s| s.to_string()).collect();
+        let routes = HashMap::from([
+            ("left".to_string(), vec!["DP-1".to_string()]),
+            ("vertical".to_string(), vec!["DP-3".to_string()]),
+            ("right".to_string(), vec!["HDMI-A-1".to_string()]),
+        ]);
+
+        let assignments = offset_assignments(&files, |_| true, false, Path::new("b.png"), &outputs, &[], &routes);
+        assert_eq!(assignments, vec![
+            (vec!["DP-1".to_string()], "e_left.png".to_string()),
+            (vec!["DP-2".to_string()], "b.png".to_string()),
+            (vec!["DP-3".to_string()], "c_vertical.png".to_string()),
+            // No _right images, so it takes the next unrouted one
+            (vec!["HDMI-A-1".to_string()], "d.png".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_split_by_transition() {
+        let fps = |fps| OutputTransition { fps: Some(fps), ..Default::default() };
+        let vrr = OutputTransition { transition: Some("simple".to_string()), ..fps(60) };
+        let transitions = HashMap::from([
+            ("DP-1".to_string(), fps(144)),
+            ("DP-2".to_string(), fps(60)),
+            ("HDMI-A-1".to_string(), fps(60)),
+            ("eDP-1".to_string(), vrr.clone()),
+        ]);
+        assert_eq!(split_by_transition(&[], &transitions), vec![
+            (vec!["DP-1".to_string()], fps(144)),
+            (vec!["DP-2".to_string(), "HDMI-A-1".to_string()], fps(60)),
+            (vec!["eDP-1".to_string()], vrr),
+        ]);
+        assert_eq!(split_by_transition(&["HDMI-A-1".to_string(), "DP-2".to_string()], &transitions), vec![
+            (vec!["DP-2".to_string(), "HDMI-A-1".to_string()], fps(60)),
+        ]);
+
+        let same = HashMap::from([("DP-1".to_string(), fps(60)), ("DP-2".to_string(), fps(60))]);
+        assert_eq!(split_by_transition(&[], &same), vec![(Vec::new(), fps(60))]);
+        assert_eq!(split_by_transition(&[], &HashMap::new()), vec![(Vec::new(), OutputTransition::default())]);
+    }
+
+    #[test]
+    fn test_sets_expand_per_output() {
+        let root = std::env::temp_dir().join(format!("swww-manager-sets-{}", std::process::id()));
+        let set = root.join("set01");
+        let loose = root.join("misc");
+        std::fs::create_dir_all(&set).unwrap();
+        std::fs::create_dir_all(&loose).unwrap();
+        for file in [set.join("DP-1.png"), set.join("eDP-1.png"), loose.join("DP-1.png"), loose.join("beach.png")] {
+            std::fs::write(file, b"").unwrap();
+        }
+
+        assert_eq!(scan_sets(std::slice::from_ref(&root)), vec![
+            (set.clone(), vec!["DP-1".to_string(), "eDP-1".to_string()]),
+        ]);
+
+        let path = set.to_string_lossy().to_string();
+        let expanded = expand_sets(&[(vec!["eDP-1".to_string(), "HDMI-A-1".to_string()], path.clone())]).unwrap();
+        assert_eq!(expanded, vec![
+            (vec!["eDP-1".to_string()], set.join("eDP-1.png").to_string_lossy().to_string()),
+        ]);
+        assert_eq!(expand_sets(&[(Vec::new(), path)]).unwrap().len(), 2);
+        assert!(expand_sets(&[(vec!["HDMI-A-1".to_string()], set.to_string_lossy().to_string())]).is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_history_steps_back() {
+        let mut manager = WallpaperManager::new();
+        assert_eq!(manager.step_back(), None);
+        for name in ["a.png", "b.png", "b.png", "c.png"] {
+            manager.remember(Path::new(name));
+        }
+        assert_eq!(manager.clone().history(), [PathBuf::from("c.png"), "b.png".into(), "a.png".into()]);
+
+        assert_eq!(manager.step_back(), Some("b.png".into()));
+        // Showing the one stepped back to doesn't add it again
+        manager.remember(Path::new("b.png"));
+        assert_eq!(manager.step_back(), Some("a.png".into()));
+        assert_eq!(manager.step_back(), None);
+
+        for i in 0..HISTORY_LEN + 5 {
+            manager.remember(Path::new(&format!("{}.png", i)));
+        }
+        assert_eq!(manager.history().len(), HISTORY_LEN);
+    }
+
+    #[test]
+    fn test_empty_collection_not_rescanned_on_pick() {
+        let root = std::env::temp_dir().join(format!("swww-manager-empty-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let profile = Profile { wallpaper_dirs: vec![root.clone()], ..Default::default() };
+
+        let mut manager = WallpaperManager::new();
+        manager.refresh_cache(&profile).unwrap();
+        // Only the next refresh picks up an image added now
+        std::fs::write(root.join("new.png"), b"").unwrap();
+        assert!(manager.get_wallpaper(&profile, &Config::default()).is_err());
+        manager.refresh_cache(&profile).unwrap();
+        assert!(manager.get_wallpaper(&profile, &Config::default()).is_ok());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_preloaded_pick_rechecked() {
+        let root = std::env::temp_dir().join(format!("swww-manager-upcoming-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        for name in ["a.png", "b.png"] {
+            std::fs::write(root.join(name), b"").unwrap();
+        }
+        let profile = Profile { wallpaper_dirs: vec![root.clone()], ..Default::default() };
+        let config = Config::default();
+
+        let mut manager = WallpaperManager::new();
+        manager.refresh_cache(&profile).unwrap();
+        manager.upcoming = Some(root.join("a.png"));
+        let mut marks = Marks::default();
+        marks.blocked.insert(root.join("a.png"));
+        *manager.marks.lock().unwrap() = Some(marks);
+        for _ in 0..10 {
+            assert_eq!(manager.get_wallpaper(&profile, &config).unwrap(), root.join("b.png").to_string_lossy());
+        }
+        assert_eq!(manager.upcoming, None);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_daily_pick_ignores_bias() {
+        let root = std::env::temp_dir().join(format!("swww-manager-daily-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        for name in ["rain-1.png", "rain-2.png", "sun-1.png", "sun-2.png", "sun-3.png"] {
+            std::fs::write(root.join(name), b"").unwrap();
+        }
+        let profile = Profile { wallpaper_dirs: vec![root.clone()], ..Default::default() };
+        let mut config = Config::default();
+        config.auto_switch.mode = SwitchMode::Daily;
+        config.weather.bias = 0.5;
+
+        let mut manager = WallpaperManager::new();
+        manager.refresh_cache(&profile).unwrap();
+        manager.set_weather_tags(vec!["rain".to_string()]);
+        let first = manager.get_wallpaper(&profile, &config).unwrap();
+        for _ in 0..20 {
+            assert_eq!(manager.get_wallpaper(&profile, &config).unwrap(), first);
+        }
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_collections_kept_per_profile() {
+        let root = std::env::temp_dir().join(format!("swww-manager-collections-{}", std::process::id()));
+        for dir in ["a", "b"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("x.png"), b"").unwrap();
+        }
+        let profile = |dir: &str| Profile { wallpaper_dirs: vec![root.join(dir)], ..Default::default() };
+
+        let mut manager = WallpaperManager::new();
+        manager.refresh_cache(&profile("a")).unwrap();
+        let a = manager.cache.clone();
+        manager.refresh_cache(&profile("b")).unwrap();
+        let b = manager.cache.clone();
+
+        // Switching back reuses the list, in clones too
+        manager.refresh_cache(&profile("a")).unwrap();
+        assert!(Arc::ptr_eq(&manager.cache, &a));
+        let mut other = manager.clone();
+        other.refresh_cache(&profile("b")).unwrap();
+        assert!(Arc::ptr_eq(&other.cache, &b));
+
+        // A changed directory is put together again
+        std::fs::write(root.join("a/y.png"), b"").unwrap();
+        other.refresh_cache(&profile("a")).unwrap();
+        assert!(!Arc::ptr_eq(&other.cache, &a));
+        assert!(other.wallpapers().contains(&root.join("a/y.png")));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
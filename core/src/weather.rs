@@ -0,0 +1,122 @@
+use crate::config::WeatherConfig;
+use anyhow::Result;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+#[cfg(feature = "weather")]
+use {anyhow::Context, tokio::process::Command, tokio::time::timeout};
+use tracing::{debug, warn};
+
+/// When a lookup was last tried, and the tags of the last one that worked
+type Observation = (Instant, Vec<String>);
+
+/// Cached Open-Meteo lookup shared between server clones
+#[derive(Clone, Default)]
+pub struct WeatherProvider {
+    cached: Arc<Mutex<Option<Observation>>>,
+}
+
+impl WeatherProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tags describing the current weather, e.g. `["rain", "night"]`.
+    /// Returns the last known tags (or none) when the lookup fails, and
+    /// doesn't try again until `refresh_minutes` have passed, so switches
+    /// made while offline don't each wait for curl.
+    pub async fn tags(&self, config: &WeatherConfig) -> Vec<String> {
+        if !config.enabled {
+            return Vec::new();
+        }
+
+        let mut cached = self.cached.lock().await;
+        let max_age = Duration::from_secs(config.refresh_minutes.max(1) * 60);
+        if let Some((fetched, tags)) = cached.as_ref()
+            && fetched.elapsed() < max_age {
+                return tags.clone();
+            }
+
+        match fetch(config).await {
+            Ok(tags) => {
+                debug!("Weather tags: {:?}", tags);
+                *cached = Some((Instant::now(), tags.clone()));
+                tags
+            }
+            Err(e) => {
+                warn!("Failed to fetch weather, trying again in {} min: {}", max_age.as_secs() / 60, e);
+                let tags = cached.take().map(|(_, t)| t).unwrap_or_default();
+                *cached = Some((Instant::now(), tags.clone()));
+                tags
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "weather"))]
+async fn fetch(_config: &WeatherConfig) -> Result<Vec<String>> {
+    anyhow::bail!("built without weather support (feature \"weather\")")
+}
+
+#[cfg(feature = "weather")]
+async fn fetch(config: &WeatherConfig) -> Result<Vec<String>> {
+    let url = format!(
+        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current=weather_code,is_day",
+        config.latitude, config.longitude
+    );
+
+    let cmd = Command::new("curl").args(["-fsS", "--max-time", "10", &url]).output();
+    let output = match timeout(Duration::from_secs(15), cmd).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(e).context("Failed to execute curl"),
+        Err(_) => anyhow::bail!("Weather request timed out"),
+    };
+
+    if !output.status.success() {
+        anyhow::bail!("Weather request failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let body: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse weather JSON")?;
+    let current = &body["current"];
+    let code = current["weather_code"].as_u64().context("Missing weather_code")?;
+    let is_day = current["is_day"].as_u64().unwrap_or(1) == 1;
+
+    let mut tags = vec![condition_for(code).to_string()];
+    if !is_day {
+        tags.push("night".to_string());
+    }
+    Ok(tags)
+}
+
+/// Map a WMO weather interpretation code to a tag
+#[cfg(feature = "weather")]
+fn condition_for(code: u64) -> &'static str {
+    match code {
+        0 | 1 => "clear",
+        2 | 3 => "cloudy",
+        45 | 48 => "fog",
+        51..=67 | 80..=82 => "rain",
+        71..=77 | 85 | 86 => "snow",
+        95..=99 => "storm",
+        _ => "cloudy",
+    }
+}
+
+#[cfg(all(test, feature = "weather"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_condition_for() {
+        assert_eq!(condition_for(0), "clear");
+        assert_eq!(condition_for(3), "cloudy");
+        assert_eq!(condition_for(48), "fog");
+        assert_eq!(condition_for(61), "rain");
+        assert_eq!(condition_for(81), "rain");
+        assert_eq!(condition_for(75), "snow");
+        assert_eq!(condition_for(86), "snow");
+        assert_eq!(condition_for(95), "storm");
+        assert_eq!(condition_for(200), "cloudy");
+    }
+}
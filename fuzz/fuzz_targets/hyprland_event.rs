@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use swww_manager::hyprland_event::EventListener;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = EventListener::parse_event(&String::from_utf8_lossy(data));
+});
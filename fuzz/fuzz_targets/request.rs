@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use swww_manager::protocol::Request;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(request) = serde_json::from_slice::<Request>(data) {
+        let json = serde_json::to_vec(&request).unwrap();
+        serde_json::from_slice::<Request>(&json).unwrap();
+    }
+});
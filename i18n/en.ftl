@@ -0,0 +1,161 @@
+# English (fallback for every key). Fluent-style `key = text` lines;
+# the text after "= " is used verbatim, including leading spaces.
+# Placeholders are written `{ $name }`.
+
+## Errors and connection hints
+error = Error: { $message }
+unexpected-response = Unexpected response
+connect-failed = Failed to connect to socket.
+connect-failed-running = Failed to connect to socket. Is the server running?
+connect-hint-systemd = - If you use systemd: try 'systemctl --user start swww-manager.socket'
+connect-hint-stale = - Stale socket file: rm -f { $path }
+connect-hint-serve = - Start foreground server: swww-manager serve
+version-mismatch = warning: daemon version { $daemon } differs from client version { $client }; restart the daemon to upgrade
+version-unknown = warning: daemon is older than this client and does not report its version; restart it to upgrade
+
+## Profiles
+profiles-title = Available Profiles
+profile-transition = { $transition } ({ $duration }s)
+switched-wallpaper = Switched to wallpaper: { $wallpaper }
+switched-profile = Switched to profile: { $profile }
+clipboard-saved = Saved clipboard image to { $path }
+profile-unchanged = Profile unchanged
+dirs-title = Wallpaper Directories
+dirs-broken-hint = Some wallpaper directories are missing or unreadable, run 'swww-manager list --detailed'
+dir-missing = missing
+dir-not-a-directory = not a directory
+dir-unreadable = unreadable
+dir-unreachable = { $count } can't be opened
+
+## Status
+status-title = Status
+status-offline = not available, switches are queued
+status-update = executable changed on disk, run 'swww-manager restart'
+seconds = { $secs }s
+none = None
+enabled = Enabled
+disabled = Disabled
+yes = Yes
+no = No
+
+## Auto-switch
+auto-title = Auto-switch Status
+auto-interval = { $secs }s ({ $minutes } minutes)
+
+## Detection
+detect-title = Detection Report
+
+## Labels and column headers
+label-profile = Profile
+label-channel = Channel
+label-wallpaper = Wallpaper
+label-output-wallpaper = Wallpaper ({ $output })
+label-auto-switch = Auto-switch
+label-monitors = Monitors
+label-compositor = Compositor
+label-uptime = Uptime
+label-pid = PID
+label-version = Version
+label-update = Update
+label-enabled = Enabled
+label-interval = Interval
+label-selected = Selected
+col-profile = Profile
+col-monitors = Monitors
+col-wallpapers = Wallpapers
+col-transition = Transition
+col-directory = Directory
+col-score = Score
+col-reasons = Reasons
+col-monitor = Monitor
+col-description = Description
+col-resolution = Resolution
+col-position = Position
+col-scale = Scale
+col-workspace = Workspace
+col-windows = Windows
+col-fullscreen = Fullscreen
+col-status = Status
+
+## Version and restart
+version-client = Client: { $version }
+version-daemon = Daemon: { $version }
+version-hyprland = Hyprland: { $version }
+restarted = Daemon restarted (PID { $pid }, version { $version })
+restart-timeout = Daemon did not come back within { $secs }s
+unknown = unknown
+
+## Daemon and setup
+daemon-started = swww-manager running in background (PID { $pid })
+daemon-logs =   Logs: { $path }
+init-exists = Config file already exists at: { $path }
+init-use-force = Use --force to overwrite
+init-done = Configuration initialized at: { $path }
+init-next-edit = Edit the file to customize your settings.
+init-next-service = Then enable the service:
+timeline-empty = No events recorded yet
+
+## Monitors
+monitors-title = Connected Monitors
+on = On
+off = Off
+monitors-watching = Watching for monitor changes... (Press Ctrl+C to exit)
+monitor-added = + Added: { $name } ({ $description })
+monitor-removed = - Removed: { $name } ({ $description })
+monitor-dpms-on = { $name } turned on
+monitor-dpms-off = { $name } turned off
+
+## Wallpapers
+needs-daemon = { $command } needs a running daemon
+marked-favorite = Marked as favorite: { $path }
+unmarked-favorite = Unmarked { $path }
+blocked = Blocked { $path }
+unblocked = Unblocked { $path }
+no-wallpapers = No wallpapers found in the profile's directories
+profile-not-found = Profile not found: { $profile }
+config-no-file = No config file at { $path }, using defaults
+
+## Slideshows, contact sheets and the gallery
+slideshow-written = Wrote { $count } images to { $path }
+sheet-written = Wrote { $count } numbered images to { $path }
+sheet-other-profile = Contact sheets number the current profile ({ $current }) for set --index; switch to { $profile } first
+sheet-no-imagemagick = ImageMagick is not installed (magick or montage)
+sheet-montage-failed = ImageMagick montage failed ({ $status })
+gallery-title = { $profile } ({ $count } wallpapers)
+gallery-favorite = Favorite
+gallery-block = Block
+gallery-crashed = The gallery window crashed
+
+## Usage report and cleanup
+report-title = Usage report ({ $count } images)
+report-profiles = Profiles:
+report-directories = Directories:
+report-most-shown = Most shown:
+report-least-shown = Least shown:
+report-none =   (none)
+report-usage =   { $shows }×  { $name }
+report-usage-images =   { $shows }×  { $name } ({ $images } images)
+report-never-shown = Never shown: { $count } image(s)
+report-more =   ... and { $count } more
+report-broken = Broken files: { $count }
+gc-summary = { $index } stale index entries, { $history } history entries, { $files } cached files ({ $mb } MB)
+gc-would-remove = Would remove { $summary }
+gc-removed = Removed { $summary }
+
+## Notifications
+notify-wallpaper = Wallpaper: { $name }
+notify-wallpaper-switched = Wallpaper switched
+notify-swww-slow = Wallpaper still loading, it may appear late
+notify-swww-daemon-down = swww-daemon is not running, start it with swww-daemon
+notify-swww-unsupported = swww cannot open { $name }
+notify-swww-socket = Lost the connection to swww-daemon, try restarting it
+notify-profile-switched = Profile switched
+notify-no-match = No matching profile
+notify-fell-back = fell back to { $profile }
+notify-auto-profile = New monitor setup
+notify-auto-profile-body = Created profile { $profile }, customize it in config.toml
+notify-ask-title = Monitor setup changed
+notify-ask-body = Switch to profile { $profile }?
+notify-ask-switch = Switch
+notify-ask-keep = Keep current
+notify-ask-fallback = profile { $profile } matches, run `swww-manager profile { $profile }` to switch
@@ -0,0 +1,159 @@
+# 正體中文。缺少的字串會退回英文。
+
+## Errors and connection hints
+error = 錯誤：{ $message }
+unexpected-response = 非預期的回應
+connect-failed = 無法連線到 socket。
+connect-failed-running = 無法連線到 socket。伺服器是否正在執行？
+connect-hint-systemd = - 若使用 systemd：請嘗試 'systemctl --user start swww-manager.socket'
+connect-hint-stale = - 殘留的 socket 檔案：rm -f { $path }
+connect-hint-serve = - 在前景啟動伺服器：swww-manager serve
+version-mismatch = 警告：daemon 版本 { $daemon } 與用戶端版本 { $client } 不同；請重新啟動 daemon 以完成升級
+version-unknown = 警告：daemon 比此用戶端舊，未回報版本；請重新啟動以完成升級
+
+## Profiles
+profiles-title = 可用的設定檔
+profile-transition = { $transition }（{ $duration } 秒）
+switched-wallpaper = 已切換桌布：{ $wallpaper }
+switched-profile = 已切換設定檔：{ $profile }
+clipboard-saved = 已將剪貼簿圖片儲存至 { $path }
+profile-unchanged = 設定檔未變更
+dirs-title = 桌布資料夾
+dirs-broken-hint = 部分桌布資料夾不存在或無法讀取，請執行 'swww-manager list --detailed'
+dir-missing = 不存在
+dir-not-a-directory = 不是資料夾
+dir-unreadable = 無法讀取
+dir-unreachable = { $count } 張圖片無法開啟
+
+## Status
+status-title = 狀態
+status-offline = 無法使用，切換已排入佇列
+status-update = 執行檔已在磁碟上變更，請執行 'swww-manager restart'
+seconds = { $secs } 秒
+none = 無
+enabled = 已啟用
+disabled = 已停用
+yes = 是
+no = 否
+
+## Auto-switch
+auto-title = 自動切換狀態
+auto-interval = { $secs } 秒（{ $minutes } 分鐘）
+
+## Detection
+detect-title = 偵測報告
+
+## Labels and column headers
+label-profile = 設定檔
+label-channel = 頻道
+label-wallpaper = 桌布
+label-output-wallpaper = 桌布（{ $output }）
+label-auto-switch = 自動切換
+label-monitors = 螢幕
+label-compositor = 合成器
+label-uptime = 執行時間
+label-pid = PID
+label-version = 版本
+label-update = 更新
+label-enabled = 啟用
+label-interval = 間隔
+label-selected = 選用
+col-profile = 設定檔
+col-monitors = 螢幕
+col-wallpapers = 桌布
+col-transition = 轉場
+col-directory = 資料夾
+col-score = 分數
+col-reasons = 原因
+col-monitor = 螢幕
+col-description = 描述
+col-resolution = 解析度
+col-position = 位置
+col-scale = 縮放
+col-workspace = 工作區
+col-windows = 視窗
+col-fullscreen = 全螢幕
+col-status = 狀態
+
+## Version and restart
+version-client = 用戶端：{ $version }
+version-daemon = Daemon：{ $version }
+version-hyprland = Hyprland：{ $version }
+restarted = Daemon 已重新啟動（PID { $pid }，版本 { $version }）
+restart-timeout = Daemon 未在 { $secs } 秒內恢復
+unknown = 未知
+
+## Daemon and setup
+daemon-started = swww-manager 已在背景執行（PID { $pid }）
+daemon-logs =   記錄檔：{ $path }
+init-exists = 設定檔已存在：{ $path }
+init-use-force = 使用 --force 覆寫
+init-done = 已建立設定檔：{ $path }
+init-next-edit = 編輯此檔案以自訂設定。
+init-next-service = 接著啟用服務：
+timeline-empty = 尚無任何事件記錄
+
+## Monitors
+monitors-title = 已連接的螢幕
+on = 開啟
+off = 關閉
+monitors-watching = 正在監看螢幕變更……（按 Ctrl+C 結束）
+monitor-added = + 新增：{ $name }（{ $description }）
+monitor-removed = - 移除：{ $name }（{ $description }）
+monitor-dpms-on = { $name } 已開啟
+monitor-dpms-off = { $name } 已關閉
+
+## Wallpapers
+needs-daemon = { $command } 需要執行中的 daemon
+marked-favorite = 已加入最愛：{ $path }
+unmarked-favorite = 已移出最愛：{ $path }
+blocked = 已封鎖：{ $path }
+unblocked = 已解除封鎖：{ $path }
+no-wallpapers = 設定檔的資料夾中找不到桌布
+profile-not-found = 找不到設定檔：{ $profile }
+config-no-file = { $path } 沒有設定檔，使用預設值
+
+## Slideshows, contact sheets and the gallery
+slideshow-written = 已將 { $count } 張圖片寫入 { $path }
+sheet-written = 已將 { $count } 張編號圖片寫入 { $path }
+sheet-other-profile = 縮圖總覽依目前的設定檔（{ $current }）為 set --index 編號；請先切換到 { $profile }
+sheet-no-imagemagick = 未安裝 ImageMagick（magick 或 montage）
+sheet-montage-failed = ImageMagick montage 失敗（{ $status }）
+gallery-title = { $profile }（{ $count } 張桌布）
+gallery-favorite = 最愛
+gallery-block = 封鎖
+gallery-crashed = 圖庫視窗當機了
+
+## Usage report and cleanup
+report-title = 使用報告（{ $count } 張圖片）
+report-profiles = 設定檔：
+report-directories = 資料夾：
+report-most-shown = 最常顯示：
+report-least-shown = 最少顯示：
+report-none =   （無）
+report-usage =   { $shows } 次  { $name }
+report-usage-images =   { $shows } 次  { $name }（{ $images } 張圖片）
+report-never-shown = 從未顯示：{ $count } 張圖片
+report-more =   ……還有 { $count } 張
+report-broken = 損壞的檔案：{ $count }
+gc-summary = { $index } 筆過期索引、{ $history } 筆歷史記錄、{ $files } 個快取檔案（{ $mb } MB）
+gc-would-remove = 將移除 { $summary }
+gc-removed = 已移除 { $summary }
+
+## Notifications
+notify-wallpaper = 桌布：{ $name }
+notify-wallpaper-switched = 已切換桌布
+notify-swww-slow = 桌布仍在載入，可能稍後才會出現
+notify-swww-daemon-down = swww-daemon 未執行，請以 swww-daemon 啟動
+notify-swww-unsupported = swww 無法開啟 { $name }
+notify-swww-socket = 與 swww-daemon 的連線中斷，請嘗試重新啟動
+notify-profile-switched = 已切換設定檔
+notify-no-match = 沒有相符的設定檔
+notify-fell-back = 改用 { $profile }
+notify-auto-profile = 新的螢幕配置
+notify-auto-profile-body = 已建立設定檔 { $profile }，可在 config.toml 中自訂
+notify-ask-title = 螢幕配置已變更
+notify-ask-body = 要切換到設定檔 { $profile } 嗎？
+notify-ask-switch = 切換
+notify-ask-keep = 保持目前設定
+notify-ask-fallback = 設定檔 { $profile } 相符，執行 `swww-manager profile { $profile }` 即可切換
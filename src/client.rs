@@ -1,16 +1,82 @@
-use crate::protocol::{Request, Response};
+use crate::protocol::{DirectoryInfo, DirectoryState, GcReport, ProfileInfo, Request, Response, StatusInfo, VersionInfo, WallpaperInfo};
+use crate::config::SwitchMode;
+use crate::daemon;
+use crate::i18n::t;
+use crate::output::{self, Mark};
+use comfy_table::Cell;
 use anyhow::Result;
 use tokio::net::UnixStream;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "remote")]
+use tokio::net::TcpStream;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::time::{sleep, timeout, Duration, Instant};
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::info;
 
+/// Ask the server to report how long each request took
+static TIMING: AtomicBool = AtomicBool::new(false);
+
+/// Server reached over TCP instead of the local socket
+struct Remote {
+    addr: String,
+    token: String,
+}
+
+static REMOTE: OnceLock<Remote> = OnceLock::new();
+
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
 pub struct Client {
-    stream: UnixStream,
+    stream: Box<dyn Connection>,
+}
+
+/// Status updates sent in answer to `Request::WatchStatus`
+pub struct StatusWatch {
+    lines: Lines<BufReader<Box<dyn Connection>>>,
+}
+
+impl StatusWatch {
+    /// The next status, `None` once the server closed the connection
+    pub async fn next(&mut self) -> Result<Option<StatusInfo>> {
+        let Some(line) = self.lines.next_line().await? else {
+            return Ok(None);
+        };
+        match serde_json::from_str(&line)? {
+            Response::Status { status } => Ok(Some(status)),
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
 }
 
 impl Client {
+    pub fn enable_timing() {
+        TIMING.store(true, Ordering::Relaxed);
+    }
+
+    /// Send every request to the server listening on `addr` (its
+    /// `listen_tcp` setting), authenticated with `token`
+    pub fn set_remote(addr: String, token: String) {
+        REMOTE.set(Remote { addr, token }).ok();
+    }
+
     pub async fn connect() -> Result<Self> {
+        if let Some(remote) = REMOTE.get() {
+            #[cfg(feature = "remote")]
+            {
+                let stream = TcpStream::connect(&remote.addr).await
+                    .map_err(|e| anyhow::anyhow!("Failed to connect to {}: {}", remote.addr, e))?;
+                return Ok(Self { stream: Box::new(stream) });
+            }
+            #[cfg(not(feature = "remote"))]
+            anyhow::bail!("Cannot reach {}: built without remote support (feature \"remote\")", remote.addr);
+        }
+
         let socket_path = Self::socket_path();
         let stream = UnixStream::connect(&socket_path).await.map_err(|_e| {
             use std::process::Command;
@@ -25,42 +91,66 @@ impl Client {
                 .args(["-x", "swww-manager"]).output()
                 .map(|o| String::from_utf8_lossy(&o.stdout).contains("serve"))
                 .unwrap_or(false);
+            let path = socket_path.display();
             let msg = match true {
                 _ if is_systemd =>
-                    "Failed to connect to socket. Is the server running?\n- If you use systemd: try 'systemctl --user start swww-manager.socket'\n".to_owned(),
+                    format!("{}\n{}\n", t!("connect-failed-running"), t!("connect-hint-systemd")),
                 _ if stale_exists && !has_fg =>
-                    format!("Failed to connect to socket.\n- Stale socket file: rm -f {}\n- Start foreground server: swww-manager serve\n", socket_path.display()),
+                    format!("{}\n{}\n{}\n", t!("connect-failed"), t!("connect-hint-stale", path = path), t!("connect-hint-serve")),
                 _ if stale_exists =>
-                    format!("Failed to connect to socket.\n- Stale socket file: rm -f {}\n", socket_path.display()),
+                    format!("{}\n{}\n", t!("connect-failed"), t!("connect-hint-stale", path = path)),
                 _ if !has_fg =>
-                    "Failed to connect to socket.\n- Start foreground server: swww-manager serve\n".to_owned(),
-                _ => "Failed to connect to socket. Is the server running?\n".to_owned()
+                    format!("{}\n{}\n", t!("connect-failed"), t!("connect-hint-serve")),
+                _ => format!("{}\n", t!("connect-failed-running")),
             };
             anyhow::anyhow!("{}", msg)
         })?;
         
-        Ok(Self { stream })
+        Ok(Self { stream: Box::new(stream) })
     }
 
     async fn send_request(&mut self, request: Request) -> Result<Response> {
-        let request_bytes = serde_json::to_vec(&request)?;
-        self.stream.write_all(&request_bytes).await?;
-        self.stream.flush().await?;
+        let request = if TIMING.load(Ordering::Relaxed) {
+            Request::Timed { request: Box::new(request) }
+        } else {
+            request
+        };
+        self.write_request(request).await?;
         
-        let mut buffer = vec![0u8; 8192];
-        let n = self.stream.read(&mut buffer).await?;
+        // The server closes the connection after its response
+        let mut buffer = Vec::new();
+        self.stream.read_to_end(&mut buffer).await?;
         
-        if n == 0 {
+        if buffer.is_empty() {
             anyhow::bail!("Server closed connection");
         }
         
-        let response: Response = serde_json::from_slice(&buffer[..n])?;
-        Ok(response)
+        let response: Response = serde_json::from_slice(&buffer)?;
+        match response {
+            Response::Timed { elapsed_ms, response } => {
+                eprintln!("(server took {} ms)", elapsed_ms);
+                Ok(*response)
+            }
+            response => Ok(response),
+        }
     }
 
-    pub async fn switch_wallpaper(&mut self, profile: Option<&str>) -> Result<()> {
+    async fn write_request(&mut self, request: Request) -> Result<()> {
+        let request = match REMOTE.get() {
+            Some(remote) => Request::Auth { token: remote.token.clone(), request: Box::new(request) },
+            None => request,
+        };
+        let request_bytes = serde_json::to_vec(&request)?;
+        self.stream.write_all(&request_bytes).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    pub async fn switch_wallpaper(&mut self, profile: Option<&str>, preset: Option<&str>, mode: Option<SwitchMode>) -> Result<()> {
         let request = Request::Switch { 
-            profile: profile.map(String::from) 
+            profile: profile.map(String::from),
+            preset: preset.map(String::from),
+            mode,
         };
         
         match self.send_request(request).await? {
@@ -69,9 +159,9 @@ impl Client {
                 Ok(())
             }
             Response::Error { message } => {
-                anyhow::bail!("Error: {}", message)
+                anyhow::bail!(t!("error", message = message))
             }
-            _ => anyhow::bail!("Unexpected response"),
+            _ => anyhow::bail!(t!("unexpected-response")),
         }
     }
 
@@ -86,9 +176,9 @@ impl Client {
                 Ok(())
             }
             Response::Error { message } => {
-                anyhow::bail!("Error: {}", message)
+                anyhow::bail!(t!("error", message = message))
             }
-            _ => anyhow::bail!("Unexpected response"),
+            _ => anyhow::bail!(t!("unexpected-response")),
         }
     }
 
@@ -97,68 +187,128 @@ impl Client {
         
         match self.send_request(request).await? {
             Response::ProfileList { profiles } => {
-                println!("\nAvailable Profiles:");
-                println!("{}", "─".repeat(70));
-                
-                for profile in profiles {
-                    let marker = if profile.is_current { "Founded" } else { " " };
-                    println!("\n[{}] {}", marker, profile.name);
-                    
-                    if detailed {
-                        println!("Monitors: {}", profile.monitors.join(", "));
-                        println!("Wallpapers: {}", profile.wallpaper_count);
-                        if let Some(transition) = &profile.transition {
-                            println!("Transition: {} ({}s)", 
-                                transition, profile.transition_duration.unwrap_or(2));
-                        }
+                output::heading(&t!("profiles-title"));
+                let mut table = output::table([
+                    String::new(),
+                    t!("col-profile"),
+                    t!("col-monitors"),
+                    t!("col-wallpapers"),
+                    t!("col-transition"),
+                ]);
+                for profile in &profiles {
+                    let marker = if profile.is_current { Mark::Ok.cell() } else { Cell::new("") };
+                    let monitors = if detailed {
+                        profile.monitors.join(", ")
                     } else {
-                        print!("{} monitor(s)", profile.monitors.len());
-                        println!("{} wallpaper(s)", profile.wallpaper_count);
-                    }
+                        profile.monitors.len().to_string()
+                    };
+                    let transition = profile.transition.as_ref()
+                        .map(|transition| t!("profile-transition",
+                            transition = transition, duration = profile.transition_duration.unwrap_or(2)))
+                        .unwrap_or_default();
+                    table.add_row(vec![
+                        marker,
+                        Cell::new(&profile.name),
+                        Cell::new(monitors),
+                        Cell::new(profile.wallpaper_count),
+                        Cell::new(transition),
+                    ]);
+                }
+                println!("{}", table);
+
+                if detailed {
+                    print_directories(&profiles);
+                } else if profiles.iter().flat_map(|p| &p.directories).any(is_broken) {
+                    output::warning(&t!("dirs-broken-hint"));
                 }
-                println!();
                 Ok(())
             }
             Response::Error { message } => {
-                anyhow::bail!("Error: {}", message)
+                anyhow::bail!(t!("error", message = message))
             }
-            _ => anyhow::bail!("Unexpected response"),
+            _ => anyhow::bail!(t!("unexpected-response")),
         }
     }
 
-    pub async fn get_status(&mut self, json: bool) -> Result<()> {
-        let request = Request::GetStatus;
+    /// Print the status as a table, as JSON, or filled into `format`
+    pub async fn get_status(&mut self, json: bool, format: Option<&str>) -> Result<()> {
+        let status = self.status().await?;
+        warn_version_mismatch(status.version.as_ref());
+        if json {
+            println!("{}", serde_json::to_string_pretty(&status)?);
+            return Ok(());
+        }
+        if let Some(format) = format {
+            let now = chrono::Utc::now().timestamp();
+            println!("{}", crate::status_format::render(format, &status, now)?);
+            return Ok(());
+        }
+
+        let file_name = |path: &str| {
+            std::path::Path::new(path).file_name().and_then(|n| n.to_str()).map(String::from)
+        };
+        let wallpaper = status.current_wallpaper
+            .as_deref()
+            .and_then(file_name)
+            .unwrap_or_else(|| t!("none"));
+        let mut rows = vec![
+            (t!("label-profile"), status.current_profile.clone()),
+        ];
+        if let Some(channel) = &status.channel {
+            rows.push((t!("label-channel"), channel.clone()));
+        }
+        // One row per output once they show different images
+        let mut shown: Vec<&String> = status.output_wallpapers.values().collect();
+        shown.dedup();
+        if shown.len() > 1 {
+            rows.extend(status.output_wallpapers.iter().map(|(output, path)| {
+                (t!("label-output-wallpaper", output = output), file_name(path).unwrap_or_else(|| path.clone()))
+            }));
+        } else {
+            rows.push((t!("label-wallpaper"), wallpaper));
+        }
+        rows.extend([
+            (t!("label-auto-switch"),
+                if status.auto_switch_enabled { t!("enabled") } else { t!("disabled") }),
+            (t!("label-monitors"), status.monitors.join(", ")),
+        ]);
+        if status.offline {
+            rows.push((t!("label-compositor"), t!("status-offline")));
+        }
+        rows.push((t!("label-uptime"), t!("seconds", secs = status.uptime_secs)));
+        if let Some(pid) = status.pid {
+            rows.push((t!("label-pid"), pid.to_string()));
+        }
+        if let Some(version) = &status.version {
+            rows.push((t!("label-version"), version.to_string()));
+        }
+        if status.binary_updated {
+            rows.push((t!("label-update"), t!("status-update")));
+        }
+
+        output::heading(&t!("status-title"));
+        output::key_values(&rows);
+        println!();
+        Ok(())
+    }
+
+    pub async fn set_auto_switch(&mut self, enabled: bool) -> Result<()> {
+        let request = Request::SetAutoSwitch { enabled };
         
         match self.send_request(request).await? {
-            Response::Status { status } => {
-                if json {
-                    println!("{}", serde_json::to_string_pretty(&status)?);
-                } else {
-                    println!("\nStatus:");
-                    println!("{}", "─".repeat(70));
-                    println!("Profile:      {}", status.current_profile);
-                    println!("Wallpaper:    {}", status.current_wallpaper
-                        .as_ref()
-                        .and_then(|p| std::path::Path::new(p).file_name())
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("None"));
-                    println!("Auto-switch:  {}", 
-                        if status.auto_switch_enabled { "Enabled" } else { "Disabled" });
-                    println!("Monitors:     {}", status.monitors.join(", "));
-                    println!("Uptime:       {}s", status.uptime_secs);
-                    println!();
-                }
+            Response::Success { message } => {
+                println!("{}", message);
                 Ok(())
             }
             Response::Error { message } => {
-                anyhow::bail!("Error: {}", message)
+                anyhow::bail!(t!("error", message = message))
             }
-            _ => anyhow::bail!("Unexpected response"),
+            _ => anyhow::bail!(t!("unexpected-response")),
         }
     }
 
-    pub async fn set_auto_switch(&mut self, enabled: bool) -> Result<()> {
-        let request = Request::SetAutoSwitch { enabled };
+    pub async fn set_channel(&mut self, name: Option<&str>) -> Result<()> {
+        let request = Request::SetChannel { name: name.map(String::from) };
         
         match self.send_request(request).await? {
             Response::Success { message } => {
@@ -166,9 +316,128 @@ impl Client {
                 Ok(())
             }
             Response::Error { message } => {
-                anyhow::bail!("Error: {}", message)
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    pub async fn set_wallpaper(&mut self, path: &str) -> Result<()> {
+        match self.send_request(Request::SetWallpaper { path: path.to_string() }).await? {
+            Response::Success { message } => {
+                println!("{}", message);
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    /// Go back to the wallpaper the current one replaced
+    pub async fn previous_wallpaper(&mut self) -> Result<()> {
+        match self.send_request(Request::PreviousWallpaper).await? {
+            Response::Success { message } => {
+                println!("{}", message);
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    /// Show the image numbered `index` by `contact-sheet`, i.e. entry
+    /// `index` (from 1) of what [`Client::list_wallpapers`] returns
+    pub async fn set_wallpaper_at(&mut self, index: usize) -> Result<()> {
+        match self.send_request(Request::SetWallpaperByIndex { index }).await? {
+            Response::Success { message } => {
+                println!("{}", message);
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    /// Show the image whose file name best matches `pattern`
+    pub async fn set_wallpaper_matching(&mut self, pattern: &str) -> Result<()> {
+        match self.send_request(Request::SetWallpaperByMatch { pattern: pattern.to_string() }).await? {
+            Response::Success { message } => {
+                println!("{}", message);
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    #[cfg_attr(not(feature = "gallery"), allow(dead_code))]
+    pub async fn list_wallpapers(&mut self) -> Result<(String, Vec<WallpaperInfo>)> {
+        match self.send_request(Request::ListWallpapers).await? {
+            Response::Wallpapers { profile, wallpapers } => Ok((profile, wallpapers)),
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    /// Have the daemon remove what refers to deleted wallpapers, including
+    /// its own history
+    pub async fn gc(&mut self, dry_run: bool) -> Result<GcReport> {
+        match self.send_request(Request::Gc { dry_run }).await? {
+            Response::Collected { report } => Ok(report),
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
             }
-            _ => anyhow::bail!("Unexpected response"),
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    pub async fn mark_wallpaper(&mut self, path: &str, favorite: Option<bool>, blocked: Option<bool>) -> Result<()> {
+        let request = Request::MarkWallpaper { path: path.to_string(), favorite, blocked };
+        
+        match self.send_request(request).await? {
+            Response::Success { .. } => Ok(()),
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    pub async fn test_transition(
+        &mut self,
+        preset: Option<&str>,
+        transition: Option<&str>,
+        duration: Option<u32>,
+        fps: Option<u32>,
+        bezier: Option<&str>,
+    ) -> Result<()> {
+        let request = Request::TestTransition {
+            preset: preset.map(String::from),
+            transition: transition.map(String::from),
+            duration,
+            fps,
+            bezier: bezier.map(String::from),
+        };
+        
+        match self.send_request(request).await? {
+            Response::Success { message } => {
+                println!("{}", message);
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
         }
     }
 
@@ -181,9 +450,9 @@ impl Client {
                 Ok(())
             }
             Response::Error { message } => {
-                anyhow::bail!("Error: {}", message)
+                anyhow::bail!(t!("error", message = message))
             }
-            _ => anyhow::bail!("Unexpected response"),
+            _ => anyhow::bail!(t!("unexpected-response")),
         }
     }
 
@@ -192,20 +461,21 @@ impl Client {
         
         match self.send_request(request).await? {
             Response::Status { status } => {
-                println!("\nAuto-switch Status:");
-                println!("{}", "─".repeat(70));
-                println!("Enabled:  {}", 
-                    if status.auto_switch_enabled { "Yes" } else { "No" });
+                let mut rows = vec![
+                    (t!("label-enabled"), if status.auto_switch_enabled { t!("yes") } else { t!("no") }),
+                ];
                 if let Some(interval) = status.auto_switch_interval {
-                    println!("Interval: {}s ({} minutes)", interval, interval / 60);
+                    rows.push((t!("label-interval"), t!("auto-interval", secs = interval, minutes = interval / 60)));
                 }
+                output::heading(&t!("auto-title"));
+                output::key_values(&rows);
                 println!();
                 Ok(())
             }
             Response::Error { message } => {
-                anyhow::bail!("Error: {}", message)
+                anyhow::bail!(t!("error", message = message))
             }
-            _ => anyhow::bail!("Unexpected response"),
+            _ => anyhow::bail!(t!("unexpected-response")),
         }
     }
 
@@ -219,12 +489,168 @@ impl Client {
                 Ok(())
             }
             Response::Error { message } => {
-                anyhow::bail!("Error: {}", message)
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    pub async fn explain_detection(&mut self) -> Result<()> {
+        let request = Request::ExplainDetection;
+        
+        match self.send_request(request).await? {
+            Response::DetectionReport { report } => {
+                output::heading(&t!("detect-title"));
+                output::key_values(&[
+                    (t!("label-monitors"), report.monitors.join(", ")),
+                    (t!("label-selected"), report.selected.clone().unwrap_or_else(|| t!("none"))),
+                ]);
+                
+                let mut table = output::table([
+                    String::new(),
+                    t!("col-profile"),
+                    t!("col-score"),
+                    t!("col-reasons"),
+                ]);
+                for profile in report.profiles {
+                    let marker = if report.selected.as_ref() == Some(&profile.name) {
+                        Mark::Ok
+                    } else if profile.matched {
+                        Mark::Partial
+                    } else {
+                        Mark::No
+                    };
+                    table.add_row(vec![
+                        marker.cell(),
+                        Cell::new(&profile.name),
+                        Cell::new(profile.score),
+                        Cell::new(profile.reasons.join("\n")),
+                    ]);
+                }
+                println!("{}", table);
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    pub async fn get_version(&mut self) -> Result<()> {
+        let request = Request::GetVersion;
+        
+        match self.send_request(request).await? {
+            Response::Version { version } => {
+                println!("{}", t!("version-client", version = VersionInfo::current()));
+                println!("{}", t!("version-daemon", version = version));
+                warn_version_mismatch(Some(&version));
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    pub async fn status(&mut self) -> Result<StatusInfo> {
+        match self.send_request(Request::GetStatus).await? {
+            Response::Status { status } => Ok(status),
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    /// Keep this connection open for the status now and after each change
+    pub async fn watch_status(mut self) -> Result<StatusWatch> {
+        self.write_request(Request::WatchStatus).await?;
+        Ok(StatusWatch { lines: BufReader::new(self.stream).lines() })
+    }
+
+    pub async fn get_config(&mut self, key: Option<&str>) -> Result<()> {
+        let request = Request::GetConfig { key: key.map(String::from) };
+        
+        match self.send_request(request).await? {
+            Response::Config { value } => {
+                match value {
+                    serde_json::Value::String(s) => println!("{}", s),
+                    other => println!("{}", serde_json::to_string_pretty(&other)?),
+                }
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    pub async fn set_config(&mut self, key: &str, value: &str) -> Result<()> {
+        let request = Request::SetConfig {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        
+        match self.send_request(request).await? {
+            Response::Success { message } => {
+                output::success(&message);
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+    }
+
+    pub async fn set_log_level(&mut self, level: &str) -> Result<()> {
+        let request = Request::SetLogLevel { level: level.to_string() };
+        
+        match self.send_request(request).await? {
+            Response::Success { message } => {
+                println!("{}", message);
+                Ok(())
+            }
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
             }
-            _ => anyhow::bail!("Unexpected response"),
+            _ => anyhow::bail!(t!("unexpected-response")),
         }
     }
 
+    /// Ask the daemon to restart and wait until a new instance answers
+    pub async fn restart(&mut self, wait: Duration) -> Result<()> {
+        let before = self.status().await?.started_at;
+        
+        let mut client = Self::connect().await?;
+        match client.send_request(Request::Restart).await? {
+            Response::Success { message } => println!("{}", message),
+            Response::Error { message } => {
+                anyhow::bail!(t!("error", message = message))
+            }
+            _ => anyhow::bail!(t!("unexpected-response")),
+        }
+        
+        let deadline = Instant::now() + wait;
+        while Instant::now() < deadline {
+            sleep(Duration::from_millis(250)).await;
+            
+            let Ok(mut client) = Self::connect().await else { continue };
+            if let Ok(Ok(status)) = timeout(Duration::from_secs(2), client.status()).await
+                && status.started_at != before {
+                    output::success(&t!("restarted",
+                        pid = status.pid.map(|p| p.to_string()).unwrap_or_else(|| "?".to_string()),
+                        version = status.version.map(|v| v.to_string()).unwrap_or_else(|| t!("unknown"))));
+                    return Ok(());
+                }
+        }
+        
+        anyhow::bail!(t!("restart-timeout", secs = wait.as_secs()))
+    }
+
     pub async fn reload_config(&mut self) -> Result<()> {
         let request = Request::ReloadConfig;
         
@@ -234,16 +660,73 @@ impl Client {
                 Ok(())
             }
             Response::Error { message } => {
-                anyhow::bail!("Error: {}", message)
+                anyhow::bail!(t!("error", message = message))
             }
-            _ => anyhow::bail!("Unexpected response"),
+            _ => anyhow::bail!(t!("unexpected-response")),
         }
     }
 
+    /// This session's server socket, falling back to the shared one that
+    /// systemd socket activation listens on
     fn socket_path() -> PathBuf {
-        let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
-            .unwrap_or_else(|_| format!("/run/user/{}", users::get_current_uid()));
-        
-        PathBuf::from(runtime_dir).join("swww-manager.sock")
+        let session = daemon::runtime_file("sock");
+        if session.exists() {
+            session
+        } else {
+            daemon::shared_runtime_file("sock")
+        }
+    }
+}
+
+fn is_broken(directory: &DirectoryInfo) -> bool {
+    directory.state != DirectoryState::Ok || directory.unreachable > 0
+}
+
+/// Every profile directory with its image count, marking ones that are
+/// missing, unreadable or hold images that can't be opened
+fn print_directories(profiles: &[ProfileInfo]) {
+    output::heading(&t!("dirs-title"));
+    let mut table = output::table([
+        String::new(),
+        t!("col-profile"),
+        t!("col-directory"),
+        t!("col-wallpapers"),
+        t!("col-status"),
+    ]);
+    for profile in profiles {
+        for directory in &profile.directories {
+            let (mark, status) = match directory.state {
+                DirectoryState::Ok if directory.unreachable > 0 => {
+                    (Mark::Partial, t!("dir-unreachable", count = directory.unreachable))
+                }
+                DirectoryState::Ok => (Mark::Ok, String::new()),
+                DirectoryState::Missing => (Mark::No, t!("dir-missing")),
+                DirectoryState::NotADirectory => (Mark::No, t!("dir-not-a-directory")),
+                DirectoryState::Unreadable => (Mark::No, t!("dir-unreadable")),
+            };
+            table.add_row(vec![
+                mark.cell(),
+                Cell::new(&profile.name),
+                Cell::new(&directory.path),
+                Cell::new(directory.wallpaper_count),
+                Cell::new(status),
+            ]);
+        }
+    }
+    println!("{}", table);
+}
+
+/// Warn when the running daemon was built from a different version than this
+/// client, typically because it wasn't restarted after an upgrade
+fn warn_version_mismatch(daemon: Option<&VersionInfo>) {
+    let client = VersionInfo::current();
+    match daemon {
+        Some(daemon) if daemon != &client => {
+            output::warning(&t!("version-mismatch", daemon = daemon, client = client));
+        }
+        None => {
+            output::warning(&t!("version-unknown"));
+        }
+        _ => {}
     }
 }
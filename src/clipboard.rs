@@ -0,0 +1,67 @@
+//! Images on the Wayland clipboard, read with wl-paste (which uses the
+//! wlr data-control protocol when the compositor offers it)
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+/// Clipboard types taken, most preferred first, with the file extension
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/webp", "webp"),
+    ("image/gif", "gif"),
+    ("image/bmp", "bmp"),
+];
+
+async fn wl_paste(args: &[&str]) -> Result<Vec<u8>> {
+    let cmd = Command::new("wl-paste").args(args).output();
+    let output = match timeout(Duration::from_secs(10), cmd).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!("wl-paste is not installed (wl-clipboard)")
+        }
+        Ok(Err(e)) => return Err(e).context("Failed to execute wl-paste"),
+        Err(_) => anyhow::bail!("wl-paste timed out"),
+    };
+    if !output.status.success() {
+        anyhow::bail!("wl-paste: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+/// The preferred image type among those the clipboard offers
+fn pick_type(offered: &str) -> Option<(&'static str, &'static str)> {
+    let offered: Vec<&str> = offered.lines().map(str::trim).collect();
+    IMAGE_TYPES.iter().copied().find(|(mime, _)| offered.contains(mime))
+}
+
+/// Save the clipboard image into `dir` and return its path
+pub async fn save_image(dir: &Path) -> Result<PathBuf> {
+    let offered = wl_paste(&["--list-types"]).await?;
+    let (mime, ext) = pick_type(&String::from_utf8_lossy(&offered))
+        .context("The clipboard holds no image")?;
+    let data = wl_paste(&["--no-newline", "--type", mime]).await?;
+    if data.is_empty() {
+        anyhow::bail!("The clipboard image is empty");
+    }
+
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    let name = format!("clipboard-{}.{}", chrono::Local::now().format("%Y%m%d-%H%M%S"), ext);
+    let path = dir.join(name);
+    std::fs::write(&path, data).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_type() {
+        assert_eq!(pick_type("text/html\nimage/jpeg\nimage/png\n"), Some(("image/png", "png")));
+        assert_eq!(pick_type("image/webp\n"), Some(("image/webp", "webp")));
+        assert_eq!(pick_type("text/plain\nUTF8_STRING\n"), None);
+    }
+}
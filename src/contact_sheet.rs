@@ -0,0 +1,80 @@
+use crate::i18n::t;
+use crate::scan::scan_dirs;
+use crate::thumbnail;
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tracing::debug;
+
+/// Size of each tile, thumbnails are fitted into it
+const TILE: &str = "320x180";
+
+/// Montage of `images` numbered from 1 in the given order, `columns` wide,
+/// written to `output`. Tiles come from the thumbnail cache where possible
+/// so large images are only decoded once; a matched set shows its first
+/// image.
+pub async fn render(images: &[PathBuf], output: &Path, columns: u32) -> Result<()> {
+    let mut tiles = Vec::with_capacity(images.len());
+    for image in images {
+        let first = image.is_dir().then(|| scan_dirs(std::slice::from_ref(image)).into_iter().next()).flatten();
+        let image = first.as_ref().unwrap_or(image);
+        match thumbnail::thumbnail(image).await {
+            Ok(preview) => tiles.push(preview.path),
+            Err(e) => {
+                debug!("No thumbnail of {:?}, using the image: {}", image, e);
+                let mut first_frame = image.as_os_str().to_owned();
+                first_frame.push("[0]");
+                tiles.push(PathBuf::from(first_frame));
+            }
+        }
+    }
+
+    let args = montage_args(&tiles, output, columns);
+    // ImageMagick 7 has montage as a `magick` subcommand, 6 as its own program
+    for (program, prefix) in [("magick", Some("montage")), ("montage", None)] {
+        let status = Command::new(program).args(prefix).args(&args).status().await;
+        match status {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => anyhow::bail!("{}", t!("sheet-montage-failed", status = status)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to execute {}", program)),
+        }
+    }
+    anyhow::bail!("{}", t!("sheet-no-imagemagick"))
+}
+
+pub(crate) fn montage_args(tiles: &[PathBuf], output: &Path, columns: u32) -> Vec<OsString> {
+    let mut args: Vec<OsString> = Vec::new();
+    for (i, tile) in tiles.iter().enumerate() {
+        args.push("-label".into());
+        args.push((i + 1).to_string().into());
+        args.push(tile.into());
+    }
+    for arg in [
+        "-tile", &format!("{}x", columns),
+        "-geometry", &format!("{}+8+8", TILE),
+        "-background", "#1e1e2e",
+        "-fill", "#cdd6f4",
+        "-pointsize", "20",
+    ] {
+        args.push(arg.into());
+    }
+    args.push(output.into());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_montage_args() {
+        let tiles = [PathBuf::from("/t/a.png"), PathBuf::from("/w/b.gif[0]")];
+        let args = montage_args(&tiles, Path::new("sheet.png"), 6);
+        let args: Vec<&str> = args.iter().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(&args[..6], ["-label", "1", "/t/a.png", "-label", "2", "/w/b.gif[0]"]);
+        assert_eq!(&args[6..8], ["-tile", "6x"]);
+        assert_eq!(args.last(), Some(&"sheet.png"));
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use tracing::debug;
+
+#[derive(Debug, Error)]
+pub enum SwitchError {
+    #[error("superseded by a newer switch request")]
+    Superseded,
+    #[error("compositor not available, switch queued until it appears")]
+    Queued,
+}
+
+#[derive(Default)]
+struct State {
+    last_applied: Option<Instant>,
+    generation: u64,
+}
+
+/// Enforces a minimum gap between applied wallpaper switches. Requests made
+/// during the cooldown wait for it to end; if several pile up, only the
+/// newest one goes through.
+#[derive(Clone, Default)]
+pub struct SwitchCooldown {
+    state: Arc<Mutex<State>>,
+}
+
+impl SwitchCooldown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn acquire(&self, cooldown: Duration) -> Result<(), SwitchError> {
+        let (generation, wait) = {
+            let mut state = self.state.lock().await;
+            state.generation += 1;
+            let wait = state
+                .last_applied
+                .map(|t| cooldown.saturating_sub(t.elapsed()))
+                .unwrap_or_default();
+            (state.generation, wait)
+        };
+
+        if !wait.is_zero() {
+            debug!("Switch cooldown active, waiting {:.3}s", wait.as_secs_f64());
+            tokio::time::sleep(wait).await;
+        }
+
+        let mut state = self.state.lock().await;
+        if state.generation != generation {
+            debug!("Switch request coalesced into a newer one");
+            return Err(SwitchError::Superseded);
+        }
+        state.last_applied = Some(Instant::now());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_coalesces() {
+        let cooldown = SwitchCooldown::new();
+        let gap = Duration::from_secs(5);
+        assert!(cooldown.acquire(gap).await.is_ok());
+
+        // Requests during the cooldown wait it out; only the newest applies
+        let start = Instant::now();
+        let burst = futures::future::join_all((0..5).map(|_| cooldown.acquire(gap))).await;
+        assert_eq!(burst.iter().filter(|r| r.is_ok()).count(), 1);
+        assert!(burst.iter().filter_map(|r| r.as_ref().err()).all(|e| matches!(e, SwitchError::Superseded)));
+        assert_eq!(start.elapsed(), gap);
+
+        // Once the cooldown has passed, a switch goes through at once
+        tokio::time::advance(gap).await;
+        let start = Instant::now();
+        assert!(cooldown.acquire(gap).await.is_ok());
+        assert!(start.elapsed().is_zero());
+    }
+}
@@ -0,0 +1,319 @@
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixListener;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info};
+
+/// Exit status asking systemd to start the service again, see
+/// `RestartForceExitStatus=` in the unit
+pub const RESTART_EXIT_CODE: i32 = 75;
+
+pub fn default_pidfile() -> PathBuf {
+    runtime_file("pid")
+}
+
+pub fn runtime_dir() -> PathBuf {
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .unwrap_or_else(|_| format!("/run/user/{}", users::get_current_uid()));
+
+    PathBuf::from(runtime_dir)
+}
+
+/// Identifies the graphical session, so two Hyprland sessions of the same
+/// user (e.g. on different TTYs) each run their own manager
+pub fn session_tag() -> Option<String> {
+    ["HYPRLAND_INSTANCE_SIGNATURE", "WAYLAND_DISPLAY"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+        .map(|tag| {
+            tag.chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+                .collect()
+        })
+}
+
+/// `swww-manager-<session>.<ext>` in the runtime directory, or
+/// `swww-manager.<ext>` outside a graphical session
+pub fn runtime_file(ext: &str) -> PathBuf {
+    match session_tag() {
+        Some(tag) => runtime_dir().join(format!("swww-manager-{}.{}", tag, ext)),
+        None => shared_runtime_file(ext),
+    }
+}
+
+/// The session-independent name, used by the systemd socket unit
+pub fn shared_runtime_file(ext: &str) -> PathBuf {
+    runtime_dir().join(format!("swww-manager.{}", ext))
+}
+
+pub fn default_log_file() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("swww-manager/swww-manager.log")
+}
+
+/// Start `args` as a detached copy of this executable in its own session,
+/// with stdout/stderr appended to `log_file`. Returns the child PID.
+pub fn spawn_detached(args: &[OsString], log_file: &Path) -> Result<u32> {
+    if let Some(parent) = log_file.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create log directory: {:?}", parent))?;
+    }
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)
+        .with_context(|| format!("Failed to open log file: {:?}", log_file))?;
+
+    let exe = std::env::current_exe().context("Could not determine executable path")?;
+    let mut cmd = Command::new(exe);
+    cmd.args(args)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+
+    // SAFETY: setsid is async-signal-safe and only affects the child
+    unsafe {
+        cmd.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = cmd.spawn().context("Failed to spawn daemon")?;
+
+    // Catch immediate failures such as an already running instance
+    std::thread::sleep(Duration::from_millis(300));
+    if let Some(status) = child.try_wait()? {
+        anyhow::bail!("Daemon exited immediately ({}), see {:?}", status, log_file);
+    }
+
+    info!("Daemon started with PID {}", child.id());
+    Ok(child.id())
+}
+
+pub fn write_pidfile(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("{}\n", std::process::id()))
+        .with_context(|| format!("Failed to write pidfile: {:?}", path))?;
+    debug!("Wrote pidfile {:?}", path);
+    Ok(())
+}
+
+pub fn remove_pidfile(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        debug!("Failed to remove pidfile {:?}: {}", path, e);
+    }
+}
+
+/// Make sure `path` is a socket we own before removing or reusing it; never
+/// follow a symlink or delete some other file that happens to have the name
+pub fn check_socket_path(path: &Path) -> Result<()> {
+    let meta = fs::symlink_metadata(path)
+        .with_context(|| format!("Failed to inspect socket path {:?}", path))?;
+    if meta.file_type().is_symlink() {
+        anyhow::bail!("Socket path {:?} is a symlink, refusing to use it", path);
+    }
+    if !meta.file_type().is_socket() {
+        anyhow::bail!("{:?} exists but is not a socket, refusing to remove it", path);
+    }
+    let uid = users::get_effective_uid();
+    if meta.uid() != uid {
+        anyhow::bail!("Socket {:?} is owned by uid {}, not by us (uid {})", path, meta.uid(), uid);
+    }
+    Ok(())
+}
+
+/// Remove our socket on shutdown, leaving anything that replaced it alone
+pub fn remove_socket(path: &Path) {
+    match check_socket_path(path) {
+        Ok(()) => {
+            if let Err(e) = fs::remove_file(path) {
+                debug!("Failed to remove socket file {:?}: {}", path, e);
+            }
+        }
+        Err(e) => debug!("Not removing socket: {}", e),
+    }
+}
+
+/// Bind a Unix socket only we can connect to. The mode comes from the umask
+/// at creation, so there is no window where the path is accessible and no
+/// chmod that could follow a swapped-in symlink.
+pub fn bind_private(path: &Path) -> Result<UnixListener> {
+    // SAFETY: umask only changes the process file mode mask
+    let old = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(old) };
+    let listener = listener.with_context(|| format!("Failed to bind socket at {:?}", path))?;
+    check_socket_path(path)?;
+    Ok(listener)
+}
+
+/// Equivalent of `sd_is_socket(fd, AF_UNIX, SOCK_STREAM, 1)`: whether an fd
+/// passed by the service manager is a listening Unix stream socket
+pub fn is_unix_listener(fd: RawFd) -> bool {
+    // SAFETY: only queries the fd; all out-pointers are valid locals
+    unsafe {
+        let mut stat: libc::stat = std::mem::zeroed();
+        if libc::fstat(fd, &mut stat) != 0 || stat.st_mode & libc::S_IFMT != libc::S_IFSOCK {
+            return false;
+        }
+
+        let int_option = |option| {
+            let mut value: libc::c_int = 0;
+            let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+            let ok = libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                option,
+                &mut value as *mut libc::c_int as *mut libc::c_void,
+                &mut len,
+            ) == 0;
+            ok.then_some(value)
+        };
+
+        let mut addr: libc::sockaddr_storage = std::mem::zeroed();
+        let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        if libc::getsockname(fd, &mut addr as *mut _ as *mut libc::sockaddr, &mut len) != 0 {
+            return false;
+        }
+
+        addr.ss_family as libc::c_int == libc::AF_UNIX
+            && int_option(libc::SO_TYPE) == Some(libc::SOCK_STREAM)
+            && int_option(libc::SO_ACCEPTCONN) == Some(1)
+    }
+}
+
+/// Exclusive `flock` on the runtime pidfile, held for the lifetime of the
+/// server. The kernel drops the lock when the process dies, so a file left
+/// behind by a crashed instance is simply taken over.
+pub struct InstanceLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    pub fn acquire(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open lock file: {:?}", path))?;
+
+        // SAFETY: the fd stays valid for as long as `file` is alive
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let mut owner = String::new();
+            file.read_to_string(&mut owner).ok();
+            anyhow::bail!(
+                "Another swww-manager instance is already running (PID {})",
+                owner.trim()
+            );
+        }
+
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{}", std::process::id())?;
+        file.flush()?;
+        debug!("Acquired instance lock {:?}", path);
+
+        Ok(Self { file, path: path.to_path_buf() })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // Truncate rather than unlink so a racing starter never locks a
+        // file that is about to disappear
+        if let Err(e) = self.file.set_len(0) {
+            debug!("Failed to clear lock file {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Identity of the executable on disk, used to notice upgrades
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExeStamp {
+    pub path: PathBuf,
+    inode: u64,
+    mtime: Option<SystemTime>,
+}
+
+impl ExeStamp {
+    pub fn current() -> Option<Self> {
+        let path = std::env::current_exe().ok()?;
+        Self::read(path)
+    }
+
+    fn read(path: PathBuf) -> Option<Self> {
+        let meta = fs::metadata(&path).ok()?;
+        Some(Self {
+            inode: meta.ino(),
+            mtime: meta.modified().ok(),
+            path,
+        })
+    }
+
+    /// Whether the file at the original path is no longer the one we started from
+    pub fn replaced(&self) -> bool {
+        Self::read(self.path.clone()).map(|now| now != *self).unwrap_or(false)
+    }
+}
+
+/// Replace the current process with a fresh copy of `exe`, keeping the
+/// original arguments. Only returns on failure.
+pub fn reexec(exe: &Path) -> anyhow::Error {
+    info!("Re-executing {:?}", exe);
+    let err = Command::new(exe)
+        .args(std::env::args_os().skip(1))
+        .exec();
+    anyhow::Error::new(err).context(format!("Failed to re-exec {:?}", exe))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn test_socket_checks() {
+        let dir = std::env::temp_dir().join(format!("swww-manager-sock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let sock = dir.join("test.sock");
+        let file = dir.join("file");
+        let link = dir.join("link.sock");
+        fs::write(&file, "").unwrap();
+
+        let listener = bind_private(&sock).unwrap();
+        assert_eq!(fs::metadata(&sock).unwrap().mode() & 0o777, 0o600);
+        assert!(is_unix_listener(listener.as_raw_fd()));
+        let stream = UnixStream::connect(&sock).unwrap();
+        assert!(!is_unix_listener(stream.as_raw_fd()));
+        assert!(!is_unix_listener(File::open(&file).unwrap().as_raw_fd()));
+
+        std::os::unix::fs::symlink(&sock, &link).unwrap();
+        assert!(check_socket_path(&sock).is_ok());
+        assert!(check_socket_path(&link).is_err());
+        assert!(check_socket_path(&file).is_err());
+
+        remove_socket(&file);
+        assert!(file.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const ENTRY: &str = "swww-manager.desktop";
+
+/// Image types offered to file managers' "Open with"
+const MIME_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+    "image/tiff",
+    "image/x-portable-anymap",
+    "image/x-tga",
+];
+
+pub fn applications_dir() -> Result<PathBuf> {
+    dirs::data_dir()
+        .map(|p| p.join("applications"))
+        .context("Could not determine applications directory")
+}
+
+/// Entry that sets the file opened with it as wallpaper. Hidden from
+/// launchers; it only shows up in file managers' "Open with" lists.
+pub fn desktop_entry(exe: &Path) -> String {
+    let exe = exe.display().to_string();
+    let exe = if exe.contains(char::is_whitespace) { format!("\"{}\"", exe) } else { exe };
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name=Set as Wallpaper (swww-manager)
+Comment=Show this image as wallpaper
+Exec={exe} open %f
+Icon=preferences-desktop-wallpaper
+Terminal=false
+NoDisplay=true
+MimeType={mime};
+Categories=Graphics;Settings;
+",
+        mime = MIME_TYPES.join(";"),
+    )
+}
+
+pub fn install(force: bool) -> Result<()> {
+    let dir = applications_dir()?;
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create {:?}", dir))?;
+
+    let path = dir.join(ENTRY);
+    if path.exists() && !force {
+        println!("{:?} already exists, use --force to overwrite", path);
+        return Ok(());
+    }
+    let exe = std::env::current_exe().context("Could not determine executable path")?;
+    fs::write(&path, desktop_entry(&exe))
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    crate::output::success(&format!("Wrote {:?}", path));
+
+    update_database(&dir);
+    Ok(())
+}
+
+pub fn uninstall() -> Result<()> {
+    let dir = applications_dir()?;
+    let path = dir.join(ENTRY);
+    if path.exists() {
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove {:?}", path))?;
+        crate::output::success(&format!("Removed {:?}", path));
+    }
+    update_database(&dir);
+    Ok(())
+}
+
+/// Refresh the MIME cache so file managers pick the entry up right away.
+/// Optional: without it they notice on their own, just later.
+fn update_database(dir: &Path) {
+    let _ = Command::new("update-desktop-database").arg(dir).status();
+}
+
+/// Local path for a command-line argument that may be a `file://` URI, as
+/// passed by file managers and drag-and-drop
+pub fn local_path(arg: &str) -> Result<PathBuf> {
+    let Some(rest) = arg.strip_prefix("file://") else {
+        return Ok(PathBuf::from(arg));
+    };
+    // file:///path or file://localhost/path
+    let path = match rest.find('/') {
+        Some(0) => rest,
+        Some(i) if &rest[..i] == "localhost" => &rest[i..],
+        _ => anyhow::bail!("Not a local file: {}", arg),
+    };
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    Ok(PathBuf::from(percent_decode(path)))
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(local_path("/a/b.png").unwrap(), PathBuf::from("/a/b.png"));
+        assert_eq!(local_path("file:///home/me/My%20Pics/%E6%A1%8C.png").unwrap(), PathBuf::from("/home/me/My Pics/桌.png"));
+        assert_eq!(local_path("file://localhost/tmp/a.jpg").unwrap(), PathBuf::from("/tmp/a.jpg"));
+        assert_eq!(local_path("file:///tmp/100%.png").unwrap(), PathBuf::from("/tmp/100%.png"));
+        assert!(local_path("file://otherhost/tmp/a.jpg").is_err());
+    }
+
+    #[test]
+    fn test_desktop_entry() {
+        let entry = desktop_entry(Path::new("/opt/my apps/swww-manager"));
+        assert!(entry.contains("Exec=\"/opt/my apps/swww-manager\" open %f\n"));
+        assert!(entry.contains("MimeType=image/png;image/jpeg;"));
+    }
+}
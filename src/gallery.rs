@@ -0,0 +1,168 @@
+use crate::client::Client;
+use crate::i18n::t;
+use crate::protocol::WallpaperInfo;
+use crate::thumbnail;
+use anyhow::Result;
+use gtk4 as gtk;
+use gtk::glib;
+use gtk::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+use tokio::runtime::{Handle, Runtime};
+use tracing::warn;
+
+const APP_ID: &str = "io.github.kaminzhi.SwwwManager.Gallery";
+
+/// Show the current profile's wallpapers until the window is closed. GTK
+/// runs on the calling thread, which must not be inside a Tokio runtime;
+/// requests to the daemon and thumbnailing go to a runtime of its own.
+pub fn run() -> Result<()> {
+    let owned = Runtime::new()?;
+    let runtime = owned.handle().clone();
+    let (profile, wallpapers) = runtime.block_on(async {
+        Client::connect().await?.list_wallpapers().await
+    })?;
+
+    let app = gtk::Application::builder().application_id(APP_ID).build();
+    app.connect_activate(move |app| build_window(app, &runtime, &profile, &wallpapers));
+    // The command line is ours, not GTK's
+    let _ = app.run_with_args::<&str>(&[]);
+    Ok(())
+}
+
+fn build_window(app: &gtk::Application, runtime: &Handle, profile: &str, wallpapers: &[WallpaperInfo]) {
+    let grid = gtk::FlowBox::builder()
+        .valign(gtk::Align::Start)
+        .selection_mode(gtk::SelectionMode::None)
+        .homogeneous(true)
+        .max_children_per_line(8)
+        .row_spacing(12)
+        .column_spacing(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .margin_start(12)
+        .margin_end(12)
+        .build();
+
+    let mut pictures = Vec::new();
+    for wallpaper in wallpapers {
+        let (card, picture) = card(runtime, wallpaper);
+        grid.append(&card);
+        pictures.push(picture);
+    }
+
+    // Thumbnails are made one at a time and shown as they come in
+    let (sender, receiver) = mpsc::channel::<(usize, PathBuf)>();
+    let paths: Vec<String> = wallpapers.iter().map(|w| w.path.clone()).collect();
+    runtime.spawn(async move {
+        for (i, path) in paths.iter().enumerate() {
+            match thumbnail::thumbnail(Path::new(path)).await {
+                Ok(preview) => {
+                    if sender.send((i, preview.path)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => warn!("No thumbnail of {}: {}", path, e),
+            }
+        }
+    });
+    glib::timeout_add_local(Duration::from_millis(100), move || loop {
+        match receiver.try_recv() {
+            Ok((i, path)) => pictures[i].set_filename(Some(&path)),
+            Err(mpsc::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+            Err(mpsc::TryRecvError::Disconnected) => return glib::ControlFlow::Break,
+        }
+    });
+
+    let scrolled = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .child(&grid)
+        .build();
+    let window = gtk::ApplicationWindow::builder()
+        .application(app)
+        .title(t!("gallery-title", profile = profile, count = wallpapers.len()))
+        .default_width(1100)
+        .default_height(720)
+        .child(&scrolled)
+        .build();
+    window.present();
+}
+
+/// Preview that applies the wallpaper when clicked, with its name and the
+/// favorite and block toggles below
+fn card(runtime: &Handle, wallpaper: &WallpaperInfo) -> (gtk::Box, gtk::Picture) {
+    let path = wallpaper.path.clone();
+    let name = Path::new(&path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.clone());
+
+    let picture = gtk::Picture::builder()
+        .content_fit(gtk::ContentFit::Cover)
+        .width_request(240)
+        .height_request(135)
+        .build();
+    let preview = gtk::Button::builder()
+        .child(&picture)
+        .tooltip_text(path.as_str())
+        .opacity(if wallpaper.blocked { 0.4 } else { 1.0 })
+        .build();
+    let (rt, p) = (runtime.clone(), path.clone());
+    preview.connect_clicked(move |_| show(&rt, &p));
+
+    let label = gtk::Label::builder()
+        .label(name.as_str())
+        .ellipsize(gtk::pango::EllipsizeMode::Middle)
+        .max_width_chars(24)
+        .hexpand(true)
+        .xalign(0.0)
+        .build();
+
+    let favorite = gtk::ToggleButton::builder()
+        .icon_name("starred-symbolic")
+        .tooltip_text(t!("gallery-favorite"))
+        .active(wallpaper.favorite)
+        .build();
+    let (rt, p) = (runtime.clone(), path.clone());
+    favorite.connect_toggled(move |button| mark(&rt, &p, Some(button.is_active()), None));
+
+    let block = gtk::ToggleButton::builder()
+        .icon_name("action-unavailable-symbolic")
+        .tooltip_text(t!("gallery-block"))
+        .active(wallpaper.blocked)
+        .build();
+    let (rt, p, dimmed) = (runtime.clone(), path, preview.clone());
+    block.connect_toggled(move |button| {
+        dimmed.set_opacity(if button.is_active() { 0.4 } else { 1.0 });
+        mark(&rt, &p, None, Some(button.is_active()));
+    });
+
+    let row = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+    row.append(&label);
+    row.append(&favorite);
+    row.append(&block);
+
+    let card = gtk::Box::new(gtk::Orientation::Vertical, 4);
+    card.append(&preview);
+    card.append(&row);
+    (card, picture)
+}
+
+fn show(runtime: &Handle, path: &str) {
+    let path = path.to_string();
+    runtime.spawn(async move {
+        if let Err(e) = async { Client::connect().await?.set_wallpaper(&path).await }.await {
+            warn!("Failed to set {}: {}", path, e);
+        }
+    });
+}
+
+fn mark(runtime: &Handle, path: &str, favorite: Option<bool>, blocked: Option<bool>) {
+    let path = path.to_string();
+    runtime.spawn(async move {
+        if let Err(e) = async { Client::connect().await?.mark_wallpaper(&path, favorite, blocked).await }.await {
+            warn!("Failed to mark {}: {}", path, e);
+        }
+    });
+}
@@ -1,23 +1,132 @@
-use anyhow::{Context, Result};
+//! Hyprland's event socket (`.socket2.sock`): one `EVENT>>DATA` line per
+//! event, parsed into [`HyprlandEvent`].
+//!
+//! Hyprland 0.34+ sends most events twice, the old form followed by a `v2`
+//! form that adds numeric IDs. Older versions only send the old form. The
+//! listener pairs them up, so every change is delivered once and carries
+//! IDs whenever Hyprland provides them.
+
+use anyhow::Result;
+use futures::{Stream, StreamExt};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+#[cfg(feature = "hyprland")]
 use tokio::net::UnixStream;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tracing::{info, warn, error};
-use std::path::PathBuf;
+use tracing::{debug, info, warn};
+#[cfg(feature = "hyprland")]
+use {anyhow::Context, std::path::PathBuf};
+
+/// How long to wait for the `v2` line after an old-style event
+const PAIR_WAIT: Duration = Duration::from_millis(50);
+/// Quiet period that ends a burst, e.g. while docking or undocking
+pub const BURST_WINDOW: Duration = Duration::from_millis(100);
+/// A burst is delivered after this many windows even if it goes on
+const MAX_BURST_WINDOWS: u32 = 10;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum HyprlandEvent {
-    MonitorAdded { id: String, name: String, description: String },
-    MonitorRemoved { id: String, name: String, description: String },
-    Workspace { id: String, name: String },
-    FocusedMon { monitor: String, workspace: String  },
+    /// `monitoradded`; the id and description come from `monitoraddedv2`
+    MonitorAdded { id: Option<i32>, name: String, description: Option<String> },
+    /// `monitorremoved`; the id and description come from `monitorremovedv2`
+    MonitorRemoved { id: Option<i32>, name: String, description: Option<String> },
+    /// The focused workspace changed (`workspace` / `workspacev2`)
+    Workspace { id: Option<i32>, name: String },
+    /// `focusedmon` names the workspace, `focusedmonv2` gives its id
+    FocusedMon { monitor: String, workspace: Option<String>, workspace_id: Option<i32> },
+    /// `moveworkspace` / `moveworkspacev2`
+    MoveWorkspace { id: Option<i32>, name: String, monitor: String },
     Other(String),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    MonitorAdded,
+    MonitorRemoved,
+    Workspace,
+    FocusedMon,
+    MoveWorkspace,
+    Other,
+}
+
+impl HyprlandEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            HyprlandEvent::MonitorAdded { .. } => EventKind::MonitorAdded,
+            HyprlandEvent::MonitorRemoved { .. } => EventKind::MonitorRemoved,
+            HyprlandEvent::Workspace { .. } => EventKind::Workspace,
+            HyprlandEvent::FocusedMon { .. } => EventKind::FocusedMon,
+            HyprlandEvent::MoveWorkspace { .. } => EventKind::MoveWorkspace,
+            HyprlandEvent::Other(_) => EventKind::Other,
+        }
+    }
+
+    /// Events with the same key supersede each other within a burst
+    fn coalesce_key(&self) -> (EventKind, Option<&str>) {
+        let subject = match self {
+            HyprlandEvent::MonitorAdded { name, .. } | HyprlandEvent::MonitorRemoved { name, .. } => Some(name),
+            HyprlandEvent::MoveWorkspace { name, .. } => Some(name),
+            HyprlandEvent::Other(line) => Some(line),
+            HyprlandEvent::Workspace { .. } | HyprlandEvent::FocusedMon { .. } => None,
+        };
+        (self.kind(), subject.map(String::as_str))
+    }
+
+    /// Old-style event that newer Hyprland follows up with a `v2` line
+    fn is_v1(&self) -> bool {
+        match self {
+            HyprlandEvent::MonitorAdded { id, .. }
+            | HyprlandEvent::MonitorRemoved { id, .. }
+            | HyprlandEvent::Workspace { id, .. }
+            | HyprlandEvent::MoveWorkspace { id, .. } => id.is_none(),
+            HyprlandEvent::FocusedMon { workspace_id, .. } => workspace_id.is_none(),
+            HyprlandEvent::Other(_) => false,
+        }
+    }
+
+    /// Whether this is the `v2` line for the old-style event `first`
+    fn completes(&self, first: &HyprlandEvent) -> bool {
+        match (first, self) {
+            (HyprlandEvent::MonitorAdded { id: None, name: a, .. }, HyprlandEvent::MonitorAdded { id: Some(_), name: b, .. })
+            | (HyprlandEvent::MonitorRemoved { id: None, name: a, .. }, HyprlandEvent::MonitorRemoved { id: Some(_), name: b, .. })
+            | (HyprlandEvent::Workspace { id: None, name: a }, HyprlandEvent::Workspace { id: Some(_), name: b })
+            | (HyprlandEvent::MoveWorkspace { id: None, name: a, .. }, HyprlandEvent::MoveWorkspace { id: Some(_), name: b, .. })
+            | (
+                HyprlandEvent::FocusedMon { monitor: a, workspace_id: None, .. },
+                HyprlandEvent::FocusedMon { monitor: b, workspace_id: Some(_), .. },
+            ) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Combine with the old-style event this `v2` line completes
+    fn merged_with(self, first: HyprlandEvent) -> HyprlandEvent {
+        match (self, first) {
+            (
+                HyprlandEvent::FocusedMon { monitor, workspace: None, workspace_id },
+                HyprlandEvent::FocusedMon { workspace, .. },
+            ) => HyprlandEvent::FocusedMon { monitor, workspace, workspace_id },
+            (event, _) => event,
+        }
+    }
+}
+
+type EventReader = BufReader<Box<dyn AsyncRead + Unpin + Send>>;
+
 pub struct EventListener {
-    reader: BufReader<UnixStream>,
+    reader: EventReader,
+    /// Bytes of the line being read, kept across an interrupted read
+    line: Vec<u8>,
+    /// Event read ahead while looking for a `v2` line
+    pending: Option<HyprlandEvent>,
 }
 
 impl EventListener {
+    #[cfg(not(feature = "hyprland"))]
+    pub async fn connect() -> Result<Self> {
+        anyhow::bail!("Built without Hyprland support (feature \"hyprland\")")
+    }
+
+    #[cfg(feature = "hyprland")]
     pub async fn connect() -> Result<Self> {
         let socket_path = Self::socket2_path()?;
         let stream = UnixStream::connect(&socket_path)
@@ -25,85 +134,138 @@ impl EventListener {
             .context("Failed to connect socket")?;
 
         info!("Connected socket at {:?}", socket_path);
-        
-        Ok(Self {
-            reader: BufReader::new(stream),
-        })
+        Ok(Self::from_reader(stream))
+    }
 
+    /// Read events from any byte source, e.g. a recorded transcript
+    #[cfg(any(feature = "hyprland", test))]
+    pub fn from_reader(reader: impl AsyncRead + Unpin + Send + 'static) -> Self {
+        Self {
+            reader: BufReader::new(Box::new(reader)),
+            line: Vec::new(),
+            pending: None,
+        }
     }
 
-    pub async fn next_event (&mut self) -> Result<Option<HyprlandEvent>> {
-        let mut line = String::new();
+    /// The remaining events as a stream. It ends when the socket closes or
+    /// a read fails; callers that want to keep going reconnect.
+    pub fn into_stream(self) -> impl Stream<Item = HyprlandEvent> + Send {
+        futures::stream::unfold(self, |mut listener| async move {
+            match listener.next_event().await {
+                Ok(Some(event)) => Some((event, listener)),
+                Ok(None) => None,
+                Err(e) => {
+                    warn!("{}", e);
+                    None
+                }
+            }
+        })
+    }
 
-        match self.reader.read_line(&mut line).await {
-            Ok(0) => Ok(None),
-            Ok(_) => {
-                let event = Self::parse_event(&line)?;
-                Ok(Some(event))
+    pub async fn next_event(&mut self) -> Result<Option<HyprlandEvent>> {
+        let event = match self.pending.take() {
+            Some(event) => event,
+            None => match self.read_event().await? {
+                Some(event) => event,
+                None => return Ok(None),
             },
-            Err(e) => Err(anyhow::anyhow!("Failed to read from socket: {}", e)),
+        };
+        if !event.is_v1() {
+            return Ok(Some(event));
+        }
+
+        match tokio::time::timeout(PAIR_WAIT, self.read_event()).await {
+            Ok(Ok(Some(next))) if next.completes(&event) => Ok(Some(next.merged_with(event))),
+            Ok(Ok(next)) => {
+                self.pending = next;
+                Ok(Some(event))
+            }
+            Ok(Err(e)) => Err(e),
+            // Older Hyprland without v2 events
+            Err(_) => Ok(Some(event)),
         }
     }
 
-    fn parse_event(line: &str) -> Result<HyprlandEvent> {
-        let line = line.trim();
-        
-        if let Some((event_type, data)) = line.split_once(">>") {
-            let event = match event_type {
-                "monitoraddedv2" => {
-                    let parts: Vec<&str> = data.split(',').collect();
-                    if parts.len() >= 3 {
-                        HyprlandEvent::MonitorAdded {
-                            id: parts[0].to_string(),
-                            name: parts[1].to_string(),
-                            description: parts[2..].join(","),
-                        }
-                    } else {
-                        HyprlandEvent::Other(line.to_string())
-                    }
-                }
-                "monitorremovedv2" => {
-                    let parts: Vec<&str> = data.split(',').collect();
-                    if parts.len() >= 3 {
-                        HyprlandEvent::MonitorRemoved {
-                            id: parts[0].to_string(),
-                            name: parts[1].to_string(),
-                            description: parts[2..].join(","),
-                        }
-                    } else {
-                        HyprlandEvent::Other(line.to_string())
-                    }
-                }
-                "workspacev2" => {
-                    let parts: Vec<&str> = data.split(',').collect();
-                    if parts.len() >= 2 {
-                        HyprlandEvent::Workspace {
-                            id: parts[0].to_string(),
-                            name: parts[1].to_string(),
-                        }
-                    } else {
-                        HyprlandEvent::Other(line.to_string())
-                    }
-                }
-                "focusedmon" => {
-                    let parts: Vec<&str> = data.split(',').collect();
-                    if parts.len() >= 2 {
-                        HyprlandEvent::FocusedMon {
-                            monitor: parts[0].to_string(),
-                            workspace: parts[1].to_string(),
-                        }
-                    } else {
-                        HyprlandEvent::Other(line.to_string())
-                    }
-                }
-                _ => HyprlandEvent::Other(line.to_string()),
-            };
-            Ok(event)
-        } else {
-            Ok(HyprlandEvent::Other(line.to_string()))
+    async fn read_event(&mut self) -> Result<Option<HyprlandEvent>> {
+        let read = self.reader.read_until(b'\n', &mut self.line).await
+            .map_err(|e| anyhow::anyhow!("Failed to read from socket: {}", e))?;
+        if read == 0 && self.line.is_empty() {
+            return Ok(None);
         }
+
+        // Window titles and names can carry invalid UTF-8, don't
+        // drop the connection over them
+        let event = Self::parse_event(&String::from_utf8_lossy(&self.line))?;
+        self.line.clear();
+        Ok(Some(event))
     }
 
+    /// Parse one line of Hyprland's event socket. Unknown or malformed
+    /// lines become `HyprlandEvent::Other`.
+    pub fn parse_event(line: &str) -> Result<HyprlandEvent> {
+        let line = line.trim();
+        let Some((event_type, data)) = line.split_once(">>") else {
+            return Ok(HyprlandEvent::Other(line.to_string()));
+        };
+        let id = |s: &str| s.parse::<i32>().ok();
+        let parts: Vec<&str> = data.split(',').collect();
+
+        // Names may contain commas (descriptions and workspace names
+        // always can), so only the fields around them are split off
+        let event = match event_type {
+            "monitoradded" => Some(HyprlandEvent::MonitorAdded {
+                id: None,
+                name: data.to_string(),
+                description: None,
+            }),
+            "monitoraddedv2" if parts.len() >= 3 => id(parts[0]).map(|id| HyprlandEvent::MonitorAdded {
+                id: Some(id),
+                name: parts[1].to_string(),
+                description: Some(parts[2..].join(",")),
+            }),
+            "monitorremoved" => Some(HyprlandEvent::MonitorRemoved {
+                id: None,
+                name: data.to_string(),
+                description: None,
+            }),
+            "monitorremovedv2" if parts.len() >= 3 => id(parts[0]).map(|id| HyprlandEvent::MonitorRemoved {
+                id: Some(id),
+                name: parts[1].to_string(),
+                description: Some(parts[2..].join(",")),
+            }),
+            "workspace" => Some(HyprlandEvent::Workspace { id: None, name: data.to_string() }),
+            "workspacev2" if parts.len() >= 2 => id(parts[0]).map(|id| HyprlandEvent::Workspace {
+                id: Some(id),
+                name: parts[1..].join(","),
+            }),
+            "moveworkspace" => data.rsplit_once(',').map(|(name, monitor)| HyprlandEvent::MoveWorkspace {
+                id: None,
+                name: name.to_string(),
+                monitor: monitor.to_string(),
+            }),
+            "moveworkspacev2" if parts.len() >= 3 => id(parts[0]).map(|id| HyprlandEvent::MoveWorkspace {
+                id: Some(id),
+                name: parts[1..parts.len() - 1].join(","),
+                monitor: parts[parts.len() - 1].to_string(),
+            }),
+            "focusedmon" => data.split_once(',').map(|(monitor, workspace)| HyprlandEvent::FocusedMon {
+                monitor: monitor.to_string(),
+                workspace: Some(workspace.to_string()),
+                workspace_id: None,
+            }),
+            "focusedmonv2" => data.split_once(',').and_then(|(monitor, workspace)| {
+                id(workspace).map(|workspace_id| HyprlandEvent::FocusedMon {
+                    monitor: monitor.to_string(),
+                    workspace: None,
+                    workspace_id: Some(workspace_id),
+                })
+            }),
+            _ => None,
+        };
+        Ok(event.unwrap_or_else(|| HyprlandEvent::Other(line.to_string())))
+    }
+
+    #[cfg(feature = "hyprland")]
     fn socket2_path() -> Result<PathBuf> {
         let his = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
             .context("HYPRLAND_INSTANCE_SIGNATURE not set")?;
@@ -118,29 +280,221 @@ impl EventListener {
     }
 }
 
-pub async fn monitor_events<F>(mut handler: F) -> Result<()>
+/// Which events [`monitor_events`] delivers, and whether bursts are
+/// merged. The default delivers every event as it comes.
+#[derive(Debug, Clone, Default)]
+pub struct Subscription {
+    /// Empty means every kind
+    kinds: Vec<EventKind>,
+    coalesce: bool,
+}
+
+impl Subscription {
+    pub fn only(kinds: &[EventKind]) -> Self {
+        Self { kinds: kinds.to_vec(), ..Self::default() }
+    }
+
+    /// Hold events until [`BURST_WINDOW`] passes without another one, then
+    /// deliver only the latest of each kind (per monitor for hotplug, per
+    /// workspace for moves), in the order they last occurred
+    pub fn coalesced(mut self) -> Self {
+        self.coalesce = true;
+        self
+    }
+
+    fn wants(&self, event: &HyprlandEvent) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&event.kind())
+    }
+}
+
+/// Keep the last event for each coalescing key
+fn coalesce(burst: Vec<HyprlandEvent>) -> Vec<HyprlandEvent> {
+    let mut seen = std::collections::HashSet::new();
+    let mut latest: Vec<_> = burst
+        .iter()
+        .rev()
+        .filter(|event| seen.insert(event.coalesce_key()))
+        .cloned()
+        .collect();
+    latest.reverse();
+    latest
+}
+
+/// Run `handler` for every subscribed event, reconnecting whenever the
+/// stream ends
+pub async fn monitor_events<F>(subscription: Subscription, mut handler: F) -> Result<()>
 where
     F: FnMut(HyprlandEvent) -> futures::future::BoxFuture<'static, ()>,
 {
-    let mut listener = EventListener::connect().await?;
-    
     info!("Starting event monitoring...");
-    
+
     loop {
-        match listener.next_event().await {
-            Ok(Some(event)) => {
+        let events = EventListener::connect().await?
+            .into_stream()
+            .filter(|event| std::future::ready(subscription.wants(event)));
+        let mut events = std::pin::pin!(events);
+
+        if !subscription.coalesce {
+            while let Some(event) = events.next().await {
                 handler(event).await;
             }
-            Ok(None) => {
-                warn!("Event stream ended, reconnecting...");
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                listener = EventListener::connect().await?;
+        } else {
+            while let Some(first) = events.next().await {
+                let deadline = tokio::time::Instant::now() + BURST_WINDOW * MAX_BURST_WINDOWS;
+                let mut burst = vec![first];
+                let mut ended = false;
+                loop {
+                    let wait = BURST_WINDOW.min(deadline.saturating_duration_since(tokio::time::Instant::now()));
+                    match tokio::time::timeout(wait, events.next()).await {
+                        Ok(Some(event)) => burst.push(event),
+                        Ok(None) => {
+                            ended = true;
+                            break;
+                        }
+                        Err(_) => break,
+                    }
+                }
+                if burst.len() > 1 {
+                    debug!("Coalesced {} events into {}", burst.len(), coalesce(burst.clone()).len());
+                }
+                for event in coalesce(burst) {
+                    handler(event).await;
+                }
+                if ended {
+                    break;
+                }
             }
-            Err(e) => {
-                error!("Event error: {}, reconnecting...", e);
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                listener = EventListener::connect().await?;
+        }
+
+        warn!("Event stream ended, reconnecting...");
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_parse_names_with_commas() {
+        let event = EventListener::parse_event("workspacev2>>3,code, misc\n").unwrap();
+        assert_eq!(event, HyprlandEvent::Workspace { id: Some(3), name: "code, misc".into() });
+
+        let event = EventListener::parse_event("moveworkspacev2>>3,a,b,DP-1").unwrap();
+        assert_eq!(
+            event,
+            HyprlandEvent::MoveWorkspace { id: Some(3), name: "a,b".into(), monitor: "DP-1".into() }
+        );
+
+        let event = EventListener::parse_event("moveworkspace>>a,b,DP-1").unwrap();
+        assert_eq!(event, HyprlandEvent::MoveWorkspace { id: None, name: "a,b".into(), monitor: "DP-1".into() });
+    }
+
+    #[test]
+    fn test_parse_ids() {
+        let event = EventListener::parse_event("workspacev2>>-98,special:scratch").unwrap();
+        assert_eq!(event, HyprlandEvent::Workspace { id: Some(-98), name: "special:scratch".into() });
+
+        let event = EventListener::parse_event("focusedmonv2>>DP-1,5").unwrap();
+        assert_eq!(
+            event,
+            HyprlandEvent::FocusedMon { monitor: "DP-1".into(), workspace: None, workspace_id: Some(5) }
+        );
+
+        // A v2 line without a numeric id isn't trusted
+        let event = EventListener::parse_event("monitoraddedv2>>x,DP-1,Dell").unwrap();
+        assert!(matches!(event, HyprlandEvent::Other(_)));
+    }
+
+    #[tokio::test]
+    async fn test_transcript() {
+        let transcript = include_bytes!("../tests/fixtures/hyprland/socket2.log");
+        let events: Vec<_> = EventListener::from_reader(&transcript[..]).into_stream().collect().await;
+
+        // v1/v2 pairs arrive as one event each
+        assert_eq!(events.len(), 8);
+        // Titles with invalid UTF-8 don't end the stream
+        assert!(matches!(&events[0], HyprlandEvent::Other(line) if line.starts_with("activewindow>>")));
+        assert_eq!(
+            events[2],
+            HyprlandEvent::MonitorAdded {
+                id: Some(1),
+                name: "HDMI-A-1".into(),
+                description: Some("LG Electronics LG ULTRAGEAR 0x0001, rev. 2".into()),
+            }
+        );
+        assert_eq!(
+            events[3],
+            HyprlandEvent::FocusedMon {
+                monitor: "HDMI-A-1".into(),
+                workspace: Some("2".into()),
+                workspace_id: Some(2),
             }
+        );
+        assert_eq!(events[4], HyprlandEvent::Workspace { id: Some(4), name: "chat, mail".into() });
+        assert_eq!(
+            events[5],
+            HyprlandEvent::MoveWorkspace { id: Some(4), name: "chat, mail".into(), monitor: "DP-1".into() }
+        );
+        assert!(matches!(events[6], HyprlandEvent::MonitorRemoved { id: Some(1), ref name, .. } if name == "HDMI-A-1"));
+        assert_eq!(events[7], HyprlandEvent::Other("configreloaded>>".into()));
+    }
+
+    #[tokio::test]
+    async fn test_v1_only() {
+        // Older Hyprland: no v2 line follows, the event still arrives
+        let (mut hyprland, socket) = tokio::io::duplex(1024);
+        let mut listener = EventListener::from_reader(socket);
+        tokio::io::AsyncWriteExt::write_all(&mut hyprland, b"monitoradded>>DP-2\nworkspace>>3\n").await.unwrap();
+
+        let event = listener.next_event().await.unwrap();
+        assert_eq!(event, Some(HyprlandEvent::MonitorAdded { id: None, name: "DP-2".into(), description: None }));
+        let event = listener.next_event().await.unwrap();
+        assert_eq!(event, Some(HyprlandEvent::Workspace { id: None, name: "3".into() }));
+    }
+
+    #[test]
+    fn test_coalesce_burst() {
+        let transcript = [
+            "monitorremovedv2>>1,DP-1,Dell",
+            "focusedmonv2>>HDMI-A-1,2",
+            "workspacev2>>2,2",
+            "monitoraddedv2>>1,DP-1,Dell",
+            "monitoraddedv2>>2,DP-2,Dell",
+            "focusedmonv2>>DP-1,1",
+            "workspacev2>>1,1",
+        ];
+        let burst = transcript.iter().map(|line| EventListener::parse_event(line).unwrap()).collect();
+        let kinds: Vec<_> = coalesce(burst).iter().map(HyprlandEvent::kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                EventKind::MonitorRemoved,
+                EventKind::MonitorAdded,
+                EventKind::MonitorAdded,
+                EventKind::FocusedMon,
+                EventKind::Workspace,
+            ]
+        );
+
+        let subscription = Subscription::only(&[EventKind::MonitorAdded, EventKind::MonitorRemoved]);
+        assert!(subscription.wants(&EventListener::parse_event(transcript[0]).unwrap()));
+        assert!(!subscription.wants(&EventListener::parse_event(transcript[2]).unwrap()));
+    }
+
+    proptest! {
+        #[test]
+        fn parse_event_never_panics(line in ".*") {
+            let _ = EventListener::parse_event(&line);
+        }
+
+        #[test]
+        fn parse_known_events_never_panics(
+            event in "(monitoradded|monitorremoved|workspace|moveworkspace|focusedmon)(v2)?",
+            data in "[a-zA-Z0-9,>\\- ]*",
+        ) {
+            let _ = EventListener::parse_event(&format!("{}>>{}", event, data));
         }
     }
 }
@@ -0,0 +1,141 @@
+//! Translations of user-facing CLI text and notifications. Bundles are
+//! Fluent-style `key = text` files in `i18n/`, compiled in and chosen by
+//! `LC_ALL`, `LC_MESSAGES` or `LANG`. Protocol messages and logs stay in
+//! English so scripts and bug reports can rely on them.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+const EN: &str = include_str!("../i18n/en.ftl");
+const ZH_TW: &str = include_str!("../i18n/zh-TW.ftl");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    ZhTw,
+}
+
+impl Lang {
+    /// Language of the first non-empty locale variable, as gettext does
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map(|locale| Self::from_locale(&locale))
+            .unwrap_or(Lang::En)
+    }
+
+    /// `zh_TW.UTF-8`, `zh-TW` and `zh_HK` select Traditional Chinese
+    pub fn from_locale(locale: &str) -> Self {
+        let tag = locale.split(['.', '@']).next().unwrap_or("").replace('-', "_");
+        match tag.as_str() {
+            "zh_TW" | "zh_HK" | "zh_MO" | "zh_Hant" => Lang::ZhTw,
+            _ => Lang::En,
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            Lang::En => EN,
+            Lang::ZhTw => ZH_TW,
+        }
+    }
+}
+
+type Bundle = HashMap<&'static str, &'static str>;
+
+fn parse(source: &'static str) -> Bundle {
+    source
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, text)| (key.trim(), text.strip_prefix(' ').unwrap_or(text).trim_end()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+fn bundle(lang: Lang) -> &'static Bundle {
+    static EN_BUNDLE: OnceLock<Bundle> = OnceLock::new();
+    static ZH_TW_BUNDLE: OnceLock<Bundle> = OnceLock::new();
+    let cell = match lang {
+        Lang::En => &EN_BUNDLE,
+        Lang::ZhTw => &ZH_TW_BUNDLE,
+    };
+    cell.get_or_init(|| parse(lang.source()))
+}
+
+fn current() -> Lang {
+    static LANG: OnceLock<Lang> = OnceLock::new();
+    *LANG.get_or_init(Lang::from_env)
+}
+
+/// `key` in `lang` with `{ $name }` placeholders filled in, falling back to
+/// English and then to the key itself
+pub fn lookup(lang: Lang, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let template = bundle(lang)
+        .get(key)
+        .or_else(|| bundle(Lang::En).get(key))
+        .copied()
+        .unwrap_or(key);
+    let mut text = template.to_string();
+    for (name, value) in args {
+        let value = value.to_string();
+        text = text
+            .replace(&format!("{{ ${} }}", name), &value)
+            .replace(&format!("{{${}}}", name), &value);
+    }
+    text
+}
+
+/// `key` in the user's language
+pub fn text(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    lookup(current(), key, args)
+}
+
+/// Translate a message key: `t!("seconds", secs = 5)`
+macro_rules! t {
+    ($key:literal) => {
+        $crate::i18n::text($key, &[])
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        // Bound here so the borrowed arguments aren't held across an await
+        let text = $crate::i18n::text($key, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+]);
+        text
+    }};
+}
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_selection() {
+        assert_eq!(Lang::from_locale("zh_TW.UTF-8"), Lang::ZhTw);
+        assert_eq!(Lang::from_locale("zh-TW"), Lang::ZhTw);
+        assert_eq!(Lang::from_locale("zh_CN.UTF-8"), Lang::En);
+        assert_eq!(Lang::from_locale("C"), Lang::En);
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup(Lang::En, "daemon-started", &[("pid", &42)]), "swww-manager running in background (PID 42)");
+        assert_eq!(lookup(Lang::ZhTw, "restart-timeout", &[("secs", &5)]), "Daemon 未在 5 秒內恢復");
+        assert_eq!(lookup(Lang::En, "monitor-added", &[("name", &"DP-1"), ("description", &"Dell U27")]),
+            "+ Added: DP-1 (Dell U27)");
+        assert_eq!(lookup(Lang::ZhTw, "no-such-key", &[]), "no-such-key");
+    }
+
+    #[test]
+    fn test_bundles_have_same_keys() {
+        let en = bundle(Lang::En);
+        for key in bundle(Lang::ZhTw).keys() {
+            assert!(en.contains_key(key), "zh-TW has unknown key {}", key);
+        }
+        for key in en.keys() {
+            assert!(bundle(Lang::ZhTw).contains_key(key), "zh-TW is missing {}", key);
+        }
+    }
+}
@@ -1,11 +1,31 @@
-pub mod config;
+pub use swww_manager_core::{ambient, config, content, convert, gc, generate, hyprland_ipc, index, profile, protocol, scan, thumbnail, wallpaper, weather};
+
 pub mod monitor;
-pub mod wallpaper;
-pub mod profile;
-pub mod protocol;
 pub mod hyprland_event;
-pub mod hyprland_ipc;
 pub mod notify;
+pub mod timeline;
+pub mod report;
+pub mod cooldown;
+pub mod maintenance;
+pub mod startup;
+pub mod units;
+pub mod daemon;
+pub mod logging;
+pub mod templates;
+pub mod snippets;
+pub mod slideshow;
+pub mod contact_sheet;
+pub mod status_format;
+pub mod widget;
+#[cfg(feature = "gallery")]
+pub mod gallery;
+pub mod session;
+pub mod clipboard;
+pub mod desktop;
+pub mod i18n;
+pub mod output;
+pub mod sandbox;
+pub mod switcher;
 pub mod server;
 pub mod client;
 
@@ -18,54 +38,422 @@ pub use server::Server;
 pub use client::Client;
 
 use anyhow::Result;
+use config::SwitchMode;
+use futures::{FutureExt, Stream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use tokio::sync::{broadcast, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tracing::warn;
+use i18n::t;
+use switcher::Switcher;
 
-pub struct Manager {
+/// Something the embedded engine did, delivered through `Manager::events`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManagerEvent {
+    WallpaperChanged { path: String, reason: String },
+    ProfileChanged { name: String },
+    MonitorsDetected { monitors: Vec<String> },
+    Error { message: String },
+}
+
+struct Inner {
     config: Config,
+    /// Where changes to `config` are saved, `None` for the default path
+    config_path: Option<PathBuf>,
     monitor_manager: MonitorManager,
     wallpaper_manager: WallpaperManager,
     profile_manager: ProfileManager,
+    weather: weather::WeatherProvider,
+    cooldown: cooldown::SwitchCooldown,
+    analyzing: Arc<AtomicBool>,
+    /// Channel of the current profile that rotation is limited to
+    channel: Option<String>,
+    /// Outputs seen by the last detection
+    known_outputs: Vec<String>,
+}
+
+impl Inner {
+    fn switcher(&mut self) -> Switcher<'_> {
+        Switcher {
+            config: &self.config,
+            wallpaper_manager: &mut self.wallpaper_manager,
+            monitor_manager: &self.monitor_manager,
+            weather: &self.weather,
+            analyzing: &self.analyzing,
+        }
+    }
+
+    fn active_profile(&self) -> Result<config::Profile> {
+        switcher::active_profile(&self.config, &self.profile_manager, self.channel.as_deref())
+    }
+}
+
+/// The whole engine without the socket server: wallpaper and profile
+/// switching, auto-switching and hotplug handling for programs (bars,
+/// shells) that embed it. Clones share the same state.
+#[derive(Clone)]
+pub struct Manager {
+    inner: Arc<Mutex<Inner>>,
+    events: broadcast::Sender<ManagerEvent>,
+    /// Detected profile the user is currently being asked to switch to
+    asking: Arc<std::sync::Mutex<Option<String>>>,
 }
 
 impl Manager {
     pub fn new(config: Config) -> Self {
-        Self {
-            monitor_manager: MonitorManager::new(),
-            wallpaper_manager: WallpaperManager::new(),
+        Self::with_path(config, None)
+    }
+
+    fn with_path(config: Config, config_path: Option<PathBuf>) -> Self {
+        let mut wallpaper_manager = WallpaperManager::new();
+        wallpaper_manager.configure(&config);
+        let inner = Inner {
+            monitor_manager: MonitorManager::new().with_detection(&config.monitor_detection),
+            wallpaper_manager,
             profile_manager: ProfileManager::new(config.clone()),
             config,
+            config_path,
+            weather: weather::WeatherProvider::new(),
+            cooldown: cooldown::SwitchCooldown::new(),
+            analyzing: Arc::default(),
+            channel: None,
+            known_outputs: Vec::new(),
+        };
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            events: broadcast::channel(64).0,
+            asking: Arc::default(),
+        }
+    }
+
+    /// Engine for the config at `path` (default: the usual location),
+    /// saving changes such as profile switches back to it
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        Ok(Self::with_path(Config::load(path)?, path.map(PathBuf::from)))
+    }
+
+    /// Events from this point on. A subscriber that falls behind skips the
+    /// events it missed.
+    pub fn events(&self) -> impl Stream<Item = ManagerEvent> + Send + 'static {
+        futures::stream::unfold(self.events.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => return Some((event, rx)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    fn emit(&self, event: ManagerEvent) {
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
+    pub async fn config(&self) -> Config {
+        self.inner.lock().await.config.clone()
+    }
+
+    pub async fn current_wallpaper(&self) -> Option<String> {
+        let inner = self.inner.lock().await;
+        inner.wallpaper_manager.last_wallpaper().map(|p| p.to_string_lossy().to_string())
+    }
+
+    pub async fn switch_wallpaper(&self) -> Result<String> {
+        self.switch_wallpaper_for("manual", None, None).await
+    }
+
+    /// Switch with transition preset `preset` instead of the profile's own
+    pub async fn switch_wallpaper_with_preset(&self, preset: &str) -> Result<String> {
+        self.switch_wallpaper_for("manual", Some(preset), None).await
+    }
+
+    /// Switch with `preset` and picking by `mode`, each `None` meaning the
+    /// profile's or config's own
+    pub async fn switch_wallpaper_using(&self, preset: Option<&str>, mode: Option<SwitchMode>) -> Result<String> {
+        self.switch_wallpaper_for("manual", preset, mode).await
+    }
+
+    async fn switch_wallpaper_for(&self, reason: &str, preset: Option<&str>, mode: Option<SwitchMode>) -> Result<String> {
+        let result = async {
+            let mut inner = self.inner.lock().await;
+            let mut profile = inner.active_profile()?;
+            if let Some(preset) = preset {
+                inner.config.apply_transition_preset(&mut profile, preset)?;
+            }
+            let cooldown = inner.cooldown.clone();
+            inner.switcher().switch(&profile, mode, &cooldown).await
+        }
+        .await;
+
+        match result {
+            Ok(wallpaper) => {
+                if let Err(e) = notify::send(&t!("notify-wallpaper-switched"), &wallpaper).await {
+                    warn!("Failed to send notification: {}", e);
+                }
+                self.emit(ManagerEvent::WallpaperChanged {
+                    path: wallpaper.clone(),
+                    reason: reason.to_string(),
+                });
+                Ok(wallpaper)
+            }
+            Err(e) => {
+                self.emit(ManagerEvent::Error { message: e.to_string() });
+                Err(e)
+            }
         }
     }
 
-    pub async fn switch_wallpaper(&mut self) -> Result<String> {
-        let profile = self.profile_manager.current_profile()?;
-        let wallpaper = self.wallpaper_manager.get_wallpaper(profile, &self.config)?;
-        self.wallpaper_manager.set_wallpaper(&wallpaper, profile).await?;
-        notify::send("Wallpaper switched", &wallpaper).await?;
+    /// Show the image at `path` on every output
+    pub async fn set_wallpaper(&self, path: &str) -> Result<()> {
+        let result = async {
+            let mut inner = self.inner.lock().await;
+            let profile = inner.active_profile()?;
+            inner.switcher().show(path, &profile).await
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                self.emit(ManagerEvent::WallpaperChanged { path: path.to_string(), reason: "set".to_string() });
+                Ok(())
+            }
+            Err(e) => {
+                self.emit(ManagerEvent::Error { message: e.to_string() });
+                Err(e)
+            }
+        }
+    }
+
+    /// Limit rotation to the current profile's channel `name`, or lift the
+    /// limit with `None`, and switch to a wallpaper from it
+    pub async fn set_channel(&self, name: Option<&str>) -> Result<String> {
+        {
+            let mut inner = self.inner.lock().await;
+            if let Some(name) = name {
+                let mut channels: Vec<String> = inner.profile_manager.current_profile()
+                    .map(|p| p.channels.keys().cloned().collect())
+                    .unwrap_or_default();
+                if !channels.iter().any(|c| c == name) {
+                    channels.sort();
+                    anyhow::bail!(
+                        "Profile '{}' has no channel '{}' (available: {})",
+                        inner.config.current_profile,
+                        name,
+                        if channels.is_empty() { "none".to_string() } else { channels.join(", ") }
+                    );
+                }
+            }
+            inner.channel = name.map(String::from);
+        }
+        self.switch_wallpaper_for("channel change", None, None).await
+    }
+
+    /// Pick the next wallpaper of `profile` (default: the current one) and
+    /// publish it through `[handoff]` without applying it, for running
+    /// before the session (and swww) starts. The daemon then starts from it.
+    pub async fn pre_session(&self, profile: Option<&str>, mode: Option<SwitchMode>) -> Result<String> {
+        let mut inner = self.inner.lock().await;
+        let inner = &mut *inner;
+        if let Some(name) = profile {
+            let name = inner.profile_manager.resolve(name)?;
+            inner.profile_manager.switch_to(&name)?;
+        }
+        let profile = inner.active_profile()?;
+        inner.wallpaper_manager.ensure_cache(&profile).await?;
+        if let Some(last) = index::WallpaperIndex::load().most_recent() {
+            inner.wallpaper_manager.set_last_wallpaper(last);
+        }
+        inner.switcher().prepare_selection(&profile).await;
+        let config = inner.config.with_switch_mode(mode);
+        let wallpaper = inner.wallpaper_manager.get_wallpaper(&profile, &config)?;
+        swww_manager_core::handoff::write(&inner.config.handoff, std::path::Path::new(&wallpaper))?;
+
+        index::WallpaperIndex::update(|index| index.mark_shown(std::path::Path::new(&wallpaper)))?;
         Ok(wallpaper)
     }
 
-    pub async fn switch_profile(&mut self, name: &str) -> Result<()> {
-        self.profile_manager.switch_to(name)?;
-        self.config.current_profile = name.to_string();
-        self.config.save(None)?;
-        notify::send("Profile switched", name).await?;
-        self.switch_wallpaper().await?;
-        Ok(())
+    /// Switch to the profile `name` is short for and to one of its
+    /// wallpapers, returning its full name
+    pub async fn switch_profile(&self, name: &str) -> Result<String> {
+        let name = self.select_profile(name).await?;
+        self.switch_wallpaper_for("profile switch", None, None).await?;
+        Ok(name)
+    }
+
+    /// Make the profile `name` is short for current without switching the
+    /// wallpaper, returning its full name
+    pub async fn select_profile(&self, name: &str) -> Result<String> {
+        let name = {
+            let mut inner = self.inner.lock().await;
+            let name = inner.profile_manager.resolve(name)?;
+            inner.profile_manager.switch_to(&name)?;
+            inner.config.current_profile = name.clone();
+            inner.config.save_key(inner.config_path.as_deref(), "current_profile")?;
+            let kept = inner.channel.as_ref().is_some_and(|c| {
+                inner.config.profiles.get(&name).is_some_and(|p| p.channels.contains_key(c))
+            });
+            if !kept {
+                inner.channel = None;
+            }
+            name
+        };
+        if let Err(e) = notify::send(&t!("notify-profile-switched"), &name).await {
+            warn!("Failed to send notification: {}", e);
+        }
+        self.emit(ManagerEvent::ProfileChanged { name: name.clone() });
+        Ok(name)
     }
 
-    pub fn list_profiles(&self) {
-        self.profile_manager.list();
+    pub async fn list_profiles(&self) {
+        self.inner.lock().await.profile_manager.list();
     }
 
-    pub async fn detect_and_switch(&mut self) -> Result<Option<String>> {
-        let monitors = self.monitor_manager.get_monitors().await?;
-        
-        if let Some(profile_name) = self.profile_manager.detect_profile(&monitors)?
-            && profile_name != self.config.current_profile {
+    /// Detect the profile for the connected monitors and switch to it like
+    /// the daemon does, returning it when switched to. With
+    /// `ask_before_switch` the user is asked first and `None` returned;
+    /// when the profile stays, `minimal_disruption` only fills new outputs.
+    pub async fn detect_and_switch(&self) -> Result<Option<String>> {
+        let (matched, created, added, config) = {
+            let mut inner = self.inner.lock().await;
+            let monitors = inner.monitor_manager.get_stable_monitors().await?;
+            let names = monitor::names(&monitors);
+            self.emit(ManagerEvent::MonitorsDetected { monitors: names.clone() });
+            let previous = std::mem::replace(&mut inner.known_outputs, names.clone());
+            let added: Option<Vec<String>> = (!previous.is_empty())
+                .then(|| names.into_iter().filter(|n| !previous.contains(n)).collect());
+
+            let auto = inner.config.monitor_detection.auto_profile
+                .then(|| inner.profile_manager.auto_profile(&monitors))
+                .flatten();
+            if let Some((name, profile)) = auto {
+                inner.config.profiles.insert(name.clone(), profile);
+                inner.config.save_key(inner.config_path.as_deref(), &format!("profiles.{}", name))?;
+                let config = inner.config.clone();
+                inner.profile_manager.update_config(config);
+                (Some(name), true, added, inner.config.clone())
+            } else {
+                let matched = inner.profile_manager.detect_profile(&monitors)?;
+                (matched, false, added, inner.config.clone())
+            }
+        };
+
+        if created && let Some(name) = &matched {
+            notify::send(&t!("notify-auto-profile"), &t!("notify-auto-profile-body", profile = name)).await.ok();
+        }
+
+        let detection = &config.monitor_detection;
+        let detected = matched.clone().or_else(|| detection.fallback_profile.clone());
+        match detected {
+            Some(profile_name) if profile_name != config.current_profile => {
+                if detection.ask_before_switch {
+                    let manager = self.clone();
+                    switcher::ask_to_switch(&profile_name, &self.asking, |profile| async move {
+                        manager.switch_profile(&profile).await.map(|_| ())
+                    });
+                    return Ok(None);
+                }
                 self.switch_profile(&profile_name).await?;
-                return Ok(Some(profile_name));
+                Ok(Some(profile_name))
             }
-        
-        Ok(None)
+            // Monitors changed under the same profile
+            _ if matched.is_some() => {
+                match added {
+                    Some(added) if detection.minimal_disruption => self.fill_outputs(&added).await?,
+                    _ => {
+                        self.switch_wallpaper_for("monitor detection", None, None).await?;
+                    }
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Give newly connected `outputs` the wallpaper the others already show
+    async fn fill_outputs(&self, outputs: &[String]) -> Result<()> {
+        if outputs.is_empty() {
+            return Ok(());
+        }
+        let wallpaper = {
+            let mut inner = self.inner.lock().await;
+            let profile = inner.active_profile()?;
+            let shown = inner.wallpaper_manager.shown(&profile)
+                .or_else(|| inner.wallpaper_manager.last_wallpaper().cloned());
+            inner.switcher().fill_outputs(&profile, outputs, shown).await?
+        };
+        self.emit(ManagerEvent::WallpaperChanged { path: wallpaper, reason: "new output".to_string() });
+        Ok(())
+    }
+
+    /// Switch wallpapers every `auto_switch.interval` seconds while
+    /// `auto_switch.enabled` is set, following changes to either. Abort
+    /// the handle to stop.
+    pub fn spawn_auto_switch(&self) -> JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let interval = manager.config().await.auto_switch.interval.max(1);
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+                if !manager.config().await.auto_switch.enabled {
+                    continue;
+                }
+                if let Err(e) = manager.switch_wallpaper_for("auto-switch", None, None).await {
+                    warn!("Auto-switch failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Follow Hyprland monitor hotplug events and switch to the matching
+    /// profile, debounced like the daemon. Abort the handle to stop.
+    pub fn spawn_hotplug(&self) -> JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let debouncer = manager.inner.lock().await.monitor_manager.debouncer();
+            loop {
+                let subscription = hyprland_event::Subscription::only(&[
+                    hyprland_event::EventKind::MonitorAdded,
+                    hyprland_event::EventKind::MonitorRemoved,
+                ]).coalesced();
+                let result = hyprland_event::monitor_events(subscription, |_| {
+                    let manager = manager.clone();
+                    let debouncer = debouncer.clone();
+                    async move {
+                        debouncer.schedule(async move {
+                            if let Err(e) = manager.detect_and_switch().await {
+                                warn!("Hotplug detection failed: {}", e);
+                            }
+                        }).await;
+                    }.boxed()
+                }).await;
+                if let Err(e) = result {
+                    warn!("Hyprland events not available: {}", e);
+                }
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_events_stream() {
+        let manager = Manager::new(Config::default());
+        let mut events = Box::pin(manager.events());
+
+        manager.emit(ManagerEvent::ProfileChanged { name: "desk".to_string() });
+        manager.emit(ManagerEvent::Error { message: "boom".to_string() });
+
+        assert_eq!(events.next().await, Some(ManagerEvent::ProfileChanged { name: "desk".to_string() }));
+        assert_eq!(events.next().await, Some(ManagerEvent::Error { message: "boom".to_string() }));
     }
 }
@@ -0,0 +1,39 @@
+use anyhow::Result;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+use tracing::Level;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::{fmt, reload, Registry};
+
+static HANDLE: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// Install the global subscriber with a level that can be changed later
+/// through `set_level`
+pub fn init(level: Level) {
+    let (filter, handle) = reload::Layer::new(LevelFilter::from_level(level));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            fmt::layer()
+                .with_ansi(std::io::stdout().is_terminal())
+                .with_target(false)
+                .with_thread_ids(false)
+                .with_file(false)
+                // Report span durations (request handling, swww calls,
+                // directory scans) when running at debug level
+                .with_span_events(FmtSpan::CLOSE),
+        )
+        .init();
+    HANDLE.set(handle).ok();
+}
+
+pub fn set_level(level: &str) -> Result<()> {
+    let filter: LevelFilter = level
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid log level: {} (use trace, debug, info, warn, error or off)", level))?;
+    let handle = HANDLE.get().ok_or_else(|| anyhow::anyhow!("Logging is not initialized"))?;
+    handle.modify(|current| *current = filter)?;
+    Ok(())
+}
@@ -2,24 +2,46 @@ use tracing::Level;
 use tracing::info;
 use clap::Subcommand;
 
-mod config;
+use swww_manager_core::{ambient, config, content, convert, gc, generate, hyprland_ipc, index, profile, protocol, scan, thumbnail, wallpaper, weather};
+
 mod monitor;
-mod wallpaper;
-mod profile;
+mod switcher;
 mod server;
 mod client;
-mod protocol;
 mod hyprland_event;
-mod hyprland_ipc;
 mod notify;
+mod timeline;
+mod report;
+mod cooldown;
+mod maintenance;
+mod startup;
+mod units;
+mod daemon;
+mod logging;
+mod templates;
+mod snippets;
+mod slideshow;
+mod contact_sheet;
+mod status_format;
+mod widget;
+#[cfg(feature = "gallery")]
+mod gallery;
+mod session;
+mod clipboard;
+mod desktop;
+mod i18n;
+mod output;
+mod sandbox;
 
 use clap::Parser;
-use config::Config;
+use config::{Config, SwitchMode};
 use client::Client;
 use server::Server;
-// use hyprland_event::{monitor_events, HyprlandEvent};
-// use futures::FutureExt;
-use anyhow::Result;
+use i18n::t;
+use comfy_table::Cell;
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(
@@ -40,11 +62,45 @@ struct Cli {
 
     #[arg(short, long, global = true)]
     verbose: bool,
+    
+    /// Print how long the daemon took to handle each request
+    #[arg(long, global = true)]
+    timing: bool,
+
+    /// Control a daemon over TCP (its `listen_tcp` address) instead of the
+    /// local socket; the token is read from SWWW_MANAGER_TOKEN
+    #[arg(long, value_name = "HOST:PORT", global = true)]
+    remote: Option<String>,
+
+    /// Run switch, profile and detect in this process when no daemon is
+    /// reachable (minimal systems, SSH sessions)
+    #[arg(long, global = true)]
+    no_daemon: bool,
+
+    /// Disable colored output (also honors NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Draw tables and markers with ASCII only
+    #[arg(long, global = true)]
+    ascii: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    Serve,
+    Serve {
+        /// Detach into the background, logging to --log-file
+        #[arg(long)]
+        daemonize: bool,
+        
+        /// Write the server PID to this file
+        #[arg(long, value_name = "FILE")]
+        pidfile: Option<PathBuf>,
+        
+        /// Log file used with --daemonize
+        #[arg(long, value_name = "FILE")]
+        log_file: Option<PathBuf>,
+    },
     
     #[command(name = "monitor-events")]
     MonitorEvents,
@@ -53,15 +109,30 @@ enum Commands {
         #[arg(short, long)]
         profile: Option<String>,
         
-        #[arg(short, long)]
+        /// Pick at random this time, whatever auto_switch.mode says
+        #[arg(short, long, conflicts_with = "next")]
         random: bool,
         
+        /// Take the wallpaper after the current one this time, whatever
+        /// auto_switch.mode says
         #[arg(short = 'n', long)]
         next: bool,
+
+        /// Transition preset for this switch (smooth, snappy, cinematic or
+        /// one from [transition_presets])
+        #[arg(long, value_name = "PRESET")]
+        preset: Option<String>,
+        
+        /// Only pick the next wallpaper and write it to the [handoff] files,
+        /// without a daemon or swww, e.g. from a greetd pre-session hook
+        #[arg(long, conflicts_with = "preset")]
+        pre_session: bool,
     },
     
     List {
-        #[arg(short, long)]
+        /// Show monitor names and each wallpaper directory's state; long
+        /// form only, -d is the global --debug
+        #[arg(long)]
         detailed: bool,
     },
     
@@ -70,10 +141,54 @@ enum Commands {
         name: String,
     },
     
+    /// Rotate through one channel of the current profile until cleared
+    Channel {
+        /// Channel name; omit to use the whole profile again
+        name: Option<String>,
+    },
+    
+    /// Re-apply the current wallpaper with other transition settings to try
+    /// them out; the config is left alone
+    #[command(name = "transition-test")]
+    TransitionTest {
+        /// swww transition type, e.g. grow, wipe, outer
+        #[arg(long = "type", value_name = "TYPE")]
+        transition: Option<String>,
+        
+        /// Duration in seconds
+        #[arg(long)]
+        duration: Option<u32>,
+        
+        #[arg(long)]
+        fps: Option<u32>,
+        
+        /// Easing curve, e.g. ".25,.1,.25,1"
+        #[arg(long)]
+        bezier: Option<String>,
+        
+        /// Start from a transition preset
+        #[arg(long)]
+        preset: Option<String>,
+    },
+    
     /// Show current status
     Status {
         #[arg(short, long)]
         json: bool,
+        
+        /// One line for status bars, e.g. '{profile} | {wallpaper_name} |
+        /// next in {next_switch_hm}'; an unknown placeholder lists them all
+        #[arg(short, long, conflicts_with = "json")]
+        format: Option<String>,
+    },
+    
+    /// Print wallpaper, preview, palette, profile and countdown as JSON for
+    /// eww and ags widgets
+    #[command(name = "widget-json")]
+    WidgetJson {
+        /// Keep running and print a new line whenever anything changes
+        #[arg(short, long)]
+        follow: bool,
     },
     
     /// Control auto-switch feature
@@ -90,19 +205,233 @@ enum Commands {
     Init {
         #[arg(short, long)]
         force: bool,
+        
+        /// Start from a configuration tailored to a common setup
+        #[arg(short, long, value_enum)]
+        template: Option<templates::Template>,
+    },
+    
+    /// Write systemd user units for socket activation
+    #[command(name = "install-units")]
+    InstallUnits {
+        /// Disable and remove previously installed units
+        #[arg(long)]
+        uninstall: bool,
+        
+        /// Overwrite existing unit files
+        #[arg(short, long)]
+        force: bool,
+    },
+    
+    /// Write a desktop entry offering "Open with swww-manager" in file managers
+    #[command(name = "install-desktop")]
+    InstallDesktop {
+        /// Remove a previously installed entry
+        #[arg(long)]
+        uninstall: bool,
+        
+        /// Overwrite an existing entry
+        #[arg(short, long)]
+        force: bool,
+    },
+    
+    /// Show client and daemon versions
+    Version,
+    
+    /// Print the JSON Schema of all protocol requests and responses
+    #[command(name = "protocol-schema")]
+    ProtocolSchema,
+    
+    /// Print a minimal client for the current protocol
+    #[command(name = "client-snippet")]
+    ClientSnippet {
+        #[arg(long, value_enum)]
+        lang: snippets::Lang,
+    },
+    
+    /// Write a profile's images in rotation order as a playlist or a video
+    #[command(name = "export-slideshow")]
+    ExportSlideshow {
+        profile: String,
+        
+        /// .m3u or .m3u8 for a playlist; anything else, e.g. .mp4, is
+        /// encoded as a video with ffmpeg. Defaults to PROFILE.m3u
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        
+        /// Seconds each image is shown in a video
+        #[arg(long, default_value_t = 5.0)]
+        duration: f32,
+        
+        /// Video size, images are fitted into it
+        #[arg(long, default_value = "1920x1080", value_parser = parse_size)]
+        size: (u32, u32),
+    },
+    
+    /// Write one image with numbered thumbnails of the current profile's
+    /// wallpapers, numbered as `set --index` picks them (needs the daemon)
+    #[command(name = "contact-sheet")]
+    ContactSheet {
+        /// Only accepted when it is the current profile
+        profile: Option<String>,
+        
+        /// Defaults to PROFILE-sheet.png
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        
+        /// Thumbnails per row
+        #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+        columns: u32,
+    },
+    
+    /// Restart the daemon, e.g. after upgrading the binary
+    Restart {
+        /// Seconds to wait for the new instance to come up
+        #[arg(long, default_value_t = 15)]
+        timeout: u64,
     },
     
     /// Reload configuration
     Reload,
     
+    /// Summarize which wallpapers, directories and profiles are shown most
+    Report {
+        /// Number of images in the most/least shown lists
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+        
+        #[arg(short, long)]
+        json: bool,
+    },
+    
+    /// Remove index entries, history entries, thumbnails and converted
+    /// copies left by wallpapers that were deleted or changed
+    Gc {
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    
+    /// Change the log level of the running daemon
+    #[command(name = "log-level")]
+    LogLevel {
+        #[arg(value_parser = ["trace", "debug", "info", "warn", "error", "off"])]
+        level: String,
+    },
+    
+    /// Show recent wallpaper, profile and hotplug events
+    Timeline {
+        /// Number of events to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        count: usize,
+    },
+    
+    /// Read or change configuration keys on the running daemon
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    
+    /// Mark a wallpaper, the current one by default, as a favorite; favorites
+    /// come up twice as often in random rotation
+    Favorite {
+        path: Option<String>,
+        
+        /// Unmark it instead
+        #[arg(long)]
+        remove: bool,
+    },
+    
+    /// Never pick a wallpaper, the current one by default, again
+    Block {
+        path: Option<String>,
+        
+        /// Allow it again instead
+        #[arg(long)]
+        remove: bool,
+    },
+    
+    /// Browse the current profile's wallpapers in a window: click to apply,
+    /// mark favorites and block images
+    #[cfg(feature = "gallery")]
+    Gallery,
+    
+    /// Show a specific image on every monitor
+    Set {
+        /// Image path or file:// URI
+        #[arg(required_unless_present_any = ["from_clipboard", "color", "gradient", "index", "pattern"])]
+        path: Option<String>,
+        
+        /// Use the image on the Wayland clipboard, saved to clipboard_dir
+        #[arg(long, conflicts_with_all = ["path", "color", "gradient", "index", "pattern"])]
+        from_clipboard: bool,
+        
+        /// Show a solid color, e.g. '#1e1e2e'
+        #[arg(long, conflicts_with_all = ["path", "gradient", "index", "pattern"])]
+        color: Option<String>,
+        
+        /// Show a top-to-bottom gradient between two colors, e.g. '#000:#333'
+        #[arg(long, conflicts_with_all = ["path", "index", "pattern"])]
+        gradient: Option<String>,
+        
+        /// Show the current profile's image with this number on its
+        /// contact sheet: its place (from 1) in the daemon's wallpaper list
+        /// for the active profile, channel and matched sets included
+        #[arg(long, conflicts_with_all = ["path", "pattern"], value_parser = clap::value_parser!(u64).range(1..))]
+        index: Option<u64>,
+        
+        /// Show the current profile's image whose file name best matches,
+        /// e.g. 'fuji' for mt-fuji-dawn.jpg
+        #[arg(long = "match", conflicts_with = "path")]
+        pattern: Option<String>,
+    },
+    
+    /// Go back to the wallpaper shown before the current one; repeat to
+    /// go further back
+    Prev,
+    
+    /// Set an image opened from a file manager as wallpaper
+    Open {
+        /// Image path or file:// URI
+        file: String,
+    },
+    
     /// Detect and switch to optimal profile
-    Detect,
+    Detect {
+        /// Explain why each profile did or didn't match, without switching
+        #[arg(short, long)]
+        explain: bool,
+    },
     
     /// Show information about monitors
     Monitors {
         /// Watch for monitor changes
         #[arg(short, long)]
         watch: bool,
+
+        /// With --watch, print each change as a JSON object on its own line
+        #[arg(short, long, requires = "watch")]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print a key such as auto_switch.interval, or the whole config
+    Get {
+        key: Option<String>,
+    },
+    
+    /// Set a key and save the config, e.g. profiles.default.transition fade
+    Set {
+        key: String,
+        value: String,
+    },
+    
+    /// Print the config file, or with --effective every resolved value and its source
+    Show {
+        #[arg(long)]
+        effective: bool,
     },
 }
 
@@ -118,30 +447,147 @@ async fn main() -> Result<()> {
         Level::WARN
     };
     
-    tracing_subscriber::fmt()
-        .with_max_level(log_level)
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_file(false)
-        .init();
+    logging::init(log_level);
+    output::init(cli.no_color, cli.ascii);
+    if cli.timing {
+        Client::enable_timing();
+    }
+    if let Some(addr) = cli.remote.clone() {
+        let token = std::env::var("SWWW_MANAGER_TOKEN")
+            .map_err(|_| anyhow::anyhow!("--remote needs the server's remote_token in SWWW_MANAGER_TOKEN"))?;
+        Client::set_remote(addr, token);
+    }
 
     // Execute command
     match cli.command {
-        Commands::Serve => {
+        Commands::Serve { daemonize, pidfile, log_file } => {
+            if daemonize {
+                let pidfile = pidfile.unwrap_or_else(daemon::default_pidfile);
+                let log_file = log_file.unwrap_or_else(daemon::default_log_file);
+                
+                let mut args: Vec<OsString> = vec!["serve".into(), "--pidfile".into(), pidfile.into()];
+                if let Some(config) = &cli.config {
+                    args.push("--config".into());
+                    args.push(config.into());
+                }
+                if cli.debug {
+                    args.push("--debug".into());
+                } else {
+                    args.push("--verbose".into());
+                }
+                
+                let pid = daemon::spawn_detached(&args, &log_file)?;
+                output::success(&t!("daemon-started", pid = pid));
+                println!("{}", t!("daemon-logs", path = log_file.display()));
+                return Ok(());
+            }
+            
             info!("Starting socket server...");
+            let _lock = daemon::InstanceLock::acquire(&daemon::default_pidfile())?;
+            let pidfile = pidfile.filter(|p| p != &daemon::default_pidfile());
+            if let Some(pidfile) = &pidfile {
+                daemon::write_pidfile(pidfile)?;
+            }
             let config = Config::load(cli.config.as_deref())?;
-            let server = Server::new(config).await?;
-            server.run().await?;
+            sandbox::apply(&config, cli.config.as_deref())?;
+            let server = Server::new(config, cli.config.as_deref()).await?;
+            let result = server.run().await;
+            if let Some(pidfile) = &pidfile {
+                daemon::remove_pidfile(pidfile);
+            }
+            result?;
         }
         
         Commands::MonitorEvents => {
             info!("Starting Hyprland event monitor...");
-            run_event_monitor().await?;
+            let config = Config::load(cli.config.as_deref())?;
+            run_event_monitor(&config).await?;
         }
         
-        Commands::Switch { profile, random: _, next: _ } => {
-            let mut client = Client::connect().await?;
-            client.switch_wallpaper(profile.as_deref()).await?;
+        Commands::Switch { profile, random, next, preset: _, pre_session: true } => {
+            let manager = local_manager(cli.config.as_deref())?;
+            println!("{}", manager.pre_session(profile.as_deref(), switch_mode(random, next)).await?);
+        }
+        
+        Commands::Switch { profile, random, next, preset, pre_session: false } => {
+            let mode = switch_mode(random, next);
+            match daemon_or_local(cli.no_daemon).await? {
+                Some(mut client) => client.switch_wallpaper(profile.as_deref(), preset.as_deref(), mode).await?,
+                None => {
+                    let manager = local_manager(cli.config.as_deref())?;
+                    if let Some(profile) = &profile {
+                        manager.select_profile(profile).await?;
+                    }
+                    let wallpaper = manager.switch_wallpaper_using(preset.as_deref(), mode).await?;
+                    println!("{}", t!("switched-wallpaper", wallpaper = wallpaper));
+                }
+            }
+        }
+        
+        Commands::Set { index: Some(index), .. } => {
+            match daemon_or_local(cli.no_daemon).await? {
+                Some(mut client) => client.set_wallpaper_at(index as usize).await?,
+                None => anyhow::bail!("{}", t!("needs-daemon", command = "--index")),
+            }
+        }
+        
+        Commands::Prev => {
+            match daemon_or_local(cli.no_daemon).await? {
+                Some(mut client) => client.previous_wallpaper().await?,
+                None => anyhow::bail!("{}", t!("needs-daemon", command = "prev")),
+            }
+        }
+        
+        Commands::Set { pattern: Some(pattern), .. } => {
+            match daemon_or_local(cli.no_daemon).await? {
+                Some(mut client) => client.set_wallpaper_matching(&pattern).await?,
+                None => anyhow::bail!("{}", t!("needs-daemon", command = "--match")),
+            }
+        }
+        
+        Commands::Set { path, from_clipboard, color, gradient, .. } => {
+            let fill = match (color, gradient) {
+                (Some(color), _) => Some(generate::Fill::color(&color)?),
+                (_, Some(gradient)) => Some(generate::Fill::gradient(&gradient)?),
+                _ => None,
+            };
+            let path = if let Some(fill) = fill {
+                let (width, height) = screen_size().await;
+                generate::render(&fill, width, height).await?
+            } else if from_clipboard {
+                let config = Config::load(cli.config.as_deref())?;
+                let dir = shellexpand::tilde(&config.clipboard_dir.to_string_lossy()).into_owned();
+                let saved = clipboard::save_image(std::path::Path::new(&dir)).await?;
+                println!("{}", t!("clipboard-saved", path = saved.display()));
+                saved
+            } else {
+                desktop::local_path(&path.context("No image given")?)?
+            };
+            set_wallpaper(&path, cli.no_daemon, cli.config.as_deref()).await?;
+        }
+        
+        Commands::Open { file } => {
+            set_wallpaper(&desktop::local_path(&file)?, cli.no_daemon, cli.config.as_deref()).await?;
+        }
+        
+        Commands::Favorite { path, remove } => {
+            let path = marked_path(path).await?;
+            Client::connect().await?.mark_wallpaper(&path, Some(!remove), None).await?;
+            output::success(&if remove { t!("unmarked-favorite", path = path) } else { t!("marked-favorite", path = path) });
+        }
+        
+        Commands::Block { path, remove } => {
+            let path = marked_path(path).await?;
+            Client::connect().await?.mark_wallpaper(&path, None, Some(!remove)).await?;
+            output::success(&if remove { t!("unblocked", path = path) } else { t!("blocked", path = path) });
+        }
+        
+        #[cfg(feature = "gallery")]
+        Commands::Gallery => {
+            // GTK's main loop would stall this single-threaded runtime
+            std::thread::spawn(gallery::run)
+                .join()
+                .map_err(|_| anyhow::anyhow!("{}", t!("gallery-crashed")))??;
         }
         
         Commands::List { detailed } => {
@@ -149,14 +595,45 @@ async fn main() -> Result<()> {
             client.list_profiles(detailed).await?;
         }
         
+        Commands::Channel { name } => {
+            match daemon_or_local(cli.no_daemon).await? {
+                Some(mut client) => client.set_channel(name.as_deref()).await?,
+                None => anyhow::bail!("{}", t!("needs-daemon", command = "channel")),
+            }
+        }
+        
+        Commands::TransitionTest { transition, duration, fps, bezier, preset } => {
+            match daemon_or_local(cli.no_daemon).await? {
+                Some(mut client) => {
+                    client.test_transition(
+                        preset.as_deref(),
+                        transition.as_deref(),
+                        duration,
+                        fps,
+                        bezier.as_deref(),
+                    ).await?
+                }
+                None => anyhow::bail!("{}", t!("needs-daemon", command = "transition-test")),
+            }
+        }
+        
         Commands::Profile { name } => {
-            let mut client = Client::connect().await?;
-            client.switch_profile(&name).await?;
+            match daemon_or_local(cli.no_daemon).await? {
+                Some(mut client) => client.switch_profile(&name).await?,
+                None => {
+                    let name = local_manager(cli.config.as_deref())?.switch_profile(&name).await?;
+                    println!("{}", t!("switched-profile", profile = name));
+                }
+            }
         }
         
-        Commands::Status { json } => {
+        Commands::Status { json, format } => {
             let mut client = Client::connect().await?;
-            client.get_status(json).await?;
+            client.get_status(json, format.as_deref()).await?;
+        }
+        
+        Commands::WidgetJson { follow } => {
+            widget::run(follow).await?;
         }
         
         Commands::Auto { action, interval } => {
@@ -178,38 +655,235 @@ async fn main() -> Result<()> {
             }
         }
         
-        Commands::Init { force } => {
+        Commands::Init { force, template } => {
             let config_path = config::Config::default_path()
                 .ok_or_else(|| anyhow::anyhow!("Could not determine config path"))?;
             
             let config_path = std::path::PathBuf::from(config_path);
             
             if config_path.exists() && !force {
-                println!("Config file already exists at: {:?}", config_path);
-                println!("Use --force to overwrite");
+                println!("{}", t!("init-exists", path = config_path.display()));
+                println!("{}", t!("init-use-force"));
                 return Ok(());
             }
             
-            Config::generate_example()?;
-            println!("✓ Configuration initialized at: {:?}", config_path);
-            println!("\nEdit the file to customize your settings.");
-            println!("Then enable the service:");
+            match template {
+                Some(template) => {
+                    if let Some(parent) = config_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&config_path, template.content())?;
+                    println!("{}", template.content());
+                }
+                None => Config::generate_example()?,
+            }
+            output::success(&t!("init-done", path = config_path.display()));
+            println!("\n{}", t!("init-next-edit"));
+            println!("{}", t!("init-next-service"));
             println!("  systemctl --user enable --now swww-manager.socket");
         }
         
+        Commands::InstallUnits { uninstall, force } => {
+            if uninstall {
+                units::uninstall()?;
+            } else {
+                units::install(force)?;
+            }
+        }
+        
+        Commands::InstallDesktop { uninstall, force } => {
+            if uninstall {
+                desktop::uninstall()?;
+            } else {
+                desktop::install(force)?;
+            }
+        }
+        
+        Commands::Version => {
+            let mut client = Client::connect().await?;
+            client.get_version().await?;
+            if let Ok(ipc) = hyprland_ipc::HyprlandIPC::new()
+                && let Ok(version) = ipc.get_version().await {
+                    println!("{}", t!("version-hyprland", version = version.release()));
+                }
+        }
+        
+        Commands::ProtocolSchema => {
+            println!("{}", serde_json::to_string_pretty(&protocol::schema())?);
+        }
+        
+        Commands::ClientSnippet { lang } => {
+            print!("{}", lang.snippet());
+        }
+        
+        Commands::ExportSlideshow { profile, output, duration, size } => {
+            let config = Config::load(cli.config.as_deref())?;
+            let images = slideshow::images(&config, &profile)?;
+            let output = output.unwrap_or_else(|| PathBuf::from(format!("{}.m3u", profile)));
+            if slideshow::is_playlist(&output) {
+                std::fs::write(&output, slideshow::playlist(&images))
+                    .with_context(|| format!("Failed to write {:?}", output))?;
+            } else {
+                slideshow::video(&images, &output, duration, size).await?;
+            }
+            output::success(&t!("slideshow-written", count = images.len(), path = format!("{:?}", output)));
+        }
+        
+        Commands::ContactSheet { profile, output, columns } => {
+            // The daemon's list, with the active channel and matched sets,
+            // is what `set --index` counts through
+            let (current, wallpapers) = Client::connect().await?.list_wallpapers().await?;
+            if let Some(profile) = profile.filter(|p| *p != current) {
+                anyhow::bail!("{}", t!("sheet-other-profile", current = current, profile = profile));
+            }
+            if wallpapers.is_empty() {
+                anyhow::bail!("{}", t!("no-wallpapers"));
+            }
+            let images: Vec<PathBuf> = wallpapers.iter().map(|w| PathBuf::from(&w.path)).collect();
+            let output = output.unwrap_or_else(|| PathBuf::from(format!("{}-sheet.png", current)));
+            contact_sheet::render(&images, &output, columns).await?;
+            output::success(&t!("sheet-written", count = images.len(), path = format!("{:?}", output)));
+        }
+        
+        Commands::Restart { timeout } => {
+            let mut client = Client::connect().await?;
+            client.restart(std::time::Duration::from_secs(timeout)).await?;
+        }
+        
+        Commands::Config { action: ConfigAction::Show { effective } } => {
+            if effective {
+                let values = Config::effective(cli.config.as_deref())?;
+                let width = values.iter().map(|v| v.key.len()).max().unwrap_or(0);
+                for v in values {
+                    println!("{:width$} = {}  # {}", v.key, v.value, v.source, width = width);
+                }
+            } else {
+                let path = cli.config.clone()
+                    .or_else(Config::default_path)
+                    .ok_or_else(|| anyhow::anyhow!("Could not determine config path"))?;
+                match std::fs::read_to_string(&path) {
+                    Ok(content) => print!("{}", content),
+                    Err(_) => println!("{}", t!("config-no-file", path = path)),
+                }
+            }
+        }
+        
+        Commands::Config { action } => {
+            let mut client = Client::connect().await?;
+            match action {
+                ConfigAction::Get { key } => client.get_config(key.as_deref()).await?,
+                ConfigAction::Set { key, value } => client.set_config(&key, &value).await?,
+                ConfigAction::Show { .. } => unreachable!(),
+            }
+        }
+        
+        Commands::Report { limit, json } => {
+            let config = Config::load(cli.config.as_deref())?;
+            let report = report::build(&config, limit);
+            
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+            
+            output::heading(&t!("report-title", count = report.total_images));
+            let section = |title: String, items: &[report::Usage], images: bool| {
+                println!("\n{}", title);
+                if items.is_empty() {
+                    println!("{}", t!("report-none"));
+                }
+                for u in items {
+                    let shows = format!("{:>5}", u.shows);
+                    if images {
+                        println!("{}", t!("report-usage", shows = shows, name = u.name));
+                    } else {
+                        println!("{}", t!("report-usage-images", shows = shows, name = u.name, images = u.images));
+                    }
+                }
+            };
+            section(t!("report-profiles"), &report.profiles, false);
+            section(t!("report-directories"), &report.directories, false);
+            section(t!("report-most-shown"), &report.most_shown, true);
+            section(t!("report-least-shown"), &report.least_shown, true);
+            
+            println!("\n{}", t!("report-never-shown", count = report.never_shown.len()));
+            for path in report.never_shown.iter().take(limit) {
+                println!("  {}", path.display());
+            }
+            if report.never_shown.len() > limit {
+                println!("{}", t!("report-more", count = report.never_shown.len() - limit));
+            }
+            
+            println!("\n{}", t!("report-broken", count = report.broken.len()));
+            for path in &report.broken {
+                println!("  {}", path.display());
+            }
+        }
+        
+        Commands::Gc { dry_run } => {
+            // The daemon also prunes its history; without one the index
+            // lock keeps this safe on its own
+            let report = match Client::connect().await {
+                Ok(mut client) => client.gc(dry_run).await?,
+                Err(_) => gc::collect(dry_run)?,
+            };
+            
+            for path in &report.index_entries {
+                println!("  {}", path);
+            }
+            let summary = t!(
+                "gc-summary",
+                index = report.index_entries.len(),
+                history = report.history_entries,
+                files = report.cache_files,
+                mb = format!("{:.1}", report.bytes as f64 / 1048576.0),
+            );
+            
+            if dry_run {
+                println!("{}", t!("gc-would-remove", summary = summary));
+            } else {
+                output::success(&t!("gc-removed", summary = summary));
+            }
+        }
+        
+        Commands::LogLevel { level } => {
+            let mut client = Client::connect().await?;
+            client.set_log_level(&level).await?;
+        }
+        
+        Commands::Timeline { count } => {
+            let events = timeline::recent(count);
+            if events.is_empty() {
+                println!("{}", t!("timeline-empty"));
+            }
+            for event in events {
+                let time = chrono::DateTime::from_timestamp(event.time, 0)
+                    .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                println!("{}  {:<9}  {}", time, event.kind, event.message);
+            }
+        }
+        
         Commands::Reload => {
             let mut client = Client::connect().await?;
             client.reload_config().await?;
         }
         
-        Commands::Detect => {
-            let mut client = Client::connect().await?;
-            client.detect_and_switch_profile().await?;
+        Commands::Detect { explain } => {
+            match daemon_or_local(cli.no_daemon).await? {
+                Some(mut client) if explain => client.explain_detection().await?,
+                Some(mut client) => client.detect_and_switch_profile().await?,
+                None if explain => anyhow::bail!("{}", t!("needs-daemon", command = "--explain")),
+                None => match local_manager(cli.config.as_deref())?.detect_and_switch().await? {
+                    Some(profile) => println!("{}", t!("switched-profile", profile = profile)),
+                    None => println!("{}", t!("profile-unchanged")),
+                },
+            }
         }
         
-        Commands::Monitors { watch } => {
+        Commands::Monitors { watch, json } => {
             if watch {
-                watch_monitors().await?;
+                watch_monitors(json).await?;
             } else {
                 show_monitors().await?;
             }
@@ -220,46 +894,114 @@ async fn main() -> Result<()> {
 }
 
 
-async fn run_event_monitor() -> Result<()> {
-    use crate::hyprland_event::{monitor_events, HyprlandEvent};
+/// The daemon client, or `None` when the daemon isn't reachable and
+/// `--no-daemon` asks to do the work in this process instead
+async fn daemon_or_local(no_daemon: bool) -> Result<Option<Client>> {
+    match Client::connect().await {
+        Ok(client) => Ok(Some(client)),
+        Err(e) if no_daemon => {
+            tracing::info!("No daemon reachable, running in-process: {}", e);
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Mode `switch --random`/`--next` asks for, if any
+fn switch_mode(random: bool, next: bool) -> Option<SwitchMode> {
+    match (random, next) {
+        (true, _) => Some(SwitchMode::Random),
+        (_, true) => Some(SwitchMode::Sequential),
+        _ => None,
+    }
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    size.split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, e.g. 1920x1080, got '{}'", size))
+}
+
+/// Width and height covering every connected monitor, for generated
+/// wallpapers
+async fn screen_size() -> (u32, u32) {
+    let monitors = match hyprland_ipc::HyprlandIPC::new() {
+        Ok(ipc) => ipc.get_monitors().await.unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    // Rotated monitors report their mode's width and height
+    let sizes: Vec<(u32, u32)> = monitors.iter()
+        .map(|m| (m.width.max(0) as u32, m.height.max(0) as u32))
+        .zip(monitors.iter().map(|m| m.transform % 2 == 1))
+        .map(|((w, h), rotated)| if rotated { (h, w) } else { (w, h) })
+        .collect();
+    let width = sizes.iter().map(|s| s.0).max().unwrap_or(0);
+    let height = sizes.iter().map(|s| s.1).max().unwrap_or(0);
+    if width == 0 || height == 0 {
+        return generate::DEFAULT_SIZE;
+    }
+    (width, height)
+}
+
+/// `path` as the daemon knows it, or the current wallpaper
+async fn marked_path(path: Option<String>) -> Result<String> {
+    match path {
+        Some(path) => {
+            let path = desktop::local_path(&path)?;
+            let path = path.canonicalize().with_context(|| format!("Cannot open {:?}", path))?;
+            Ok(path.to_string_lossy().to_string())
+        }
+        None => Client::connect().await?.status().await?
+            .current_wallpaper
+            .context("No wallpaper has been set yet"),
+    }
+}
+
+async fn set_wallpaper(path: &std::path::Path, no_daemon: bool, config: Option<&str>) -> Result<()> {
+    let path = path.canonicalize()
+        .with_context(|| format!("Cannot open {:?}", path))?
+        .to_string_lossy()
+        .to_string();
+    match daemon_or_local(no_daemon).await? {
+        Some(mut client) => client.set_wallpaper(&path).await?,
+        None => {
+            local_manager(config)?.set_wallpaper(&path).await?;
+            println!("{}", t!("switched-wallpaper", wallpaper = path));
+        }
+    }
+    Ok(())
+}
+
+fn local_manager(config: Option<&str>) -> Result<swww_manager::Manager> {
+    swww_manager::Manager::load(config)
+}
+
+async fn run_event_monitor(config: &Config) -> Result<()> {
+    use crate::hyprland_event::{monitor_events, EventKind, HyprlandEvent, Subscription};
+    use crate::monitor::MonitorManager;
     use futures::FutureExt;
-    use std::sync::Arc;
-    use std::time::Duration;
-    use tokio::sync::Mutex;
-    use tokio::task::JoinHandle;
 
-    let scheduled_task: Arc<Mutex<Option<JoinHandle<()>>>> = Arc::new(Mutex::new(None));
-    let debounce_delay = Duration::from_millis(900);
+    let debouncer = MonitorManager::new()
+        .with_detection(&config.monitor_detection)
+        .debouncer();
+    let subscription = Subscription::only(&[EventKind::MonitorAdded, EventKind::MonitorRemoved]).coalesced();
     
-    monitor_events(move |event| {
-        let scheduled_task = Arc::clone(&scheduled_task);
-        let debounce_delay = debounce_delay;
+    monitor_events(subscription, move |event| {
+        let debouncer = debouncer.clone();
         async move {
-            match event {
-                HyprlandEvent::MonitorAdded { .. } | HyprlandEvent::MonitorRemoved { .. }=> {
-                    let event_type = match event {
-                        HyprlandEvent::MonitorAdded { .. } => "added",
-                        HyprlandEvent::MonitorRemoved { .. } => "removed",
-                        _ => unreachable!(),
-                    };
-                    info!("Monitor: {} (debouncing)", event_type);
-
-                    if let Some(handle) = scheduled_task.lock().await.take() {
-                        handle.abort();
-                    }
-
-                    let handle = tokio::spawn(async move {
-                        tokio::time::sleep(debounce_delay).await;
-                        if let Ok(mut client) = Client::connect().await
-                            && let Err(e) = client.detect_and_switch_profile().await {
-                                tracing::warn!("Failed to switch profile after monitor change: {}", e);
-                            }
-                    });
+            let event_type = match event {
+                HyprlandEvent::MonitorAdded { .. } => "added",
+                _ => "removed",
+            };
+            info!("Monitor: {} (debouncing)", event_type);
 
-                    *scheduled_task.lock().await = Some(handle);
-                }
-                _ => {}
-            }
+            debouncer.schedule(async move {
+                if let Ok(mut client) = Client::connect().await
+                    && let Err(e) = client.detect_and_switch_profile().await {
+                        tracing::warn!("Failed to switch profile after monitor change: {}", e);
+                    }
+            }).await;
         }.boxed()
     }).await
 }
@@ -268,71 +1010,146 @@ async fn show_monitors() -> Result<()> {
     use hyprland_ipc::HyprlandIPC;
     
     let ipc = HyprlandIPC::new()?;
-    let monitors = ipc.get_monitors().await?;
+    let hyprland_ipc::Layout { monitors, workspaces, clients } = ipc.get_layout().await?;
     
-    println!("\nConnected Monitors:");
-    println!("{}", "═".repeat(70));
+    output::heading(&t!("monitors-title"));
+    let mut table = output::table([
+        String::new(),
+        t!("col-monitor"),
+        t!("col-description"),
+        t!("col-resolution"),
+        t!("col-position"),
+        t!("col-scale"),
+        t!("col-workspace"),
+        t!("col-windows"),
+        t!("col-fullscreen"),
+        t!("col-status"),
+    ]);
     
     for monitor in monitors {
-        let status = if monitor.dpmsStatus { "On" } else { "Off" };
-        let indicator = if monitor.focused { "➤" } else { " " };
-        println!(
-            "{} {}",
+        let status = if monitor.dpmsStatus { t!("on") } else { t!("off") };
+        let indicator = if monitor.focused { output::Mark::Focus.cell() } else { Cell::new("") };
+        let windows = workspaces.iter()
+            .find(|w| w.id == monitor.activeWorkspace.id)
+            .map(|ws| ws.windows.to_string())
+            .unwrap_or_default();
+        let fullscreen = clients.iter()
+            .find(|c| c.fullscreen && c.workspace.id == monitor.activeWorkspace.id)
+            .map(|client| format!("{} ({})", client.title, client.class))
+            .unwrap_or_default();
+        table.add_row(vec![
             indicator,
-            monitor.name
-        );
-        println!("  Description: {} {}", monitor.make, monitor.model);
-        println!("  Resolution:  {}x{} @ {:.2}Hz", monitor.width, monitor.height, monitor.refreshRate);
-        println!("  Position:    ({}, {})", monitor.x, monitor.y);
-        println!("  Scale:       {:.2}x", monitor.scale);
-        println!("  Workspace:   {} (ID: {})", 
-            monitor.activeWorkspace.name, monitor.activeWorkspace.id);
-        println!("  Status:      {}", 
-            if monitor.dpmsStatus { "On" } else { "Off" });
+            Cell::new(&monitor.name),
+            Cell::new(format!("{} {}", monitor.make, monitor.model)),
+            Cell::new(format!("{}x{} @ {:.2}Hz", monitor.width, monitor.height, monitor.refreshRate)),
+            Cell::new(format!("{},{}", monitor.x, monitor.y)),
+            Cell::new(format!("{:.2}x", monitor.scale)),
+            Cell::new(format!("{} ({})", monitor.activeWorkspace.name, monitor.activeWorkspace.id)),
+            Cell::new(windows),
+            Cell::new(fullscreen),
+            Cell::new(status),
+        ]);
     }
     
-    println!();
+    println!("{}", table);
     Ok(())
 }
 
-async fn watch_monitors() -> Result<()> {
+/// A monitor change printed by `monitors --watch`
+#[derive(serde::Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum MonitorChange {
+    Added { name: String, description: String },
+    Removed { name: String, description: String },
+    Dpms { name: String, on: bool },
+}
+
+/// Follow Hyprland's event socket and print monitor changes as they arrive.
+/// Hyprland doesn't announce DPMS changes, so power states are compared
+/// with the last known ones whenever a monitor or focus event comes in.
+async fn watch_monitors(json: bool) -> Result<()> {
+    use crate::hyprland_event::{EventListener, HyprlandEvent};
+    use futures::StreamExt;
     use hyprland_ipc::HyprlandIPC;
-    
-    println!("Watching for monitor changes... (Press Ctrl+C to exit)\n");
-    
+    use std::collections::HashMap;
+
     let ipc = HyprlandIPC::new()?;
-    let mut last_monitors = ipc.get_monitors().await?;
-    
+    let snapshot = async || -> Result<HashMap<String, hyprland_ipc::Monitor>> {
+        Ok(ipc.get_monitors().await?.into_iter().map(|m| (m.name.clone(), m)).collect())
+    };
+    let mut known = snapshot().await?;
+    let mut events = Box::pin(EventListener::connect().await?.into_stream());
+
+    if !json {
+        println!("{}\n", t!("monitors-watching"));
+    }
+
     loop {
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-        
-        match ipc.get_monitors().await {
-            Ok(current_monitors) => {
-                if current_monitors.len() != last_monitors.len() {
-                    println!("\n[{}] Monitor count changed: {} → {}", 
-                        chrono::Local::now().format("%H:%M:%S"),
-                        last_monitors.len(),
-                        current_monitors.len()
-                    );
-                    
-                    for monitor in &current_monitors {
-                        if !last_monitors.iter().any(|m| m.activeWorkspace.name == monitor.activeWorkspace.name) {
-                            println!("  + Added: {} ({})", monitor.activeWorkspace.name, format!("{} {}", monitor.make, monitor.model));
-                        }
-                    }
-                    
-                    for monitor in &last_monitors {
-                        if !current_monitors.iter().any(|m| m.activeWorkspace.name == monitor.activeWorkspace.name) {
-                            println!("  - Removed: {} ({})", monitor.activeWorkspace.name, format!("{} {}", monitor.make, monitor.model));
-                        }
-                    }
-                    
-                    last_monitors = current_monitors;
-                }
+        let Some(event) = events.next().await else {
+            tracing::warn!("Event stream ended, reconnecting...");
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            events = Box::pin(EventListener::connect().await?.into_stream());
+            continue;
+        };
+        if !matches!(
+            event,
+            HyprlandEvent::MonitorAdded { .. } | HyprlandEvent::MonitorRemoved { .. } | HyprlandEvent::FocusedMon { .. }
+        ) {
+            continue;
+        }
+
+        let current = snapshot().await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to get monitors: {}", e);
+            known.clone()
+        });
+        // Older Hyprland doesn't send descriptions with the event
+        let describe = |name: &str| {
+            current.get(name).or(known.get(name)).map(|m| m.description.clone()).unwrap_or_default()
+        };
+
+        let mut changes = Vec::new();
+        match event {
+            HyprlandEvent::MonitorAdded { name, description, .. } => {
+                let description = description.unwrap_or_else(|| describe(&name));
+                changes.push(MonitorChange::Added { name, description });
             }
-            Err(e) => {
-                tracing::warn!("Failed to get monitors: {}", e);
+            HyprlandEvent::MonitorRemoved { name, description, .. } => {
+                let description = description.unwrap_or_else(|| describe(&name));
+                changes.push(MonitorChange::Removed { name, description });
             }
+            _ => {}
+        }
+        for (name, monitor) in &current {
+            if known.get(name).is_some_and(|before| before.dpmsStatus != monitor.dpmsStatus) {
+                changes.push(MonitorChange::Dpms { name: name.clone(), on: monitor.dpmsStatus });
+            }
+        }
+        known = current;
+
+        for change in changes {
+            print_monitor_change(&change, json)?;
         }
     }
 }
+
+fn print_monitor_change(change: &MonitorChange, json: bool) -> Result<()> {
+    if json {
+        let mut line = serde_json::to_value(change)?;
+        line["time"] = chrono::Local::now().to_rfc3339().into();
+        println!("{}", line);
+        return Ok(());
+    }
+
+    let message = match change {
+        MonitorChange::Added { name, description } => {
+            t!("monitor-added", name = name, description = description)
+        }
+        MonitorChange::Removed { name, description } => {
+            t!("monitor-removed", name = name, description = description)
+        }
+        MonitorChange::Dpms { name, on: true } => t!("monitor-dpms-on", name = name),
+        MonitorChange::Dpms { name, on: false } => t!("monitor-dpms-off", name = name),
+    };
+    output::event(&message);
+    Ok(())
+}
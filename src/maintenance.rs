@@ -0,0 +1,141 @@
+use crate::config::Maintenance;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{debug, info, warn};
+
+/// Where swww keeps its copies of the images it has shown
+pub fn swww_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("swww"))
+}
+
+/// Where the time of the last clear is kept, so the schedule carries over
+/// server restarts
+fn stamp_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .map(|p| p.join("swww-manager/swww-cache-cleared"))
+}
+
+/// When the cache was last cleared, as recorded by [`record_clear`]
+fn last_clear(stamp: &Path) -> Option<SystemTime> {
+    let secs = fs::read_to_string(stamp).ok()?.trim().parse().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn record_clear(stamp: &Path, at: SystemTime) -> Result<()> {
+    if let Some(parent) = stamp.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let secs = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    fs::write(stamp, secs.to_string()).with_context(|| format!("Failed to write {:?}", stamp))
+}
+
+/// Total size in bytes of the files under `dir`
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Whether to clear now, `since_clear` after the last clear with the cache
+/// at `size` bytes
+fn due(settings: &Maintenance, since_clear: Duration, size: u64) -> bool {
+    let scheduled = settings.clear_every_hours > 0
+        && since_clear >= Duration::from_secs(settings.clear_every_hours * 3600);
+    let too_big = settings.max_cache_mb > 0 && size > settings.max_cache_mb * 1024 * 1024;
+    scheduled || too_big
+}
+
+pub async fn clear_cache() -> Result<()> {
+    let output = Command::new("swww")
+        .arg("clear-cache")
+        .output()
+        .await
+        .context("Failed to execute swww")?;
+    if !output.status.success() {
+        anyhow::bail!("swww clear-cache failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Clear swww's cache whenever `settings` call for it, until the task is
+/// aborted. The schedule counts from the last clear, or the first time
+/// maintenance ran.
+pub async fn run(settings: Maintenance) {
+    let (Some(dir), Some(stamp)) = (swww_cache_dir(), stamp_path()) else {
+        warn!("Could not determine swww's cache path, cache maintenance is off");
+        return;
+    };
+    let mut last_clear = match last_clear(&stamp) {
+        Some(at) => at,
+        None => {
+            let now = SystemTime::now();
+            if let Err(e) = record_clear(&stamp, now) {
+                warn!("Failed to record the cache maintenance start: {:#}", e);
+            }
+            now
+        }
+    };
+    let mut ticker = interval(Duration::from_secs(settings.check_minutes.max(1) * 60));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        ticker.tick().await;
+        let measured = dir.clone();
+        let size = tokio::task::spawn_blocking(move || dir_size(&measured)).await.unwrap_or(0);
+        if !due(&settings, last_clear.elapsed().unwrap_or_default(), size) {
+            debug!("swww cache at {:.1} MB, not clearing", size as f64 / 1048576.0);
+            continue;
+        }
+        match clear_cache().await {
+            Ok(()) => {
+                info!("Cleared swww's cache ({:.1} MB)", size as f64 / 1048576.0);
+                last_clear = SystemTime::now();
+                if let Err(e) = record_clear(&stamp, last_clear) {
+                    warn!("Failed to record the cache clear: {:#}", e);
+                }
+            }
+            Err(e) => warn!("Failed to clear swww's cache: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_due() {
+        let settings = Maintenance { enabled: true, clear_every_hours: 24, max_cache_mb: 100, check_minutes: 30 };
+        let hour = Duration::from_secs(3600);
+        assert!(!due(&settings, hour, 50 * 1024 * 1024));
+        assert!(due(&settings, 24 * hour, 0));
+        assert!(due(&settings, hour, 101 * 1024 * 1024));
+
+        let size_only = Maintenance { clear_every_hours: 0, ..settings.clone() };
+        assert!(!due(&size_only, 1000 * hour, 0));
+        let schedule_only = Maintenance { max_cache_mb: 0, ..settings };
+        assert!(!due(&schedule_only, hour, u64::MAX));
+    }
+
+    #[test]
+    fn test_last_clear_persists() {
+        let stamp = std::env::temp_dir().join(format!("swww-manager-cleared-{}", std::process::id()));
+        assert_eq!(last_clear(&stamp), None);
+        let at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        record_clear(&stamp, at).unwrap();
+        assert_eq!(last_clear(&stamp), Some(at));
+        fs::remove_file(&stamp).unwrap();
+    }
+}
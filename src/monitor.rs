@@ -1,11 +1,17 @@
-// use crate::hyprland_ipc::{HyprlandIPC, Monitor as HyprMonitor};
-use crate::hyprland_ipc::HyprlandIPC;
+use crate::config::MonitorDetection;
+use crate::hyprland_ipc::{HyprlandIPC, Monitor as HyprMonitor};
 use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
 use tracing::warn;
 
 #[derive(Clone)]
 pub struct MonitorManager {
-    ipc: Option<HyprlandIPC>,
+    detection: MonitorDetection,
+    debouncer: Debouncer,
 }
 
 impl Default for MonitorManager {
@@ -16,43 +22,61 @@ impl Default for MonitorManager {
 
 impl MonitorManager {
     pub fn new() -> Self {
-        let ipc = match HyprlandIPC::new() {
-            Ok(ipc) => Some(ipc),
-            Err(e) => {
-                warn!("Failed to initialize Hyprland IPC: {}. Monitor detection disabled.", e);
-                None
-            }
-        };
+        if let Err(e) = HyprlandIPC::shared() {
+            warn!("Hyprland IPC not available yet: {}. Monitor detection starts once it appears.", e);
+        }
+
+        let detection = MonitorDetection::default();
+        let debouncer = Debouncer::new(Duration::from_millis(detection.debounce_ms));
+        Self { detection, debouncer }
+    }
+
+    /// The shared IPC handle, looked up on every use so detection starts
+    /// working as soon as Hyprland comes up
+    fn ipc(&self) -> Option<HyprlandIPC> {
+        HyprlandIPC::shared().ok()
+    }
+
+    pub fn with_detection(mut self, detection: &MonitorDetection) -> Self {
+        self.set_detection(detection);
+        self
+    }
+
+    pub fn set_detection(&mut self, detection: &MonitorDetection) {
+        self.detection = detection.clone();
+        self.debouncer.set_delay(Duration::from_millis(detection.debounce_ms));
+        HyprlandIPC::set_timeout(Duration::from_millis(detection.ipc_timeout_ms));
+    }
 
-        Self { ipc }
+    /// Debouncer for hotplug events using the configured quiet period. It
+    /// is shared by every clone of this manager, so `set_detection` on any
+    /// of them changes the delay of debouncers already handed out.
+    pub fn debouncer(&self) -> Debouncer {
+        self.debouncer.clone()
     }
 
     pub async fn get_monitors(&self) -> Result<Vec<String>> {
-        if let Some(ipc) = &self.ipc {
-            let monitors = ipc.get_monitors().await?;
-            Ok(monitors
-                .into_iter()
-                .filter(|m| m.dpmsStatus && m.width > 0 && m.height > 0)
-                .map(|m| m.name.clone())
-                .collect())
-        } else {
-            Err(anyhow!("Hyprland IPC not available"))
-        }
+        Ok(self
+            .get_monitor_details()
+            .await?
+            .into_iter()
+            .map(|m| m.name)
+            .collect())
     }
 
-    /*
     pub async fn get_monitor_details(&self) -> Result<Vec<HyprMonitor>> {
-        if let Some(ipc) = &self.ipc {
+        if let Some(ipc) = self.ipc() {
             let monitors = ipc.get_monitors().await?;
             Ok(monitors
                 .into_iter()
-                .filter(|m| m.dpmsStatus && m.width > 0 && m.height > 0)
+                .filter(|m| m.dpmsStatus && !m.disabled && m.width > 0 && m.height > 0)
                 .collect())
         } else {
             Err(anyhow!("Hyprland IPC not available"))
         }
     }
 
+    /*
     pub async fn get_focused_monitor(&self) -> Result<String> {
         if let Some(ipc) = &self.ipc {
             let monitors = ipc.get_monitors().await?;
@@ -66,36 +90,71 @@ impl MonitorManager {
         }
     }*/
     
-    pub async fn get_stable_monitors(&self) -> Result<Vec<String>> {
-        use tokio::time::{sleep, Duration, Instant};
-        let total = Duration::from_millis(1200);
-        let step = Duration::from_millis(200);
-        let required_same = 3usize;
+    /// Monitor details once the set of names stops changing, sorted by name.
+    /// At least one sample is taken, and with `stabilize_ms = 0` it is the
+    /// answer.
+    pub async fn get_stable_monitors(&self) -> Result<Vec<HyprMonitor>> {
+        use tokio::time::{sleep, Instant};
+        let required_same = self.detection.samples.max(1);
+        let total = Duration::from_millis(self.detection.stabilize_ms);
+        let step = total / (required_same as u32 * 2);
 
         let start = Instant::now();
-        let mut last: Option<Vec<String>> = None;
+        let mut last: Option<Vec<HyprMonitor>> = None;
         let mut same = 0usize;
 
-        while start.elapsed() < total {
-            let mut current = self.get_monitors().await.unwrap_or_default();
-            current.sort();
-            if let Some(prev) = &last {
-                if prev == &current {
-                    same += 1;
-                    if same >= required_same {
-                        return Ok(current);
-                    }
-                } else {
-                    same = 1;
-                    last = Some(current);
-                }
-            } else {
-                same = 1;
-                last = Some(current);
+        loop {
+            let mut current = self.get_monitor_details().await.unwrap_or_default();
+            current.sort_by(|a, b| a.name.cmp(&b.name));
+            match &last {
+                Some(prev) if names(prev) == names(&current) => same += 1,
+                _ => same = 1,
             }
+            if same >= required_same || start.elapsed() >= total {
+                return Ok(current);
+            }
+            last = Some(current);
             sleep(step).await;
         }
+    }
+}
+
+pub fn names(monitors: &[HyprMonitor]) -> Vec<String> {
+    monitors.iter().map(|m| m.name.clone()).collect()
+}
+
+/// Runs only the most recently scheduled task once `delay` has passed
+/// without another call to `schedule`.
+#[derive(Clone)]
+pub struct Debouncer {
+    delay: Arc<std::sync::Mutex<Duration>>,
+    pending: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl Debouncer {
+    pub fn new(delay: Duration) -> Self {
+        Self { delay: Arc::new(std::sync::Mutex::new(delay)), pending: Arc::new(Mutex::new(None)) }
+    }
+
+    /// Change the quiet period for this debouncer and all its clones,
+    /// starting with the next `schedule`
+    pub fn set_delay(&self, delay: Duration) {
+        *self.delay.lock().unwrap() = delay;
+    }
+
+    pub async fn schedule<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut pending = self.pending.lock().await;
+        if let Some(handle) = pending.take() {
+            handle.abort();
+        }
 
-        Ok(last.unwrap_or_default())
+        let delay = *self.delay.lock().unwrap();
+        *pending = Some(tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            task.await;
+        }));
     }
 }
@@ -1,5 +1,7 @@
 use crate::hyprland_ipc::HyprlandIPC;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tokio::process::Command;
 use tracing::warn;
 
 #[allow(dead_code)]
@@ -33,13 +35,18 @@ pub async fn send_error(message: &str) -> Result<()> {
     send_with_color(NotificationKind::Error, &text, "rgb(ff8888)", 8000).await
 }
 
+pub async fn send_warning(message: &str) -> Result<()> {
+    let text = message.to_string();
+    send_with_color(NotificationKind::Warning, &text, "rgb(ffcc66)", 6000).await
+}
+
 pub async fn send_success(message: &str) -> Result<()> {
     let text = message.to_string();
     send_with_color(NotificationKind::Success, &text, "rgb(88ff88)", 3000).await
 }
 
 async fn send_with_color(kind: NotificationKind, message: &str, color: &str, duration_ms: u32) -> Result<()> {
-    match HyprlandIPC::new() {
+    match HyprlandIPC::shared() {
         Ok(ipc) => {
             let icon = icon_for(kind);
             ipc.notify(icon, duration_ms, color, message).await?;
@@ -51,6 +58,30 @@ async fn send_with_color(kind: NotificationKind, message: &str, color: &str, dur
     Ok(())
 }
 
+/// Desktop notification with buttons (`actions` are `(key, label)`),
+/// shown through `notify-send --wait` (libnotify 0.7.9 or newer) since
+/// Hyprland's own notifications can't take input. Returns the key of the
+/// button pressed, or None when dismissed or not answered in `timeout`.
+pub async fn ask(title: &str, message: &str, actions: &[(&str, &str)], timeout: Duration) -> Result<Option<String>> {
+    let mut cmd = Command::new("notify-send");
+    cmd.args(["--app-name=swww-manager", "--wait"])
+        .arg(format!("--expire-time={}", timeout.as_millis()))
+        .args(actions.iter().map(|(key, label)| format!("--action={}={}", key, label)))
+        .args([title, message])
+        .kill_on_drop(true);
+
+    let output = match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(output) => output.context("Failed to run notify-send")?,
+        Err(_) => return Ok(None),
+    };
+    if !output.status.success() {
+        anyhow::bail!("notify-send failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let answer = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(actions.iter().any(|(key, _)| *key == answer).then_some(answer))
+}
+
 #[allow(dead_code)]
 pub fn send_sync(title: &str, message: &str) -> Result<()> {
     let title = title.to_owned();
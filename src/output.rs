@@ -0,0 +1,203 @@
+//! Terminal output shared by the CLI commands: headings, aligned tables and
+//! status markers. Color and Unicode are decided once at startup; color is
+//! off with `--no-color`, `NO_COLOR` or when stdout isn't a terminal, and
+//! box drawing falls back to ASCII with `--ascii` or a non-UTF-8 locale.
+
+use comfy_table::presets::{ASCII_FULL_CONDENSED, NOTHING, UTF8_FULL_CONDENSED};
+use comfy_table::{Attribute, Cell, Color, ContentArrangement, Table};
+use owo_colors::OwoColorize;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+/// Widest layout used even on very wide terminals
+const MAX_WIDTH: u16 = 100;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    pub color: bool,
+    pub unicode: bool,
+}
+
+impl Style {
+    fn detect(no_color: bool, ascii: bool) -> Self {
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Self {
+            color: !no_color && !no_color_env && std::io::stdout().is_terminal(),
+            unicode: !ascii && utf8_locale(),
+        }
+    }
+}
+
+fn utf8_locale() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+        // No locale at all is typically a minimal system; assume modern
+        .unwrap_or(true)
+}
+
+static STYLE: OnceLock<Style> = OnceLock::new();
+
+/// Set up from the global `--no-color` and `--ascii` flags
+pub fn init(no_color: bool, ascii: bool) {
+    STYLE.set(Style::detect(no_color, ascii)).ok();
+}
+
+pub fn style() -> Style {
+    *STYLE.get_or_init(|| Style::detect(false, false))
+}
+
+fn width() -> u16 {
+    terminal_size::terminal_size()
+        .map(|(w, _)| w.0)
+        .unwrap_or(80)
+        .min(MAX_WIDTH)
+}
+
+/// Section title followed by a rule across the usable width
+pub fn heading(title: &str) {
+    let style = style();
+    let rule = if style.unicode { "─" } else { "-" }.repeat(width() as usize);
+    if style.color {
+        println!("\n{}", title.bold().cyan());
+        println!("{}", rule.dimmed());
+    } else {
+        println!("\n{}", title);
+        println!("{}", rule);
+    }
+}
+
+/// Bordered table with a bold header, wrapped to the terminal width
+pub fn table<I, S>(header: I) -> Table
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let style = style();
+    let mut table = Table::new();
+    table
+        .load_preset(if style.unicode { UTF8_FULL_CONDENSED } else { ASCII_FULL_CONDENSED })
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_width(width())
+        .set_header(header.into_iter().map(|h| header_cell(h.into())));
+    if style.color {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+    table
+}
+
+fn header_cell(text: String) -> Cell {
+    if style().color {
+        Cell::new(text).add_attribute(Attribute::Bold)
+    } else {
+        Cell::new(text)
+    }
+}
+
+/// Borderless `label  value` lines with the labels aligned
+pub fn key_values(rows: &[(String, String)]) {
+    let style = style();
+    let mut table = Table::new();
+    table
+        .load_preset(NOTHING)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_width(width());
+    if style.color {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+    for (label, value) in rows {
+        table.add_row(vec![header_cell(format!("{}:", label)), Cell::new(value)]);
+    }
+    for line in table.lines() {
+        println!("{}", line.trim_end());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mark {
+    /// Selected / current / succeeded
+    Ok,
+    /// Matched but not chosen
+    Partial,
+    /// Not matched / failed
+    No,
+    /// Focused monitor
+    Focus,
+}
+
+impl Mark {
+    fn symbol(self, unicode: bool) -> &'static str {
+        match (self, unicode) {
+            (Mark::Ok, true) => "✓",
+            (Mark::Ok, false) => "*",
+            (Mark::Partial, _) => "~",
+            (Mark::No, true) => "✗",
+            (Mark::No, false) => "x",
+            (Mark::Focus, true) => "➤",
+            (Mark::Focus, false) => ">",
+        }
+    }
+
+    fn table_color(self) -> Color {
+        match self {
+            Mark::Ok | Mark::Focus => Color::Green,
+            Mark::Partial => Color::Yellow,
+            Mark::No => Color::Red,
+        }
+    }
+
+    /// The marker for use inside a table
+    pub fn cell(self) -> Cell {
+        let style = style();
+        let cell = Cell::new(self.symbol(style.unicode));
+        if style.color { cell.fg(self.table_color()) } else { cell }
+    }
+}
+
+impl std::fmt::Display for Mark {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let style = style();
+        let symbol = self.symbol(style.unicode);
+        if !style.color {
+            return f.write_str(symbol);
+        }
+        match self {
+            Mark::Ok | Mark::Focus => write!(f, "{}", symbol.green()),
+            Mark::Partial => write!(f, "{}", symbol.yellow()),
+            Mark::No => write!(f, "{}", symbol.red()),
+        }
+    }
+}
+
+/// `✓ message` for completed actions
+pub fn success(message: &str) {
+    println!("{} {}", Mark::Ok, message);
+}
+
+/// `[HH:MM:SS] message` for changes reported as they happen
+pub fn event(message: &str) {
+    let time = format!("[{}]", chrono::Local::now().format("%H:%M:%S"));
+    if style().color {
+        println!("{} {}", time.dimmed(), message);
+    } else {
+        println!("{} {}", time, message);
+    }
+}
+
+/// Warning line on stderr, highlighted when stderr is a terminal
+pub fn warning(message: &str) {
+    if style().color && std::io::stderr().is_terminal() {
+        eprintln!("{}", message.yellow());
+    } else {
+        eprintln!("{}", message);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::config::Config;
+use crate::index::WallpaperIndex;
+use crate::scan::scan_dirs;
+use crate::wallpaper::{expand_dir, expand_dirs};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Usage {
+    pub name: String,
+    pub images: usize,
+    pub shows: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub total_images: usize,
+    pub most_shown: Vec<Usage>,
+    pub least_shown: Vec<Usage>,
+    pub never_shown: Vec<PathBuf>,
+    pub directories: Vec<Usage>,
+    pub profiles: Vec<Usage>,
+    pub broken: Vec<PathBuf>,
+}
+
+/// Summarize how often the wallpapers of every profile and season have been
+/// shown, according to the wallpaper index. Lists are capped at `limit`.
+pub fn build(config: &Config, limit: usize) -> UsageReport {
+    let index = WallpaperIndex::load();
+    let shows = |path: &Path| {
+        index
+            .entries
+            .get(path)
+            .map(|e| e.shown_count.max(e.last_shown.is_some() as u32))
+            .unwrap_or(0)
+    };
+
+    let mut profiles = Vec::new();
+    let mut all_dirs = Vec::new();
+    for (name, profile) in &config.profiles {
+        let dirs = expand_dirs(profile);
+        let files = scan_dirs(&dirs);
+        profiles.push(Usage {
+            name: name.clone(),
+            images: files.len(),
+            shows: files.iter().map(|f| shows(f)).sum(),
+        });
+        all_dirs.extend(dirs);
+    }
+    all_dirs.extend(config.seasons.iter().flat_map(|s| s.dirs.iter().map(|d| expand_dir(d))));
+    all_dirs.sort();
+    all_dirs.dedup();
+
+    let files = scan_dirs(&all_dirs);
+    let mut images: Vec<Usage> = files
+        .iter()
+        .map(|f| Usage {
+            name: f.to_string_lossy().to_string(),
+            images: 1,
+            shows: shows(f),
+        })
+        .collect();
+
+    let mut directories: BTreeMap<PathBuf, Usage> = BTreeMap::new();
+    for (file, usage) in files.iter().zip(&images) {
+        let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+        let entry = directories.entry(dir.clone()).or_insert_with(|| Usage {
+            name: dir.to_string_lossy().to_string(),
+            images: 0,
+            shows: 0,
+        });
+        entry.images += 1;
+        entry.shows += usage.shows;
+    }
+    let mut directories: Vec<Usage> = directories.into_values().collect();
+    directories.sort_by(|a, b| b.shows.cmp(&a.shows).then(a.name.cmp(&b.name)));
+    profiles.sort_by(|a, b| b.shows.cmp(&a.shows).then(a.name.cmp(&b.name)));
+
+    let never_shown: Vec<PathBuf> = files.iter().filter(|f| shows(f) == 0).cloned().collect();
+    let broken: Vec<PathBuf> = files.iter().filter(|f| is_broken(f)).cloned().collect();
+
+    images.retain(|u| u.shows > 0);
+    images.sort_by(|a, b| b.shows.cmp(&a.shows).then(a.name.cmp(&b.name)));
+    let most_shown = images.iter().take(limit).cloned().collect();
+    let least_shown = images.iter().rev().take(limit).cloned().collect();
+
+    UsageReport {
+        total_images: files.len(),
+        most_shown,
+        least_shown,
+        never_shown,
+        directories,
+        profiles,
+        broken,
+    }
+}
+
+/// An image that is empty, unreadable, or whose header matches none of the
+/// formats swww understands
+pub fn is_broken(path: &Path) -> bool {
+    let mut header = [0u8; 12];
+    let read = File::open(path).and_then(|mut f| f.read(&mut header));
+    match read {
+        Ok(n) if n >= 4 => !(header.starts_with(b"\x89PNG")
+            || header.starts_with(b"\xff\xd8\xff")
+            || header.starts_with(b"GIF8")
+            || header.starts_with(b"BM")
+            || (header.starts_with(b"RIFF") && &header[8..12] == b"WEBP")),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_broken() {
+        let dir = std::env::temp_dir().join(format!("swww-manager-report-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let png = dir.join("ok.png");
+        std::fs::write(&png, b"\x89PNG\r\n\x1a\n0000").unwrap();
+        let empty = dir.join("empty.jpg");
+        std::fs::write(&empty, b"").unwrap();
+        let text = dir.join("text.webp");
+        std::fs::write(&text, b"not an image").unwrap();
+
+        assert!(!is_broken(&png));
+        assert!(is_broken(&empty));
+        assert!(is_broken(&text));
+        assert!(is_broken(&dir.join("missing.png")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
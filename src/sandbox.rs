@@ -0,0 +1,238 @@
+//! Opt-in hardening for `serve` (`[sandbox] enabled = true`). Once the
+//! configuration is loaded, Landlock limits the server to the paths it
+//! needs and seccomp takes away syscalls a wallpaper daemon never uses.
+//! Both are inherited by swww, curl and notify-send, so the system
+//! locations they run from stay readable.
+
+use crate::config::Config;
+use anyhow::Result;
+use tracing::warn;
+#[cfg(any(feature = "sandbox", test))]
+use {crate::daemon, std::path::{Path, PathBuf}};
+#[cfg(feature = "sandbox")]
+use {anyhow::Context, tracing::info};
+
+/// Read-only locations of the helper programs, their libraries and data
+#[cfg(any(feature = "sandbox", test))]
+const SYSTEM_READ: &[&str] = &[
+    "/usr", "/lib", "/lib64", "/bin", "/sbin", "/etc", "/opt", "/nix", "/proc", "/sys",
+];
+/// System locations helpers write to
+#[cfg(any(feature = "sandbox", test))]
+const SYSTEM_WRITE: &[&str] = &["/dev", "/tmp"];
+
+/// Filesystem access granted to the sandboxed server
+#[cfg(any(feature = "sandbox", test))]
+#[derive(Debug, Default)]
+pub struct Paths {
+    pub read: Vec<PathBuf>,
+    pub write: Vec<PathBuf>,
+    /// Our own directories, created up front since new top-level
+    /// directories can't be made once restricted
+    pub own: Vec<PathBuf>,
+}
+
+#[cfg(any(feature = "sandbox", test))]
+fn expand(path: &Path) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).into_owned())
+}
+
+/// Paths the server needs with `config`. Wallpaper directories added to the
+/// config later only become readable after a restart.
+#[cfg(any(feature = "sandbox", test))]
+pub fn paths(config: &Config, config_path: Option<&str>) -> Paths {
+    let mut paths = Paths::default();
+
+    paths.read.extend(SYSTEM_READ.iter().map(PathBuf::from));
+    if let Some(dir) = std::env::current_exe().ok().and_then(|e| e.parent().map(Path::to_path_buf)) {
+        // Needed to re-execute ourselves on restart
+        paths.read.push(dir);
+    }
+    for profile in config.profiles.values() {
+        paths.read.extend(profile.wallpaper_dirs.iter().map(|d| expand(d)));
+        paths.read.extend(profile.channels.values().flatten().map(|d| expand(d)));
+        paths.read.extend(profile.output_dirs.values().flatten().map(|d| expand(d)));
+    }
+    for season in &config.seasons {
+        paths.read.extend(season.dirs.iter().map(|d| expand(d)));
+    }
+    paths.read.push(expand(&config.clipboard_dir));
+    if let Some(wallpaper) = &config.lock.wallpaper {
+        paths.read.push(expand(wallpaper));
+    }
+
+    let config_file = config_path.map(String::from).or_else(Config::default_path);
+    if let Some(dir) = config_file.as_deref().and_then(|f| Path::new(f).parent()) {
+        paths.own.push(dir.to_path_buf());
+    }
+    if let Some(cache) = dirs::cache_dir() {
+        paths.own.push(cache.join("swww-manager"));
+        // swww keeps its own image cache here
+        paths.write.push(cache.join("swww"));
+    }
+    if let Some(state) = dirs::state_dir() {
+        paths.own.push(state.join("swww-manager"));
+    }
+    if config.handoff.enabled {
+        // Created here as well, the handoff would fail to make them later
+        let handoff = [&config.handoff.file, &config.handoff.link];
+        paths.own.extend(handoff.iter().filter_map(|f| expand(f).parent().map(Path::to_path_buf)));
+    }
+    paths.write.push(daemon::runtime_dir());
+    paths.write.extend(SYSTEM_WRITE.iter().map(PathBuf::from));
+    paths.write.extend(config.sandbox.allow_paths.iter().map(|p| expand(p)));
+    paths.write.extend(paths.own.iter().cloned());
+
+    paths
+}
+
+#[cfg(not(feature = "sandbox"))]
+pub fn apply(config: &Config, _config_path: Option<&str>) -> Result<()> {
+    if config.sandbox.enabled {
+        warn!("[sandbox] is enabled but this build has no sandbox support (feature \"sandbox\")");
+    }
+    Ok(())
+}
+
+/// Restrict this process (and everything it starts) when the sandbox is
+/// enabled. Must run before the runtime spawns extra threads, since
+/// Landlock only applies to the calling thread and its future children.
+#[cfg(feature = "sandbox")]
+pub fn apply(config: &Config, config_path: Option<&str>) -> Result<()> {
+    if !config.sandbox.enabled {
+        return Ok(());
+    }
+
+    let paths = paths(config, config_path);
+    for dir in &paths.own {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            warn!("Sandbox: could not create {:?}: {}", dir, e);
+        }
+    }
+
+    restrict_paths(&paths).context("Failed to apply Landlock rules")?;
+    restrict_syscalls().context("Failed to apply seccomp filter")?;
+    Ok(())
+}
+
+#[cfg(feature = "sandbox")]
+fn restrict_paths(paths: &Paths) -> Result<()> {
+    use landlock::{
+        ABI, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus,
+        path_beneath_rules,
+    };
+
+    let abi = ABI::V3;
+    // Paths that don't exist are skipped
+    let status = Ruleset::default()
+        .handle_access(AccessFs::from_all(abi))?
+        .create()?
+        .add_rules(path_beneath_rules(&paths.read, AccessFs::from_read(abi)))?
+        .add_rules(path_beneath_rules(&paths.write, AccessFs::from_all(abi)))?
+        .restrict_self()?;
+
+    match status.ruleset {
+        RulesetStatus::FullyEnforced => info!("Sandbox: filesystem access restricted"),
+        RulesetStatus::PartiallyEnforced => {
+            info!("Sandbox: filesystem access restricted (older kernel, some rules not enforced)")
+        }
+        RulesetStatus::NotEnforced => {
+            warn!("Sandbox: Landlock is not available in this kernel, filesystem access is not restricted")
+        }
+    }
+    Ok(())
+}
+
+/// Syscalls that are refused with EPERM. Everything else stays allowed, so
+/// the helpers we start keep working whatever libc they use.
+#[cfg(feature = "sandbox")]
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_chroot,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_reboot,
+    libc::SYS_kexec_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_userfaultfd,
+    libc::SYS_open_by_handle_at,
+    libc::SYS_name_to_handle_at,
+    libc::SYS_acct,
+    libc::SYS_quotactl,
+    libc::SYS_syslog,
+    libc::SYS_settimeofday,
+    libc::SYS_clock_settime,
+    libc::SYS_adjtimex,
+    libc::SYS_personality,
+];
+
+#[cfg(feature = "sandbox")]
+fn restrict_syscalls() -> Result<()> {
+    use seccompiler::{BpfProgram, SeccompAction, SeccompFilter};
+    use std::collections::BTreeMap;
+
+    let rules = DENIED_SYSCALLS.iter().map(|&nr| (nr, Vec::new())).collect::<BTreeMap<_, _>>();
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EPERM as u32),
+        std::env::consts::ARCH.try_into()?,
+    )?;
+    let program: BpfProgram = filter.try_into()?;
+    seccompiler::apply_filter_all_threads(&program)?;
+    info!("Sandbox: {} syscalls blocked", DENIED_SYSCALLS.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+
+    #[test]
+    fn test_paths_cover_config() {
+        let mut config = Config::default();
+        config.profiles.insert(
+            "test".to_string(),
+            Profile {
+                wallpaper_dirs: vec![PathBuf::from("/srv/walls")],
+                ..Default::default()
+            },
+        );
+        config.sandbox.allow_paths = vec![PathBuf::from("/mnt/extra")];
+        config.lock.wallpaper = Some(PathBuf::from("/srv/lock.png"));
+
+        let paths = paths(&config, Some("/etc/swww-manager/config.toml"));
+        assert!(paths.read.contains(&PathBuf::from("/srv/walls")));
+        assert!(paths.read.contains(&PathBuf::from("/srv/lock.png")));
+        assert!(paths.write.contains(&PathBuf::from("/mnt/extra")));
+        assert!(paths.write.contains(&PathBuf::from("/etc/swww-manager")));
+        assert!(!paths.write.contains(&PathBuf::from("/srv/walls")));
+    }
+    #[test]
+    fn test_paths_cover_handoff() {
+        let mut config = Config::default();
+        config.handoff.file = PathBuf::from("/run/greeter/wallpaper");
+        config.handoff.link = PathBuf::from("/srv/share/wallpaper-image");
+        assert!(!paths(&config, None).write.contains(&PathBuf::from("/run/greeter")));
+
+        config.handoff.enabled = true;
+        let paths = paths(&config, None);
+        assert!(paths.write.contains(&PathBuf::from("/run/greeter")));
+        assert!(paths.write.contains(&PathBuf::from("/srv/share")));
+    }
+}
@@ -1,57 +1,190 @@
-use crate::config::Config;
-use crate::monitor::MonitorManager;
-use crate::wallpaper::WallpaperManager;
+use crate::config::{Config, Profile, SwitchMode};
+use crate::monitor::{self, MonitorManager};
+use crate::wallpaper::{find_by_name, SwwwError, WallpaperManager};
 use crate::profile::ProfileManager;
-use crate::protocol::{Request, Response, StatusInfo};
+use crate::protocol::{Request, Response, StatusInfo, VersionInfo, WallpaperInfo};
+use crate::session::SessionEvent;
 use crate::notify;
+use crate::i18n::t;
+use crate::weather::WeatherProvider;
+use crate::cooldown::{SwitchCooldown, SwitchError};
+use crate::switcher::{self, Switcher};
+use crate::daemon::{self, ExeStamp};
+use crate::index::WallpaperIndex;
+use crate::scan::PathList;
+use crate::timeline::{self, EventKind};
 
 use futures::FutureExt;
 use anyhow::{Context, Result};
-use tokio::net::{UnixListener, UnixStream};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixListener;
+#[cfg(feature = "remote")]
+use tokio::net::TcpListener;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 // use tokio::process::Command as TokioCommand;
-use tokio::fs as TokioFs;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::time::Instant;
+use tokio::sync::{mpsc, watch, Notify, Semaphore};
+use tokio::task::{JoinHandle, JoinSet};
 use tracing::{info, error, warn, debug};
 use tokio::time::{Duration, MissedTickBehavior};
 
+/// Largest request accepted from a client; real requests are a few hundred bytes
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+/// A client must send its complete request within this time
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Client handlers allowed to run at once, further connections are rejected
+const MAX_CLIENTS: usize = 32;
+
+/// `WatchStatus` clients look again this often even without a change
+/// notification, for the compositor going away or monitors changing
+const STATUS_RECHECK: Duration = Duration::from_secs(30);
+
+/// A connection accepted on `listen_tcp` and the token it must present
+type RemoteClient = (tokio::net::TcpStream, String);
+
+#[derive(Debug, thiserror::Error)]
+enum RequestError {
+    #[error("Request exceeds {MAX_REQUEST_BYTES} bytes")]
+    TooLarge,
+    #[error("Invalid request: {0}")]
+    Invalid(serde_json::Error),
+    #[error("No complete request within {}s", READ_TIMEOUT.as_secs())]
+    Timeout,
+    #[error("Read error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
 #[derive(Clone)]
 pub struct Server {
     config: Config,
+    /// `--config` the server was started with, the default path when unset
+    config_path: Option<String>,
     monitor_manager: MonitorManager,
     wallpaper_manager: WallpaperManager,
     profile_manager: ProfileManager,
+    weather: WeatherProvider,
+    cooldown: SwitchCooldown,
     start_time: Instant,
+    started_at: i64,
+    exe: Option<ExeStamp>,
+    restart: Arc<Notify>,
+    /// Outputs seen by the last detection, shared by all client handlers
+    known_outputs: Arc<std::sync::Mutex<Vec<String>>>,
+    /// Wallpaper chosen for each workspace name in per-workspace mode
+    workspace_wallpapers: Arc<std::sync::Mutex<HashMap<String, String>>>,
+    /// Switch requested while no compositor was reachable, applied once
+    /// one appears
+    pending_switch: Arc<std::sync::Mutex<Option<PendingSwitch>>>,
+    /// Set while a queued switch is being applied
+    replaying: Arc<AtomicBool>,
+    /// Limits concurrently running client handlers (Unix and TCP together)
+    client_slots: Arc<Semaphore>,
+    /// Detected profile the user is currently being asked to switch to
+    asking: Arc<std::sync::Mutex<Option<String>>>,
+    /// Channel of the current profile that rotation is limited to
+    channel: Arc<std::sync::Mutex<Option<String>>>,
+    /// Set while wallpapers are being analyzed in the background
+    analyzing: Arc<AtomicBool>,
+    /// `Some` while the session is locked
+    locked: Arc<std::sync::Mutex<Option<Locked>>>,
+    /// Unix time in seconds of the next auto-switch tick, 0 until the loop
+    /// runs
+    next_switch: Arc<AtomicI64>,
+    /// Running cache maintenance task, replaced when `[maintenance]` changes
+    maintenance: Arc<std::sync::Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    /// Config as last changed by any clone, with a generation counter
+    latest: Arc<std::sync::Mutex<(u64, Config)>>,
+    /// Generation of `latest` this clone's config is from
+    generation: u64,
+    /// Bumped whenever what `GetStatus` reports may have changed, wakes
+    /// `WatchStatus` clients
+    changes: Arc<watch::Sender<u64>>,
+    /// Set once the main loop has stopped, ends `WatchStatus` clients
+    stopping: Arc<AtomicBool>,
+}
+
+/// A switch queued while offline, replayed with the preset and mode it
+/// was requested with
+struct PendingSwitch {
+    reason: String,
+    preset: Option<String>,
+    mode: Option<SwitchMode>,
+}
+
+struct Locked {
+    /// Wallpaper to bring back on unlock, when `[lock] wallpaper` replaced it
+    restore: Option<String>,
+    /// Current wallpaper in the history when the session was locked
+    current: Option<PathBuf>,
 }
 
 impl Server {
-    pub async fn new(config: Config) -> Result<Self> {
+    pub async fn new(config: Config, config_path: Option<&str>) -> Result<Self> {
         info!("Initializing server with profile: {}", config.current_profile);
         
+        let mut wallpaper_manager = WallpaperManager::new();
+        wallpaper_manager.configure(&config);
+        if let Some(last) = WallpaperIndex::load().most_recent() {
+            wallpaper_manager.set_last_wallpaper(last);
+        }
+        
         Ok(Self {
-            monitor_manager: MonitorManager::new(),
-            wallpaper_manager: WallpaperManager::new(),
+            monitor_manager: MonitorManager::new().with_detection(&config.monitor_detection),
+            wallpaper_manager,
             profile_manager: ProfileManager::new(config.clone()),
+            latest: Arc::new(std::sync::Mutex::new((0, config.clone()))),
+            generation: 0,
+            weather: WeatherProvider::new(),
+            cooldown: SwitchCooldown::new(),
             config,
+            config_path: config_path.map(String::from),
             start_time: Instant::now(),
+            started_at: chrono::Utc::now().timestamp_millis(),
+            exe: ExeStamp::current(),
+            restart: Arc::new(Notify::new()),
+            known_outputs: Arc::default(),
+            workspace_wallpapers: Arc::default(),
+            pending_switch: Arc::default(),
+            replaying: Arc::default(),
+            client_slots: Arc::new(Semaphore::new(MAX_CLIENTS)),
+            asking: Arc::default(),
+            channel: Arc::default(),
+            analyzing: Arc::default(),
+            locked: Arc::default(),
+            next_switch: Arc::default(),
+            maintenance: Arc::default(),
+            changes: Arc::new(watch::Sender::new(0)),
+            stopping: Arc::default(),
         })
     }
 
     pub async fn run(mut self) -> Result<()> {
+        let mut socket_activated = false;
         let listener = unsafe {
             let listen_pid = std::env::var("LISTEN_PID").ok();
             let listen_fds = std::env::var("LISTEN_FDS").ok();
             match (listen_pid.as_deref(), listen_fds.as_deref()) {
                 (Some(pid_str), Some(fds_str)) => match (pid_str.parse::<u32>().ok(), fds_str.parse::<i32>().ok()) {
+                    (Some(pid), Some(nfds)) if pid == std::process::id() && nfds > 0
+                        && !daemon::is_unix_listener(3) => {
+                        error!("fd 3 passed by systemd is not a listening Unix stream socket, ignoring it");
+                        None
+                    }
                     (Some(pid), Some(nfds)) if pid == std::process::id() && nfds > 0 => {
                         use std::os::unix::io::FromRawFd;
+                        if nfds > 1 {
+                            warn!("systemd passed {} sockets, only using the first", nfds);
+                        }
                         let raw_fd = 3;
                         let std_listener = std::os::unix::net::UnixListener::from_raw_fd(raw_fd);
                         let _ = std_listener.set_nonblocking(true).map_err(|e| error!("Failed to set nonblocking: {}", e));
                         match UnixListener::from_std(std_listener) {
                             Ok(l) => {
                                 info!("Using systemd socket activation (fd=3)");
+                                socket_activated = true;
                                 Some(l)
                             }
                             Err(e) => {
@@ -71,7 +204,8 @@ impl Server {
             None => {
                 let socket_path = Self::socket_path();
 
-                if socket_path.exists() {
+                if std::fs::symlink_metadata(&socket_path).is_ok() {
+                    daemon::check_socket_path(&socket_path)?;
                     // Try connect: success => someone owns it; failure => likely stale file
                     match std::os::unix::net::UnixStream::connect(&socket_path) {
                         Ok(_) => {
@@ -96,23 +230,21 @@ impl Server {
                         .with_context(|| format!("Failed to create socket directory: {:?}", parent))?;
                 }
 
-                let listener = UnixListener::bind(&socket_path)
-                    .with_context(|| format!("Failed to bind socket at {:?}", socket_path))?;
+                let std_listener = daemon::bind_private(&socket_path)?;
+                std_listener.set_nonblocking(true)?;
+                let listener = UnixListener::from_std(std_listener)
+                    .context("Failed to register socket with the runtime")?;
                 
                 info!("Socket server listening at {:?}", socket_path);
                 info!("Server ready to accept connections");
 
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    let perms = std::fs::Permissions::from_mode(0o600);
-                    std::fs::set_permissions(&socket_path, perms)?;
-                }
-
                 let socket_path = Self::socket_path();
                 let initial_runtime_dir = std::env::var_os("XDG_RUNTIME_DIR");
 
                 tokio::spawn(async move {
+                    // Without a compositor yet the server keeps running in
+                    // offline mode; it only exits once a session it has seen ends
+                    let mut seen_compositor = false;
                     loop {
                         tokio::time::sleep(Duration::from_secs(5)).await;
 
@@ -121,7 +253,7 @@ impl Server {
                             Some(d) => d,
                             None => {
                                 tracing::info!("XDG_RUNTIME_DIR gone, shutting down swww-manager.");
-                                let _ = TokioFs::remove_file(&socket_path).await;
+                                daemon::remove_socket(&socket_path);
                                 std::process::exit(0);
                             }
                         };
@@ -154,12 +286,16 @@ impl Server {
                             .map(|d| !Path::new(d).exists())
                             .unwrap_or(false);
 
-                        if (!hypr_running && !sway_running) || initial_gone {
+                        let compositor_running = hypr_running || sway_running;
+                        if compositor_running && !seen_compositor {
+                            debug!("Compositor detected");
+                            seen_compositor = true;
+                        }
+
+                        if (seen_compositor && !compositor_running) || initial_gone {
                             tracing::info!("No compositor socket detected (hyprland/sway) or runtime dir changed. Shutting down swww-manager.");
 
-                            if let Err(e) = TokioFs::remove_file(&socket_path).await {
-                                tracing::debug!("Failed to remove socket file: {}", e);
-                            }
+                            daemon::remove_socket(&socket_path);
 
                             std::process::exit(0);
                         }
@@ -170,31 +306,101 @@ impl Server {
             }
         };
         {
-            use std::sync::Arc;
-            use tokio::sync::Mutex as TokioMutex;
-            let debounce_delay = std::time::Duration::from_millis(900);
-            tokio::spawn(async move {
-                let scheduled_task: Arc<TokioMutex<Option<tokio::task::JoinHandle<()>>>> = Arc::new(TokioMutex::new(None));
-                let scheduled_task_cloned = scheduled_task.clone();
-                let _ = crate::hyprland_event::monitor_events(move |event| {
-                    let scheduled_task = scheduled_task_cloned.clone();
+            let debouncer = self.monitor_manager.debouncer();
+            let server = self.clone();
+            let focused: Arc<std::sync::Mutex<Option<String>>> = Arc::default();
+            if let Ok(ipc) = crate::hyprland_ipc::HyprlandIPC::new()
+                && let Ok(workspace) = ipc.get_active_workspace().await {
+                    *focused.lock().unwrap() = Some(workspace.monitor);
+                }
+            let handler = move |event| {
+                    let debouncer = debouncer.clone();
+                    let mut server = server.clone();
+                    let focused = focused.clone();
                     async move {
+                        server.sync_config();
                         match event {
                             crate::hyprland_event::HyprlandEvent::MonitorAdded { .. } |
                             crate::hyprland_event::HyprlandEvent::MonitorRemoved { .. } => {
-                                if let Some(handle) = scheduled_task.lock().await.take() { handle.abort(); }
-                                let handle = tokio::spawn(async move {
-                                    tokio::time::sleep(debounce_delay).await;
+                                debouncer.schedule(async move {
                                     if let Ok(mut client) = crate::client::Client::connect().await {
                                         let _ = client.detect_and_switch_profile().await;
                                     }
-                                });
-                                *scheduled_task.lock().await = Some(handle);
+                                }).await;
+                            }
+                            crate::hyprland_event::HyprlandEvent::FocusedMon { monitor, .. } => {
+                                *focused.lock().unwrap() = Some(monitor);
                             }
+                            crate::hyprland_event::HyprlandEvent::Workspace { name, .. }
+                                if server.config.per_workspace.enabled => {
+                                    let monitor = focused.lock().unwrap().clone();
+                                    if let Some(monitor) = monitor
+                                        && let Err(e) = server.show_workspace(&name, &monitor).await {
+                                            warn!("Failed to set wallpaper for workspace {}: {}", name, e);
+                                        }
+                                }
+                            crate::hyprland_event::HyprlandEvent::MoveWorkspace { name, monitor, .. }
+                                if server.config.per_workspace.enabled && server.config.per_workspace.follow_moves => {
+                                    if let Err(e) = server.show_workspace(&name, &monitor).await {
+                                        warn!("Failed to move wallpaper with workspace {}: {}", name, e);
+                                    }
+                                }
                             _ => {}
                         }
                     }.boxed()
-                }).await;
+                };
+            tokio::spawn(async move {
+                // Keep retrying so events start flowing once Hyprland appears
+                loop {
+                    use crate::hyprland_event::EventKind as Kind;
+                    let subscription = crate::hyprland_event::Subscription::only(&[
+                        Kind::MonitorAdded,
+                        Kind::MonitorRemoved,
+                        Kind::FocusedMon,
+                        Kind::Workspace,
+                        Kind::MoveWorkspace,
+                    ]).coalesced();
+                    if let Err(e) = crate::hyprland_event::monitor_events(subscription, handler.clone()).await {
+                        debug!("Hyprland events not available: {}", e);
+                    }
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            });
+        }
+
+        if self.config.lock.enabled {
+            let server = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    let server = server.clone();
+                    let handler = move |event| {
+                        let mut server = server.clone();
+                        async move {
+                            server.sync_config();
+                            server.on_session_event(event).await;
+                            server.status_changed();
+                        }.boxed()
+                    };
+                    if let Err(e) = crate::session::watch(handler).await {
+                        debug!("Session lock events not available: {}", e);
+                    }
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                }
+            });
+        }
+
+        self.start_maintenance();
+
+        let (remote_tx, mut remote_rx) = mpsc::channel(MAX_CLIENTS);
+        let tcp_accept = self.listen_tcp(remote_tx).await?;
+
+        if self.config.startup.restore_wallpaper {
+            let mut s = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = s.restore_wallpaper().await {
+                    warn!("Failed to restore wallpaper on startup: {}", e);
+                }
+                s.status_changed();
             });
         }
 
@@ -210,6 +416,12 @@ impl Server {
         }
 
         let mut last_config_mtime: Option<std::time::SystemTime> = None;
+        let mut binary_update_logged = false;
+        let mut clients = JoinSet::new();
+        let mut restarting = false;
+        let restart = self.restart.clone();
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .context("Failed to install SIGTERM handler")?;
 
         loop {
             tokio::select! {
@@ -217,10 +429,16 @@ impl Server {
                     match result {
                         Ok((stream, addr)) => {
                             debug!("Client connected: {:?}", addr);
+                            let Ok(permit) = self.client_slots.clone().try_acquire_owned() else {
+                                warn!("Too many clients, rejecting connection");
+                                tokio::spawn(reject(stream, "Server busy, try again"));
+                                continue;
+                            };
                             let mut server = self.clone();
                             
-                            tokio::spawn(async move {
-                                if let Err(e) = server.handle_client(stream).await {
+                            clients.spawn(async move {
+                                let _permit = permit;
+                                if let Err(e) = server.handle_client(stream, None).await {
                                     error!("Client handler error: {}", e);
                                 }
                             });
@@ -230,23 +448,143 @@ impl Server {
                         }
                     }
                 }
+                Some((stream, token)) = remote_rx.recv() => {
+                    let Ok(permit) = self.client_slots.clone().try_acquire_owned() else {
+                        warn!("Too many clients, rejecting remote connection");
+                        tokio::spawn(reject(stream, "Server busy, try again"));
+                        continue;
+                    };
+                    let mut server = self.clone();
+                    clients.spawn(async move {
+                        let _permit = permit;
+                        if let Err(e) = server.handle_client(stream, Some(&token)).await {
+                            error!("Remote client handler error: {}", e);
+                        }
+                    });
+                }
                 _ = tokio::time::sleep(std::time::Duration::from_secs(1)) => {
+                    while clients.try_join_next().is_some() {}
+                    self.sync_config();
                     self.check_and_reload_config(&mut last_config_mtime).await;
+                    self.apply_pending_switch();
+                    
+                    if !binary_update_logged && self.binary_updated() {
+                        info!("Executable was replaced on disk; run 'swww-manager restart' to load the new version");
+                        binary_update_logged = true;
+                    }
                 }
                 , _ = tokio::signal::ctrl_c() => {
                     info!("Received shutdown signal");
                     break;
                 }
+                _ = sigterm.recv() => {
+                    info!("Received SIGTERM");
+                    break;
+                }
+                _ = restart.notified() => {
+                    restarting = true;
+                    break;
+                }
             }
         }
 
-        info!("Shutting down server...");
-        
-        Ok(())
+        self.stopping.store(true, Ordering::SeqCst);
+        self.status_changed();
+
+        if !restarting {
+            info!("Shutting down server...");
+            return Ok(());
+        }
+
+        // Stop accepting and let running requests (including the one that
+        // asked for the restart) write their responses. Wallpaper history is
+        // already persisted in the index as each switch is applied.
+        drop(listener);
+        if let Some(task) = tcp_accept {
+            task.abort();
+        }
+        drop(remote_rx);
+        if !clients.is_empty() {
+            info!("Waiting for {} in-flight request(s)", clients.len());
+            let drained = tokio::time::timeout(Duration::from_secs(10), async {
+                while clients.join_next().await.is_some() {}
+            });
+            if drained.await.is_err() {
+                warn!("Timed out waiting for in-flight requests, restarting anyway");
+            }
+        }
+
+        if !socket_activated {
+            daemon::remove_socket(&Self::socket_path());
+        }
+
+        // Under systemd hand the restart back to the service manager, which
+        // keeps the activation socket open in the meantime
+        if std::env::var_os("INVOCATION_ID").is_some() {
+            info!("Exiting with restart code {}", daemon::RESTART_EXIT_CODE);
+            std::process::exit(daemon::RESTART_EXIT_CODE);
+        }
+
+        match &self.exe {
+            Some(exe) => Err(daemon::reexec(&exe.path)),
+            None => anyhow::bail!("Cannot restart: executable path unknown"),
+        }
+    }
+
+    /// (Re)start cache maintenance with the current `[maintenance]` settings
+    fn start_maintenance(&self) {
+        let mut task = self.maintenance.lock().unwrap();
+        if let Some(handle) = task.take() {
+            handle.abort();
+        }
+        if self.config.maintenance.enabled {
+            *task = Some(tokio::spawn(crate::maintenance::run(self.config.maintenance.clone())));
+        }
+    }
+
+    /// Take over a reloaded or edited config and hand it to every clone
+    fn apply_config(&mut self, new_config: Config) {
+        let maintenance_changed = new_config.maintenance != self.config.maintenance;
+        self.use_config(new_config);
+        self.publish_config();
+        if maintenance_changed {
+            self.start_maintenance();
+        }
+    }
+
+    fn use_config(&mut self, new_config: Config) {
+        self.monitor_manager.set_detection(&new_config.monitor_detection);
+        self.wallpaper_manager.configure(&new_config);
+        self.config = new_config.clone();
+        self.profile_manager.update_config(new_config);
+    }
+
+    /// Share this clone's config after changing it, so the other clones
+    /// (client handlers, event handlers, the auto-switch loop) pick it up
+    fn publish_config(&mut self) {
+        let mut latest = self.latest.lock().unwrap();
+        latest.0 += 1;
+        latest.1 = self.config.clone();
+        self.generation = latest.0;
+        drop(latest);
+        self.status_changed();
+    }
+
+    /// Catch up with config changes other clones made since this one was
+    /// cloned or last synced
+    fn sync_config(&mut self) {
+        let latest = self.latest.lock().unwrap();
+        if latest.0 == self.generation {
+            return;
+        }
+        let (generation, config) = (latest.0, latest.1.clone());
+        drop(latest);
+        self.generation = generation;
+        self.use_config(config);
     }
 
     async fn check_and_reload_config(&mut self, last_config_mtime: &mut Option<std::time::SystemTime>) {
-        let Some(path_str) = crate::config::Config::default_path() else { return };
+        let Some(path_str) = self.config_path.clone().or_else(Config::default_path) else { return };
         let path = std::path::PathBuf::from(path_str);
         let Ok(meta) = std::fs::metadata(&path) else { return };
         let Ok(mtime) = meta.modified() else { return };
@@ -255,31 +593,37 @@ impl Server {
             return;
         }
 
-        let new_config = match Config::load(None) {
+        let new_config = match Config::load(self.config_path.as_deref()) {
             Ok(c) => c,
             Err(e) => { warn!("Failed to reload updated config: {}", e); return },
         };
 
         info!("Config changed on disk, reloading");
-        self.config = new_config.clone();
-        self.profile_manager.update_config(new_config);
+        timeline::record(EventKind::Config, "Config changed on disk, reloaded");
+        self.apply_config(new_config);
 
         if let Ok(profile) = self.profile_manager.current_profile()
-            && let Err(e) = self.wallpaper_manager.refresh_cache(profile) {
+            && let Err(e) = self.wallpaper_manager.rescan(profile).await {
                 warn!("Failed to refresh wallpaper cache: {}", e);
             }
 
         match self.monitor_manager.get_stable_monitors().await {
             Ok(monitors) => {
-                info!("Running detect after config reload: {:?}", monitors);
-                match self.profile_manager.detect_profile(&monitors) {
+                info!("Running detect after config reload: {:?}", monitor::names(&monitors));
+                let detected = self.profile_manager.detect_profile(&monitors)
+                    .map(|p| p.or_else(|| self.config.monitor_detection.fallback_profile.clone()));
+                match detected {
+                    Ok(Some(profile)) if profile != self.config.current_profile
+                        && self.config.monitor_detection.ask_before_switch => {
+                        self.ask_to_switch(&profile);
+                    }
                     Ok(Some(profile)) if profile != self.config.current_profile => {
                         if let Err(e) = self.switch_profile(&profile).await {
                             warn!("Failed to switch profile after config reload: {}", e);
                         }
                     }
                     Ok(_) => {
-                        if let Err(e) = self.switch_wallpaper().await {
+                        if let Err(e) = self.switch_wallpaper("config reload").await {
                             warn!("Failed to refresh wallpaper after config reload: {}", e);
                         }
                     }
@@ -292,27 +636,99 @@ impl Server {
         *last_config_mtime = Some(mtime);
     }
 
-    async fn handle_client(&mut self, mut stream: UnixStream) -> Result<()> {
-        let mut buffer = vec![0u8; 8192];
-        
-        let n = match stream.read(&mut buffer).await {
-            Ok(0) => {
+    /// Accept remote clients on `listen_tcp` when a token is configured,
+    /// handing each one with its token to `clients` so the main loop serves
+    /// them alongside local ones. Returns the accept task.
+    #[cfg(feature = "remote")]
+    async fn listen_tcp(&self, clients: mpsc::Sender<RemoteClient>) -> Result<Option<JoinHandle<()>>> {
+        let remote_token = self.config.remote_token.clone().filter(|t| !t.is_empty());
+        if self.config.listen_tcp.is_some() && remote_token.is_none() {
+            warn!("listen_tcp is set but remote_token is missing, not listening on TCP");
+        }
+        let (Some(addr), Some(token)) = (self.config.listen_tcp.clone(), remote_token) else {
+            return Ok(None);
+        };
+        let tcp = TcpListener::bind(&addr).await
+            .with_context(|| format!("Failed to bind TCP listener at {}", addr))?;
+        info!("Listening for remote clients on {}", addr);
+        Ok(Some(tokio::spawn(async move {
+            loop {
+                match tcp.accept().await {
+                    Ok((stream, peer)) => {
+                        debug!("Remote client connected: {}", peer);
+                        if clients.send((stream, token.clone())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => error!("TCP accept error: {}", e),
+                }
+            }
+        })))
+    }
+
+    #[cfg(not(feature = "remote"))]
+    async fn listen_tcp(&self, _clients: mpsc::Sender<RemoteClient>) -> Result<Option<JoinHandle<()>>> {
+        if self.config.listen_tcp.is_some() {
+            warn!("listen_tcp is set but this build has no remote support (feature \"remote\")");
+        }
+        Ok(None)
+    }
+
+    /// Serve one request read from `stream`. With `token` set (TCP clients)
+    /// the request must be wrapped in `Request::Auth` carrying that token.
+    async fn handle_client<S>(&mut self, mut stream: S, token: Option<&str>) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.sync_config();
+        let request = match read_request(&mut stream).await {
+            Ok(Some(request)) => request,
+            Ok(None) => {
                 debug!("Client disconnected (EOF)");
                 return Ok(());
             }
-            Ok(n) => n,
-            Err(e) => {
+            Err(RequestError::Io(e)) => {
                 error!("Read error: {}", e);
                 return Err(e.into());
             }
+            Err(e) => {
+                warn!("Rejected request: {}", e);
+                return reject(stream, &e.to_string()).await;
+            }
+        };
+        
+        let request = match (request, token) {
+            (Request::Auth { token: given, request }, Some(expected))
+                if constant_time_eq(given.as_bytes(), expected.as_bytes()) => *request,
+            (Request::Auth { request, .. }, None) => *request,
+            (_, Some(_)) => {
+                warn!("Rejected unauthenticated TCP request");
+                return reject(stream, "Unauthorized").await;
+            }
+            (request, None) => request,
         };
-
-        let request: Request = serde_json::from_slice(&buffer[..n])
-            .context("Failed to parse request JSON")?;
         
         info!("Processing request: {:?}", request);
+
+        if let Request::WatchStatus = request {
+            return self.watch_status(stream).await;
+        }
+        let changes_status = changes_status(&request);
        
-        let response = self.process_request(request).await;
+        let response = match request {
+            Request::Timed { request } => {
+                let start = Instant::now();
+                let response = self.process_request(*request).await;
+                Response::Timed {
+                    elapsed_ms: start.elapsed().as_millis() as u64,
+                    response: Box::new(response),
+                }
+            }
+            request => self.process_request(request).await,
+        };
+        if changes_status {
+            self.status_changed();
+        }
         
         debug!("Sending response: {:?}", response);
         
@@ -328,9 +744,89 @@ impl Server {
         Ok(())
     }
 
+    fn config_file(&self) -> Option<&Path> {
+        self.config_path.as_deref().map(Path::new)
+    }
+
+    /// The config file as it is now, which may hold edits not reloaded yet
+    fn latest_config(&self) -> Config {
+        Config::load(self.config_path.as_deref()).unwrap_or_else(|e| {
+            warn!("Using in-memory config: {}", e);
+            self.config.clone()
+        })
+    }
+
+    fn binary_updated(&self) -> bool {
+        self.exe.as_ref().map(|e| e.replaced()).unwrap_or(false)
+    }
+
+    async fn status(&self) -> StatusInfo {
+        let monitors = self.monitor_manager.get_monitors().await.unwrap_or_default();
+        
+        StatusInfo {
+            current_profile: self.config.current_profile.clone(),
+            current_wallpaper: self.shown_wallpaper().map(|p| p.to_string_lossy().to_string()),
+            auto_switch_enabled: self.config.auto_switch.enabled,
+            auto_switch_interval: Some(self.config.auto_switch.interval),
+            monitors,
+            uptime_secs: self.start_time.elapsed().as_secs(),
+            pid: Some(std::process::id()),
+            version: Some(VersionInfo::current()),
+            binary_updated: self.binary_updated(),
+            started_at: Some(self.started_at),
+            offline: !crate::startup::compositor_available(),
+            channel: self.channel.lock().unwrap().clone(),
+            next_switch_at: match self.next_switch.load(Ordering::SeqCst) {
+                next if next > 0 && self.config.auto_switch.enabled => Some(next),
+                _ => None,
+            },
+            output_wallpapers: self.wallpaper_manager.output_wallpapers()
+                .into_iter()
+                .map(|(output, path)| (output, path.to_string_lossy().to_string()))
+                .collect(),
+        }
+    }
+
+    /// Let `WatchStatus` clients know the status may have changed
+    fn status_changed(&self) {
+        self.changes.send_modify(|n| *n = n.wrapping_add(1));
+    }
+
+    /// Write the status to `stream` as one `Status` response per line, now
+    /// and again whenever it changes, until the client goes away or the
+    /// server stops. The uptime alone doesn't count as a change.
+    async fn watch_status<S: AsyncWrite + Unpin>(&mut self, mut stream: S) -> Result<()> {
+        let mut changes = self.changes.subscribe();
+        let mut sent = None;
+        loop {
+            changes.mark_unchanged();
+            if self.stopping.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            self.sync_config();
+            let status = self.status().await;
+            let mut compared = serde_json::to_value(&status)?;
+            if let Some(fields) = compared.as_object_mut() {
+                fields.remove("uptime_secs");
+            }
+            if sent.as_ref() != Some(&compared) {
+                let mut line = serde_json::to_vec(&Response::Status { status })?;
+                line.push(b'\n');
+                if stream.write_all(&line).await.and(stream.flush().await).is_err() {
+                    debug!("Status watcher disconnected");
+                    return Ok(());
+                }
+                sent = Some(compared);
+            }
+            // `changes` can't close, this clone holds its sender
+            let _ = tokio::time::timeout(STATUS_RECHECK, changes.changed()).await;
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
     async fn process_request(&mut self, request: Request) -> Response {
         match request {
-            Request::Switch { profile } => {
+            Request::Switch { profile, preset, mode } => {
                 // Switch profile first if specified
                 if let Some(prof) = profile
                     && let Err(e) = self.switch_profile(&prof).await {
@@ -340,24 +836,41 @@ impl Server {
                     }
                 
                 // Then switch wallpaper
-                match self.switch_wallpaper().await {
+                match self.switch_wallpaper_with("manual", preset.as_deref(), mode).await {
                     Ok(path) => {
                         let filename = std::path::Path::new(&path)
                             .file_name()
                             .and_then(|n| n.to_str())
                             .unwrap_or(&path);
                         
-                        notify::send_success(&format!("Wallpaper: {}", filename)).await.ok();
+                        notify::send_success(&t!("notify-wallpaper", name = filename)).await.ok();
                         
                         Response::Success { 
                             message: format!("Switched to wallpaper: {}", filename) 
                         }
                     }
+                    Err(e) if matches!(e.downcast_ref(), Some(SwitchError::Queued)) => {
+                        Response::Success {
+                            message: "Compositor not available, switch queued until it appears".to_string()
+                        }
+                    }
+                    Err(e) if e.is::<SwitchError>() => {
+                        Response::Success {
+                            message: "Switch coalesced into a newer request".to_string()
+                        }
+                    }
+                    Err(e) if matches!(SwwwError::find(&e), Some(SwwwError::TimedOut(_))) => {
+                        warn!("Wallpaper switch is slow: {:#}", e);
+                        timeline::record(EventKind::Error, format!("{:#}", e));
+                        notify::send_warning(&t!("notify-swww-slow")).await.ok();
+                        Response::Error { message: format!("{:#}", e) }
+                    }
                     Err(e) => {
-                        error!("Failed to switch wallpaper: {}", e);
-                        notify::send_error(&e.to_string()).await.ok();
+                        error!("Failed to switch wallpaper: {:#}", e);
+                        timeline::record(EventKind::Error, format!("Failed to switch wallpaper: {:#}", e));
+                        notify::send_error(&failure_notice(&e)).await.ok();
                         Response::Error { 
-                            message: format!("Failed to switch wallpaper: {}", e)
+                            message: format!("Failed to switch wallpaper: {:#}", e)
                         }
                     }
                 }
@@ -365,7 +878,7 @@ impl Server {
             
             Request::SwitchProfile { name } => {
                 match self.switch_profile(&name).await {
-                    Ok(_) => {
+                    Ok(name) => {
                         Response::Success { 
                             message: format!("Switched to profile: {}", name) 
                         }
@@ -390,13 +903,41 @@ impl Server {
                     }
                 };
                 
-                info!("Detecting profile for monitors: {:?}", monitors);
+                info!("Detecting profile for monitors: {:?}", monitor::names(&monitors));
+                
+                let previous = std::mem::replace(
+                    &mut *self.known_outputs.lock().unwrap(),
+                    monitor::names(&monitors),
+                );
+                
+                let detected = match self.add_auto_profile(&monitors).await {
+                    Ok(Some(profile)) => Ok(Some(profile)),
+                    Ok(None) => self.profile_manager.detect_profile(&monitors),
+                    Err(e) => {
+                        warn!("{:#}", e);
+                        self.profile_manager.detect_profile(&monitors)
+                    }
+                };
+                if let Ok(profile) = &detected {
+                    timeline::record(EventKind::Hotplug, format!(
+                        "Monitors {} matched {}",
+                        monitor::names(&monitors).join(", "),
+                        profile.as_deref().unwrap_or("no profile")
+                    ));
+                }
                 
-                match self.profile_manager.detect_profile(&monitors) {
+                match detected {
                     Ok(Some(profile)) => {
                         if profile != self.config.current_profile {
                             info!("Detected profile: {} (current: {})", profile, self.config.current_profile);
                             
+                            if self.config.monitor_detection.ask_before_switch {
+                                self.ask_to_switch(&profile);
+                                return Response::Success {
+                                    message: format!("Asking before switching to profile: {}", profile)
+                                };
+                            }
+                            
                             if let Err(e) = self.switch_profile(&profile).await {
                                 return Response::Error { 
                                     message: format!("Failed to switch to detected profile: {}", e)
@@ -406,8 +947,28 @@ impl Server {
                             Response::Success { 
                                 message: format!("Auto-switched to profile: {}", profile) 
                             }
+                        } else if self.config.monitor_detection.minimal_disruption && !previous.is_empty() {
+                            let added: Vec<String> = monitor::names(&monitors)
+                                .into_iter()
+                                .filter(|n| !previous.contains(n))
+                                .collect();
+                            
+                            match self.fill_outputs(&added).await {
+                                Ok(()) if added.is_empty() => Response::Success {
+                                    message: format!("Already using optimal profile: {}", profile)
+                                },
+                                Ok(()) => Response::Success {
+                                    message: format!("Applied wallpaper to new output(s): {}", added.join(", "))
+                                },
+                                Err(e) => {
+                                    error!("Failed to set wallpaper on new outputs: {}", e);
+                                    Response::Error {
+                                        message: format!("Failed to set wallpaper on new outputs: {}", e),
+                                    }
+                                }
+                            }
                         } else {
-                            match self.switch_wallpaper().await {
+                            match self.switch_wallpaper("monitor detection").await {
                                 Ok(path) => {
                                     let filename = std::path::Path::new(&path)
                                         .file_name()
@@ -430,9 +991,30 @@ impl Server {
                         }
                     }
                     Ok(None) => {
-                        warn!("No matching profile found for monitors: {:?}", monitors);
-                        Response::Success { 
-                            message: "No matching profile found, using current".to_string()
+                        warn!("No matching profile found for monitors: {:?}", monitor::names(&monitors));
+                        
+                        let Some(fallback) = self.config.monitor_detection.fallback_profile.clone() else {
+                            return Response::Success { 
+                                message: "No matching profile found, using current".to_string()
+                            };
+                        };
+                        
+                        if fallback == self.config.current_profile {
+                            return Response::Success {
+                                message: format!("No matching profile found, already on fallback profile: {}", fallback)
+                            };
+                        }
+                        
+                        info!("Falling back to profile: {}", fallback);
+                        if let Err(e) = self.switch_profile(&fallback).await {
+                            return Response::Error {
+                                message: format!("Failed to switch to fallback profile: {}", e)
+                            };
+                        }
+                        notify::send(&t!("notify-no-match"), &t!("notify-fell-back", profile = fallback)).await.ok();
+                        
+                        Response::Success {
+                            message: format!("No matching profile found, fell back to: {}", fallback)
                         }
                     }
                     Err(e) => {
@@ -444,36 +1026,45 @@ impl Server {
                 }
             }
             
+            Request::ExplainDetection => {
+                match self.monitor_manager.get_stable_monitors().await {
+                    Ok(monitors) => Response::DetectionReport {
+                        report: self.profile_manager.explain(&monitors),
+                    },
+                    Err(e) => {
+                        error!("Failed to get monitors: {}", e);
+                        Response::Error {
+                            message: format!("Failed to get monitors: {}", e)
+                        }
+                    }
+                }
+            }
+            
+            Request::GetVersion => {
+                Response::Version { version: VersionInfo::current() }
+            }
+            
             Request::ListProfiles => {
-                let profiles = self.profile_manager.get_profile_list();
+                let profiles = self.profile_manager.get_profile_list(self.wallpaper_manager.scans()).await;
                 Response::ProfileList { profiles }
             }
             
-            Request::GetStatus => {
-                let monitors = self.monitor_manager.get_monitors().await.unwrap_or_default();
-                
-                let status = StatusInfo {
-                    current_profile: self.config.current_profile.clone(),
-                    current_wallpaper: self.wallpaper_manager.last_wallpaper()
-                        .map(|p| p.to_string_lossy().to_string()),
-                    auto_switch_enabled: self.config.auto_switch.enabled,
-                    auto_switch_interval: Some(self.config.auto_switch.interval),
-                    monitors,
-                    uptime_secs: self.start_time.elapsed().as_secs(),
-                };
-                
-                Response::Status { status }
-            }
+            Request::GetStatus => Response::Status { status: self.status().await },
+
+            Request::WatchStatus => Response::Error {
+                message: "WatchStatus cannot be wrapped in another request".to_string()
+            },
             
             Request::SetAutoSwitch { enabled } => {
                 self.config.auto_switch.enabled = enabled;
                 
-                if let Err(e) = self.config.save(None) {
+                if let Err(e) = self.config.save_key(self.config_file(), "auto_switch.enabled") {
                     error!("Failed to save config: {}", e);
                     return Response::Error { 
                         message: format!("Failed to save config: {}", e)
                     };
                 }
+                self.publish_config();
                 
                 let status = if enabled { "enabled" } else { "disabled" };
                 info!("Auto-switch {}", status);
@@ -486,12 +1077,13 @@ impl Server {
             Request::SetAutoSwitchInterval { interval } => {
                 self.config.auto_switch.interval = interval;
                 
-                if let Err(e) = self.config.save(None) {
+                if let Err(e) = self.config.save_key(self.config_file(), "auto_switch.interval") {
                     error!("Failed to save config: {}", e);
                     return Response::Error { 
                         message: format!("Failed to save config: {}", e)
                     };
                 }
+                self.publish_config();
                 
                 info!("Auto-switch interval set to {}s", interval);
                 
@@ -502,15 +1094,15 @@ impl Server {
             }
             
             Request::ReloadConfig => {
-                match Config::load(None) {
+                match Config::load(self.config_path.as_deref()) {
                     Ok(new_config) => {
                         info!("Reloading configuration");
-                        self.config = new_config.clone();
-                        self.profile_manager.update_config(new_config);
+                        timeline::record(EventKind::Config, "Config reloaded");
+                        self.apply_config(new_config);
                         
                         // Refresh wallpaper cache
                         if let Ok(profile) = self.profile_manager.current_profile()
-                            && let Err(e) = self.wallpaper_manager.refresh_cache(profile) {
+                            && let Err(e) = self.wallpaper_manager.rescan(profile).await {
                                 warn!("Failed to refresh wallpaper cache: {}", e);
                             }
                         
@@ -527,6 +1119,200 @@ impl Server {
                 }
             }
             
+            Request::GetConfig { key } => {
+                match self.latest_config().get_key(key.as_deref().unwrap_or("")) {
+                    Ok(value) => Response::Config { value },
+                    Err(e) => Response::Error { message: e.to_string() },
+                }
+            }
+            
+            Request::SetConfig { key, value } => {
+                let mut new_config = self.latest_config();
+                if let Err(e) = new_config.set_key(&key, &value) {
+                    return Response::Error { message: format!("{:#}", e) };
+                }
+                if let Err(e) = new_config.save_key(self.config_file(), &key) {
+                    error!("Failed to save config: {}", e);
+                    return Response::Error {
+                        message: format!("Failed to save config: {}", e)
+                    };
+                }
+                
+                info!("Config key {} set to {}", key, value);
+                timeline::record(EventKind::Config, format!("Set {} = {}", key, value));
+                self.apply_config(new_config);
+                
+                Response::Success {
+                    message: format!("{} = {}", key, value)
+                }
+            }
+            
+            Request::SetLogLevel { level } => {
+                match crate::logging::set_level(&level) {
+                    Ok(()) => {
+                        warn!("Log level set to {}", level);
+                        Response::Success {
+                            message: format!("Log level set to {}", level)
+                        }
+                    }
+                    Err(e) => Response::Error { message: e.to_string() },
+                }
+            }
+            
+            Request::SetChannel { name } => {
+                if let Some(name) = &name {
+                    let mut channels: Vec<String> = self.profile_manager.current_profile()
+                        .map(|p| p.channels.keys().cloned().collect())
+                        .unwrap_or_default();
+                    if !channels.contains(name) {
+                        channels.sort();
+                        return Response::Error {
+                            message: format!(
+                                "Profile '{}' has no channel '{}' (available: {})",
+                                self.config.current_profile,
+                                name,
+                                if channels.is_empty() { "none".to_string() } else { channels.join(", ") }
+                            )
+                        };
+                    }
+                }
+                
+                *self.channel.lock().unwrap() = name.clone();
+                let message = match &name {
+                    Some(name) => format!("Channel set to: {}", name),
+                    None => "Channel cleared".to_string(),
+                };
+                info!("{}", message);
+                timeline::record(EventKind::Profile, message.clone());
+                
+                match self.switch_wallpaper("channel change").await {
+                    Err(e) if !matches!(e.downcast_ref(), Some(SwitchError::Queued)) => Response::Error {
+                        message: format!("{}, but switching wallpaper failed: {}", message, e)
+                    },
+                    _ => Response::Success { message },
+                }
+            }
+            
+            Request::SetWallpaper { path } => self.set_wallpaper_reply(&path).await,
+            
+            Request::SetWallpaperByIndex { index } => match self.wallpaper_at(index).await {
+                Ok(path) => self.set_wallpaper_reply(&path).await,
+                Err(e) => Response::Error { message: format!("Failed to set wallpaper: {}", e) },
+            },
+            
+            Request::SetWallpaperByMatch { pattern } => match self.wallpaper_matching(&pattern).await {
+                Ok(path) => self.set_wallpaper_reply(&path).await,
+                Err(e) => Response::Error { message: format!("Failed to set wallpaper: {}", e) },
+            },
+            
+            Request::PreviousWallpaper => match self.show_previous().await {
+                Ok(path) => {
+                    timeline::record(EventKind::Wallpaper, format!("{} (previous)", path));
+                    let filename = Path::new(&path).file_name().and_then(|n| n.to_str()).unwrap_or(&path);
+                    Response::Success { message: format!("Back to wallpaper: {}", filename) }
+                }
+                Err(e) => Response::Error { message: format!("Failed to go back: {:#}", e) },
+            },
+            
+            Request::Gc { dry_run } => match crate::gc::collect(dry_run) {
+                Ok(mut report) => {
+                    report.history_entries = self.wallpaper_manager.prune_history(dry_run);
+                    Response::Collected { report }
+                }
+                Err(e) => Response::Error { message: format!("Failed to clean up: {:#}", e) },
+            },
+            
+            Request::ListWallpapers => match self.list_wallpapers().await {
+                Ok((profile, wallpapers)) => Response::Wallpapers { profile, wallpapers },
+                Err(e) => Response::Error { message: format!("Failed to list wallpapers: {}", e) },
+            },
+            
+            Request::MarkWallpaper { path, favorite, blocked } => {
+                let marked = WallpaperIndex::update(|index| {
+                    if let Some(favorite) = favorite {
+                        index.set_favorite(Path::new(&path), favorite);
+                    }
+                    if let Some(blocked) = blocked {
+                        index.set_blocked(Path::new(&path), blocked);
+                    }
+                });
+                match marked {
+                    Ok(()) => {
+                        self.wallpaper_manager.mark(Path::new(&path), favorite, blocked);
+                        info!("Marked {}: favorite {:?}, blocked {:?}", path, favorite, blocked);
+                        Response::Success { message: format!("Updated {}", path) }
+                    }
+                    Err(e) => Response::Error { message: format!("Failed to save wallpaper index: {}", e) },
+                }
+            }
+            
+            Request::TestTransition { preset, transition, duration, fps, bezier } => {
+                let mut profile = match self.active_profile() {
+                    Ok(profile) => profile,
+                    Err(e) => return Response::Error { message: e.to_string() },
+                };
+                if let Some(preset) = &preset
+                    && let Err(e) = self.config.apply_transition_preset(&mut profile, preset) {
+                        return Response::Error { message: e.to_string() };
+                    }
+                if let Some(transition) = transition {
+                    profile.transition = transition;
+                }
+                if let Some(duration) = duration {
+                    profile.transition_duration = duration;
+                }
+                if fps.is_some() {
+                    profile.transition_fps = fps;
+                }
+                if bezier.is_some() {
+                    profile.transition_bezier = bezier;
+                }
+                
+                match self.reapply_wallpaper(&profile).await {
+                    Ok(path) => {
+                        let filename = std::path::Path::new(&path)
+                            .file_name()
+                            .and_then(|n| n.to_str())
+                            .unwrap_or(&path);
+                        let mut settings = format!("{}, {}s", profile.transition, profile.transition_duration);
+                        if let Some(fps) = profile.transition_fps {
+                            settings.push_str(&format!(", {} fps", fps));
+                        }
+                        if let Some(bezier) = &profile.transition_bezier {
+                            settings.push_str(&format!(", bezier {}", bezier));
+                        }
+                        Response::Success {
+                            message: format!("Re-applied {} with {}", filename, settings)
+                        }
+                    }
+                    Err(e) => Response::Error {
+                        message: format!("Failed to test transition: {}", e)
+                    },
+                }
+            }
+            
+            Request::Auth { .. } => Response::Error {
+                message: "Auth requests cannot be nested".to_string()
+            },
+            Request::Timed { .. } => Response::Error {
+                message: "Nested timed requests are not supported".to_string()
+            },
+            
+            Request::Restart => {
+                if self.exe.is_none() {
+                    return Response::Error {
+                        message: "Cannot restart: executable path unknown".to_string()
+                    };
+                }
+                
+                info!("Restart requested");
+                self.restart.notify_one();
+                
+                Response::Success {
+                    message: "Server restarting".to_string()
+                }
+            }
+            
             Request::Shutdown => {
                 info!("Shutdown requested");
                 
@@ -537,48 +1323,352 @@ impl Server {
         }
     }
 
-    async fn switch_wallpaper(&mut self) -> Result<String> {
-        let profile = self.profile_manager.current_profile()
-            .context("Failed to get current profile")?;
+    async fn switch_wallpaper(&mut self, reason: &str) -> Result<String> {
+        self.switch_wallpaper_with(reason, None, None).await
+    }
+
+    /// Switch using transition preset `preset` instead of the profile's
+    async fn switch_wallpaper_with(&mut self, reason: &str, preset: Option<&str>, mode: Option<SwitchMode>) -> Result<String> {
+        if !crate::startup::compositor_available() {
+            info!("No compositor available, queueing {} switch", reason);
+            *self.pending_switch.lock().unwrap() = Some(PendingSwitch {
+                reason: reason.to_string(),
+                preset: preset.map(String::from),
+                mode,
+            });
+            return Err(SwitchError::Queued.into());
+        }
+        if let Some(shown) = self.shown_wallpaper() {
+            self.wallpaper_manager.set_last_wallpaper(shown);
+        }
+        let mut profile = self.active_profile()?;
+        if let Some(preset) = preset {
+            self.config.apply_transition_preset(&mut profile, preset)?;
+        }
+        let cooldown = self.cooldown.clone();
+        let wallpaper = self.switcher().switch(&profile, mode, &cooldown).await?;
+        timeline::record(EventKind::Wallpaper, format!("{} ({})", wallpaper, reason));
+        self.status_changed();
+        
+        Ok(wallpaper)
+    }
+
+    /// Show an image chosen by the user on every output
+    async fn set_given_wallpaper(&mut self, path: &str) -> Result<()> {
+        if !crate::startup::compositor_available() {
+            anyhow::bail!("Compositor not available");
+        }
+        let profile = self.active_profile()?;
+        self.switcher().show(path, &profile).await
+    }
+
+    async fn set_wallpaper_reply(&mut self, path: &str) -> Response {
+        match self.set_given_wallpaper(path).await {
+            Ok(()) => {
+                timeline::record(EventKind::Wallpaper, format!("{} (set)", path));
+                let filename = Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or(path);
+                Response::Success { message: format!("Set wallpaper: {}", filename) }
+            }
+            Err(e) => Response::Error { message: format!("Failed to set wallpaper: {}", e) },
+        }
+    }
+
+    /// The current profile's wallpaper as far as any connection knows,
+    /// before the first switch the one on screen when the server started.
+    /// This manager's own last wallpaper may be older, switches made by
+    /// other connections only reach the shared state.
+    fn shown_wallpaper(&self) -> Option<PathBuf> {
+        self.active_profile().ok()
+            .and_then(|profile| self.wallpaper_manager.shown(&profile))
+            .or_else(|| self.wallpaper_manager.last_wallpaper().cloned())
+    }
+
+    /// Show the wallpaper the current one replaced, keeping the history as
+    /// it was if that fails
+    async fn show_previous(&mut self) -> Result<String> {
+        let replaced = self.wallpaper_manager.history().first().cloned();
+        let previous = self.wallpaper_manager.step_back()
+            .context("No earlier wallpaper to go back to")?
+            .to_string_lossy()
+            .to_string();
+        if let Err(e) = self.set_given_wallpaper(&previous).await {
+            if let Some(replaced) = replaced {
+                self.wallpaper_manager.remember(&replaced);
+            }
+            return Err(e);
+        }
+        Ok(previous)
+    }
+
+    /// Wallpaper at 1-based `index` of the current profile's cached list,
+    /// as `ListWallpapers` lists it
+    async fn wallpaper_at(&mut self, index: usize) -> Result<String> {
+        let profile = self.active_profile()?;
+        self.wallpaper_manager.ensure_cache(&profile).await?;
+        let wallpapers = self.wallpaper_manager.wallpapers();
+        numbered(wallpapers, index)
+            .map(|path| path.to_string_lossy().to_string())
+            .with_context(|| format!("No wallpaper {} in profile {}, it has {}", index, self.config.current_profile, wallpapers.len()))
+    }
+
+    /// Wallpaper of the current profile whose file name best matches `pattern`
+    async fn wallpaper_matching(&mut self, pattern: &str) -> Result<String> {
+        let profile = self.active_profile()?;
+        self.wallpaper_manager.ensure_cache(&profile).await?;
+        find_by_name(self.wallpaper_manager.wallpapers().iter(), pattern)
+            .map(|path| path.to_string_lossy().to_string())
+            .with_context(|| format!("No wallpaper of profile {} matches '{}'", self.config.current_profile, pattern))
+    }
+
+    async fn list_wallpapers(&mut self) -> Result<(String, Vec<WallpaperInfo>)> {
+        let profile = self.active_profile()?;
+        self.wallpaper_manager.ensure_cache(&profile).await?;
+        let wallpapers = listed(self.wallpaper_manager.wallpapers(), &WallpaperIndex::load());
+        Ok((self.config.current_profile.clone(), wallpapers))
+    }
+
+    /// Show the current wallpaper again using `profile`'s transition
+    async fn reapply_wallpaper(&mut self, profile: &Profile) -> Result<String> {
+        if !crate::startup::compositor_available() {
+            anyhow::bail!("Compositor not available");
+        }
+        let wallpaper = self.shown_wallpaper()
+            .map(|p| p.to_string_lossy().to_string())
+            .context("No wallpaper has been set yet")?;
         
         if let Err(e) = self.wallpaper_manager.ensure_cache(profile).await {
             warn!("Failed to ensure wallpaper cache: {}", e);
         }
+        let outputs = self.switcher().prepare_outputs(profile).await;
+        self.wallpaper_manager.apply(&wallpaper, profile, &self.config, &outputs).await?;
+        Ok(wallpaper)
+    }
+
+    /// Show `[lock] wallpaper` while the session is locked and bring back
+    /// the previous one on unlock
+    async fn on_session_event(&mut self, event: SessionEvent) {
+        match event {
+            SessionEvent::Lock => {
+                if self.locked.lock().unwrap().is_some() {
+                    return;
+                }
+                info!("Session locked");
+                let current = self.wallpaper_manager.history().first().cloned();
+                let mut restore = None;
+                if let Some(wallpaper) = self.config.lock.wallpaper.clone() {
+                    let wallpaper = shellexpand::tilde(&wallpaper.to_string_lossy()).into_owned();
+                    restore = current.clone().or_else(|| WallpaperIndex::load().most_recent())
+                        .map(|p| p.to_string_lossy().to_string());
+                    if let Err(e) = self.show_around_lock(&wallpaper, false).await {
+                        warn!("Failed to show lock wallpaper: {}", e);
+                        restore = None;
+                    }
+                }
+                *self.locked.lock().unwrap() = Some(Locked { restore, current });
+            }
+            SessionEvent::Unlock => {
+                let Some(locked) = self.locked.lock().unwrap().take() else { return };
+                info!("Session unlocked");
+                if self.wallpaper_manager.history().first() != locked.current.as_ref() {
+                    // Switched while locked, which already replaced the lock wallpaper
+                    return;
+                }
+                if let Some(wallpaper) = locked.restore {
+                    match self.show_around_lock(&wallpaper, true).await {
+                        Ok(()) => timeline::record(EventKind::Wallpaper, format!("{} (unlocked)", wallpaper)),
+                        Err(e) => warn!("Failed to restore wallpaper after unlock: {}", e),
+                    }
+                }
+            }
+        }
+    }
+
+    /// The lock wallpaper goes on every output, leaving the history and
+    /// handoff file alone; the restored one is spread across them again
+    /// like the switch that chose it
+    async fn show_around_lock(&mut self, wallpaper: &str, restoring: bool) -> Result<()> {
+        let profile = self.active_profile()?;
+        if let Err(e) = self.wallpaper_manager.ensure_cache(&profile).await {
+            warn!("Failed to ensure wallpaper cache: {}", e);
+        }
+        let outputs = self.switcher().prepare_outputs(&profile).await;
+        if restoring {
+            self.wallpaper_manager.apply(wallpaper, &profile, &self.config, &outputs).await
+        } else {
+            self.wallpaper_manager.show_briefly(wallpaper, &profile).await
+        }
+    }
+
+    /// Apply a switch queued while offline once wallpapers can be set
+    /// again. Runs in its own task so waiting on swww never holds up
+    /// accepting clients.
+    fn apply_pending_switch(&self) {
+        if self.pending_switch.lock().unwrap().is_none() || self.replaying.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let mut server = self.clone();
+        tokio::spawn(async move {
+            let pending = if crate::startup::session_ready().await {
+                server.pending_switch.lock().unwrap().take()
+            } else {
+                None
+            };
+            if let Some(pending) = pending {
+                info!("Compositor available, applying queued {} switch", pending.reason);
+                server.sync_config();
+                let reason = format!("queued {}", pending.reason);
+                if let Err(e) = server.switch_wallpaper_with(&reason, pending.preset.as_deref(), pending.mode).await {
+                    warn!("Failed to apply queued switch: {}", e);
+                }
+            }
+            server.replaying.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Show the wallpaper belonging to `workspace` on `monitor`, picking one
+    /// the first time the workspace is seen
+    async fn show_workspace(&mut self, workspace: &str, monitor: &str) -> Result<()> {
+        let profile = &self.active_profile()?;
         
-        let wallpaper = self.wallpaper_manager.get_wallpaper(profile, &self.config)
-            .context("Failed to get wallpaper")?;
-        
-        info!("Switching to wallpaper: {}", wallpaper);
+        let known = self.workspace_wallpapers.lock().unwrap().get(workspace).cloned();
+        let wallpaper = match known {
+            Some(path) => path,
+            None => {
+                self.wallpaper_manager.ensure_cache(profile).await?;
+                let path = self.wallpaper_manager.get_wallpaper(profile, &self.config)?;
+                self.workspace_wallpapers.lock().unwrap().insert(workspace.to_string(), path.clone());
+                path
+            }
+        };
         
-        self.wallpaper_manager.set_wallpaper(&wallpaper, profile).await
-            .context("Failed to set wallpaper")?;
+        debug!("Workspace {} on {}: {}", workspace, monitor, wallpaper);
+        self.wallpaper_manager.set_outputs(&[(vec![monitor.to_string()], wallpaper)], profile).await?;
+        self.status_changed();
+        Ok(())
+    }
+
+    /// The current profile with active seasons and the channel applied
+    fn active_profile(&self) -> Result<Profile> {
+        let channel = self.channel.lock().unwrap().clone();
+        switcher::active_profile(&self.config, &self.profile_manager, channel.as_deref())
+    }
+
+    /// The switch logic shared with the embedded `Manager`
+    fn switcher(&mut self) -> Switcher<'_> {
+        Switcher {
+            config: &self.config,
+            wallpaper_manager: &mut self.wallpaper_manager,
+            monitor_manager: &self.monitor_manager,
+            weather: &self.weather,
+            analyzing: &self.analyzing,
+        }
+    }
+
+    /// Offer `profile` with a notification and switch only if accepted. A
+    /// newer detection replaces the question; late answers to the old one
+    /// are ignored.
+    fn ask_to_switch(&self, profile: &str) {
+        switcher::ask_to_switch(profile, &self.asking, |profile| async move {
+            let mut client = crate::client::Client::connect().await?;
+            client.switch_profile(&profile).await
+        });
+    }
+
+    /// With `auto_profile` enabled, save a new profile for monitors only the
+    /// wildcard or fallback profile covers and return its name
+    async fn add_auto_profile(&mut self, monitors: &[crate::hyprland_ipc::Monitor]) -> Result<Option<String>> {
+        if !self.config.monitor_detection.auto_profile {
+            return Ok(None);
+        }
+        let Some((name, profile)) = self.profile_manager.auto_profile(monitors) else {
+            return Ok(None);
+        };
+
+        self.config.profiles.insert(name.clone(), profile);
+        self.config.save_key(self.config_file(), &format!("profiles.{}", name))
+            .context("Failed to save auto-created profile")?;
+        self.profile_manager.update_config(self.config.clone());
+        self.publish_config();
+        info!("Created profile {} for unknown monitors", name);
+        timeline::record(EventKind::Profile, format!("Created profile {}", name));
+        notify::send(&t!("notify-auto-profile"), &t!("notify-auto-profile-body", profile = name)).await.ok();
+        Ok(Some(name))
+    }
+
+    /// Give newly connected `outputs` the wallpaper the others already show,
+    /// leaving existing outputs untouched
+    async fn fill_outputs(&mut self, outputs: &[String]) -> Result<()> {
+        if outputs.is_empty() {
+            return Ok(());
+        }
+        let profile = &self.active_profile()?;
         
-        Ok(wallpaper)
+        let shown = self.shown_wallpaper();
+        let wallpaper = self.switcher().fill_outputs(profile, outputs, shown).await?;
+        timeline::record(EventKind::Wallpaper, format!("{} on {} (new output)", wallpaper, outputs.join(", ")));
+        self.status_changed();
+        Ok(())
     }
 
-    async fn switch_profile(&mut self, name: &str) -> Result<()> {
+    /// Switch to the profile `name` is short for, returning its full name
+    async fn switch_profile(&mut self, name: &str) -> Result<String> {
+        let resolved = self.profile_manager.resolve(name)?;
+        let name = resolved.as_str();
         info!("Switching to profile: {}", name);
         
         self.profile_manager.switch_to(name)
             .with_context(|| format!("Profile '{}' not found", name))?;
         
         self.config.current_profile = name.to_string();
-        self.config.save(None)
+        self.config.save_key(self.config_file(), "current_profile")
             .context("Failed to save config after profile switch")?;
+        self.publish_config();
+        {
+            let mut channel = self.channel.lock().unwrap();
+            let kept = channel.as_ref().is_some_and(|c| {
+                self.config.profiles.get(name).is_some_and(|p| p.channels.contains_key(c))
+            });
+            if !kept {
+                *channel = None;
+            }
+        }
+        timeline::record(EventKind::Profile, format!("Switched to profile {}", name));
         
-        notify::send("Profile switched", name).await
+        notify::send(&t!("notify-profile-switched"), name).await
             .context("Failed to send notification")?;
         
-        self.switch_wallpaper().await?;
+        match self.switch_wallpaper("profile switch").await {
+            Err(e) if matches!(e.downcast_ref(), Some(SwitchError::Queued)) => {}
+            result => { result?; }
+        }
         
-        Ok(())
+        Ok(resolved)
+    }
+
+    async fn restore_wallpaper(&mut self) -> Result<()> {
+        crate::startup::wait_for_session(self.startup_timeout()).await?;
+
+        let profile = &self.active_profile()?;
+        self.wallpaper_manager.ensure_cache(profile).await?;
+
+        match self.wallpaper_manager.previously_shown() {
+            Some(path) => {
+                let path = path.to_string_lossy().to_string();
+                info!("Restoring wallpaper: {}", path);
+                self.wallpaper_manager.set_wallpaper(&path, profile).await?;
+                timeline::record(EventKind::Wallpaper, format!("{} (restored)", path));
+                Ok(())
+            }
+            None => self.switch_wallpaper("startup").await.map(|_| ()),
+        }
+    }
+
+    fn startup_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.startup.wait_timeout_secs)
     }
 
     fn socket_path() -> PathBuf {
-        let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
-            .unwrap_or_else(|_| format!("/run/user/{}", users::get_current_uid()));
-        
-        PathBuf::from(runtime_dir).join("swww-manager.sock")
+        daemon::runtime_file("sock")
     }
 
     pub async fn auto_switch_loop(mut self) {
@@ -588,6 +1678,10 @@ impl Server {
             return;
         }
 
+        if let Err(e) = crate::startup::wait_for_session(self.startup_timeout()).await {
+            warn!("Auto-switch: {}", e);
+        }
+
         debug!("Starting auto-switch loop (interval = {}s)", interval_secs);
 
         let mut intrvl = tokio::time::interval(Duration::from_secs(interval_secs));
@@ -599,35 +1693,46 @@ impl Server {
             intrvl.tick().await;
             let waited = tokio::time::Instant::now().duration_since(wait_start);
             debug!("Auto-switch tick (waited {:.3}s)", waited.as_secs_f64());
+            let next = chrono::Utc::now().timestamp() + interval_secs as i64;
+            self.next_switch.store(next, Ordering::SeqCst);
+            self.status_changed();
+            self.sync_config();
 
             if !self.config.auto_switch.enabled {
                 debug!("Auto-switch disabled, skipping tick");
                 continue;
             }
 
-            let profile = match self.profile_manager.current_profile() {
-                Ok(p) => p.clone(),
+            if !crate::startup::compositor_available() {
+                debug!("No compositor available, skipping auto-switch tick");
+                continue;
+            }
+
+            if self.config.lock.pause && self.locked.lock().unwrap().is_some() {
+                debug!("Session locked, skipping auto-switch tick");
+                continue;
+            }
+
+            let profile = match self.active_profile() {
+                Ok(p) => p,
                 Err(e) => {
                     tracing::warn!("Auto-switch: failed to get current profile: {}", e);
                     continue;
                 }
             };
 
-            // measure ensure_cache
-            let t0 = tokio::time::Instant::now();
-            if let Err(e) = self.wallpaper_manager.ensure_cache(&profile).await {
-                tracing::warn!("Auto-switch: ensure_cache failed: {}", e);
-            }
-            let ensure_dur = tokio::time::Instant::now().duration_since(t0);
-            debug!("ensure_cache took {:.3}s", ensure_dur.as_secs_f64());
-
-            // pick wallpaper (fast sync op) and log
+            // pick wallpaper and log
             let pick_t0 = tokio::time::Instant::now();
-            match self.wallpaper_manager.get_wallpaper(&profile, &self.config) {
-                Ok(wp) => {
+            match self.switcher().pick(&profile, None).await {
+                Ok((wp, outputs)) => {
                     let pick_dur = tokio::time::Instant::now().duration_since(pick_t0);
                     debug!("Picked wallpaper '{}' (pick took {:.3}s)", wp, pick_dur.as_secs_f64());
 
+                    if self.wallpaper_manager.last_wallpaper().map(|p| p == Path::new(&wp)).unwrap_or(false) {
+                        debug!("Wallpaper unchanged, skipping tick");
+                        continue;
+                    }
+
                     // clone minimal state for background task and spawn
                     let wm_for_spawn = self.wallpaper_manager.clone();
                     let prof = profile.clone();
@@ -638,24 +1743,42 @@ impl Server {
                     self.wallpaper_manager.set_last_wallpaper(PathBuf::from(&wp_clone));
 
                     debug!("Spawning background set_wallpaper task for '{}'", wp_clone);
+                    let cooldown = self.cooldown.clone();
+                    let cooldown_ms = self.config.switch_cooldown_ms;
+                    let config = self.config.clone();
                     tokio::spawn(async move {
+                        if cooldown_ms > 0
+                            && let Err(e) = cooldown.acquire(Duration::from_millis(cooldown_ms)).await {
+                                debug!("Auto-switch skipped: {}", e);
+                                return;
+                            }
                         let mut wm = wm_for_spawn;
-                        let set_timeout = Duration::from_secs(12);
                         let set_t0 = tokio::time::Instant::now();
 
-                        match tokio::time::timeout(set_timeout, wm.set_wallpaper(&wp_clone, &prof)).await {
-                            Ok(Ok(())) => {
+                        // apply waits for swww at most swww_timeout_secs
+                        match wm.apply(&wp_clone, &prof, &config, &outputs).await {
+                            Ok(()) => {
                                 let set_dur = tokio::time::Instant::now().duration_since(set_t0);
                                 tracing::info!("Auto-switch applied wallpaper: {} (took {:.3}s)", wp_clone, set_dur.as_secs_f64());
+                                timeline::record(EventKind::Wallpaper, format!("{} (auto-switch)", wp_clone));
                             }
-                            Ok(Err(e)) => {
-                                tracing::warn!("Auto-switch set_wallpaper error: {}", e);
+                            Err(e) if matches!(SwwwError::find(&e), Some(SwwwError::TimedOut(_))) => {
+                                tracing::warn!("Auto-switch is slow: {:#}", e);
+                                timeline::record(EventKind::Error, format!("Auto-switch: {:#}", e));
+                                notify::send_warning(&t!("notify-swww-slow")).await.ok();
                             }
-                            Err(_) => {
-                                tracing::warn!("Auto-switch set_wallpaper timed out (> {}s)", set_timeout.as_secs());
+                            Err(e) => {
+                                tracing::warn!("Auto-switch set_wallpaper error: {:#}", e);
+                                timeline::record(EventKind::Error, format!("Auto-switch failed: {:#}", e));
+                                notify::send_error(&failure_notice(&e)).await.ok();
                             }
                         }
                     });
+
+                    if self.config.auto_switch.preload
+                        && let Err(e) = self.wallpaper_manager.prepare_next(&profile, &self.config) {
+                            debug!("Auto-switch: failed to prepare next wallpaper: {}", e);
+                        }
                 }
                 Err(e) => {
                     tracing::warn!("Auto-switch: failed to pick wallpaper: {}", e);
@@ -665,14 +1788,192 @@ impl Server {
     }
 }
 
+/// Whether `request` may change what `GetStatus` reports
+fn changes_status(request: &Request) -> bool {
+    match request {
+        Request::Timed { request } | Request::Auth { request, .. } => changes_status(request),
+        Request::ListProfiles
+        | Request::GetStatus
+        | Request::WatchStatus
+        | Request::ExplainDetection
+        | Request::GetVersion
+        | Request::GetConfig { .. }
+        | Request::ListWallpapers => false,
+        _ => true,
+    }
+}
+
+/// Read one JSON request, which may arrive in several chunks. Gives up on
+/// clients that send too much or stall, and returns `None` when the client
+/// disconnects without sending anything.
+async fn read_request<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Option<Request>, RequestError> {
+    let read = async {
+        let mut buffer = Vec::with_capacity(1024);
+        let mut chunk = [0u8; 8192];
+        loop {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 && buffer.is_empty() {
+                return Ok(None);
+            }
+            buffer.extend_from_slice(&chunk[..n]);
+            match serde_json::from_slice(&buffer) {
+                Ok(request) => return Ok(Some(request)),
+                Err(e) if e.is_eof() && n > 0 => {
+                    if buffer.len() > MAX_REQUEST_BYTES {
+                        return Err(RequestError::TooLarge);
+                    }
+                }
+                Err(e) => return Err(RequestError::Invalid(e)),
+            }
+        }
+    };
+    tokio::time::timeout(READ_TIMEOUT, read)
+        .await
+        .unwrap_or(Err(RequestError::Timeout))
+}
+
+/// Answer with an error and close, without waiting on a client that
+/// doesn't read
+async fn reject<S: AsyncWrite + Unpin>(mut stream: S, message: &str) -> Result<()> {
+    let response = serde_json::to_vec(&Response::Error { message: message.to_string() })?;
+    tokio::time::timeout(READ_TIMEOUT, async {
+        stream.write_all(&response).await?;
+        stream.flush().await
+    })
+    .await
+    .context("Timed out sending rejection")??;
+    Ok(())
+}
+
+/// Notification text for a failed wallpaper switch
+fn failure_notice(e: &anyhow::Error) -> String {
+    match SwwwError::find(e) {
+        Some(SwwwError::DaemonNotRunning) => t!("notify-swww-daemon-down"),
+        Some(SwwwError::UnsupportedImage { path, .. }) => t!(
+            "notify-swww-unsupported",
+            name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy()
+        ),
+        Some(SwwwError::Socket(_)) => t!("notify-swww-socket"),
+        _ => e.to_string(),
+    }
+}
+
+/// Compare secrets without leaking the position of the first mismatch
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Wallpaper number `index` (from 1) of `wallpapers`, the numbering of
+/// [`listed`] that contact sheets show
+fn numbered(wallpapers: &PathList, index: usize) -> Option<PathBuf> {
+    index.checked_sub(1).and_then(|i| wallpapers.get(i))
+}
+
+/// `wallpapers` in order with their marks, as `ListWallpapers` returns them
+fn listed(wallpapers: &PathList, index: &WallpaperIndex) -> Vec<WallpaperInfo> {
+    wallpapers
+        .iter()
+        .map(|path| WallpaperInfo {
+            path: path.to_string_lossy().to_string(),
+            favorite: index.is_favorite(&path),
+            blocked: index.is_blocked(&path),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::TempDir;
+
+    #[test]
+    fn test_contact_sheet_numbers_match_set_index() {
+        let wallpapers: PathList = ["/w/b.png", "/w/a.png", "/w/sets/dual"].into_iter().collect();
+        let tiles: Vec<PathBuf> = listed(&wallpapers, &WallpaperIndex::default())
+            .into_iter()
+            .map(|w| PathBuf::from(w.path))
+            .collect();
+        let args = crate::contact_sheet::montage_args(&tiles, Path::new("sheet.png"), 8);
+        let labelled: Vec<(usize, PathBuf)> = args
+            .chunks(3)
+            .take(tiles.len())
+            .map(|arg| (arg[1].to_str().unwrap().parse().unwrap(), PathBuf::from(&arg[2])))
+            .collect();
+        assert_eq!(labelled.len(), 3);
+        for (number, tile) in labelled {
+            assert_eq!(numbered(&wallpapers, number), Some(tile));
+        }
+        assert_eq!(numbered(&wallpapers, 0), None);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret-token", b"secret-token"));
+        assert!(!constant_time_eq(b"secret-token", b"secret-tokem"));
+        assert!(!constant_time_eq(b"secret", b"secret-token"));
+    }
+
+    #[tokio::test]
+    async fn test_read_request_limits() {
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        client.write_all(b"{\"SetAutoSwitch\":").await.unwrap();
+        client.write_all(b"{\"enabled\":true}}").await.unwrap();
+        assert!(matches!(
+            read_request(&mut server).await,
+            Ok(Some(Request::SetAutoSwitch { enabled: true }))
+        ));
+
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        let writer = tokio::spawn(async move {
+            client.write_all(b"{\"SwitchProfile\":{\"name\":\"").await.unwrap();
+            let _ = client.write_all(&vec![b'a'; MAX_REQUEST_BYTES + 1]).await;
+        });
+        assert!(matches!(read_request(&mut server).await, Err(RequestError::TooLarge)));
+        drop(server);
+        writer.await.unwrap();
+
+        let (mut client, mut server) = tokio::io::duplex(4096);
+        client.write_all(b"not json").await.unwrap();
+        assert!(matches!(read_request(&mut server).await, Err(RequestError::Invalid(_))));
+    }
+
+    #[tokio::test]
+    async fn test_watch_status_sends_changes_only() {
+        use tokio::io::AsyncBufReadExt;
+
+        let server = Server::new(Config::default(), None).await.unwrap();
+        let (client, stream) = tokio::io::duplex(64 * 1024);
+        let mut watcher = server.clone();
+        let watching = tokio::spawn(async move { watcher.watch_status(stream).await });
+        let mut lines = tokio::io::BufReader::new(client).lines();
+        async fn next<R: tokio::io::AsyncBufRead + Unpin>(lines: &mut tokio::io::Lines<R>) -> Option<StatusInfo> {
+            let line = tokio::time::timeout(Duration::from_millis(500), lines.next_line()).await.ok()?;
+            match serde_json::from_str(&line.unwrap().unwrap()).unwrap() {
+                Response::Status { status } => Some(status),
+                other => panic!("unexpected response: {:?}", other),
+            }
+        }
+
+        assert_eq!(next(&mut lines).await.unwrap().channel, None);
+        server.status_changed();
+        assert!(next(&mut lines).await.is_none(), "unchanged status sent again");
+
+        *server.channel.lock().unwrap() = Some("dark".to_string());
+        server.status_changed();
+        assert_eq!(next(&mut lines).await.unwrap().channel.as_deref(), Some("dark"));
+
+        server.stopping.store(true, Ordering::SeqCst);
+        server.status_changed();
+        watching.await.unwrap().unwrap();
+    }
 
     #[tokio::test]
     async fn test_socket_path() {
         let path = Server::socket_path();
-        assert!(path.ends_with("swww-manager.sock"));
+        let name = path.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("swww-manager") && name.ends_with(".sock"));
+        match daemon::session_tag() {
+            Some(tag) => assert!(name.contains(&tag)),
+            None => assert_eq!(name, "swww-manager.sock"),
+        }
     }
 }
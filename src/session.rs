@@ -0,0 +1,107 @@
+//! Lock and unlock of the logind session (`loginctl lock-session`, idle
+//! daemons), followed through `gdbus monitor` on the system bus. Unlike
+//! busctl or dbus-monitor it only adds a match rule, so it needs no
+//! privileges.
+
+use anyhow::{Context, Result};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+use tracing::{debug, info};
+
+const LOGIN1: &str = "org.freedesktop.login1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Lock,
+    Unlock,
+}
+
+/// Object path of this process's session, from `XDG_SESSION_ID` or else
+/// our PID. `None` for services started outside a session.
+async fn session_path() -> Option<String> {
+    let (method, arg) = match std::env::var("XDG_SESSION_ID") {
+        Ok(id) if !id.is_empty() => ("GetSession", id),
+        _ => ("GetSessionByPID", std::process::id().to_string()),
+    };
+    let cmd = Command::new("gdbus")
+        .args(["call", "--system", "--dest", LOGIN1, "--object-path", "/org/freedesktop/login1"])
+        .args(["--method", &format!("{}.Manager.{}", LOGIN1, method), &arg])
+        .output();
+    let output = timeout(Duration::from_secs(5), cmd).await.ok()?.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // Printed as "(objectpath '/org/freedesktop/login1/session/_32',)"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.split('\'').nth(1).map(String::from)
+}
+
+/// Parse a `gdbus monitor` line such as
+/// `/org/freedesktop/login1/session/_32: org.freedesktop.login1.Session.Lock ()`,
+/// keeping only signals of `session` when it is known
+fn parse_line(line: &str, session: Option<&str>) -> Option<SessionEvent> {
+    let (path, signal) = line.split_once(": ")?;
+    if session.is_some_and(|s| s != path) {
+        return None;
+    }
+    match signal.split_whitespace().next()? {
+        "org.freedesktop.login1.Session.Lock" => Some(SessionEvent::Lock),
+        "org.freedesktop.login1.Session.Unlock" => Some(SessionEvent::Unlock),
+        _ => None,
+    }
+}
+
+/// Run `handler` for every lock and unlock until the monitor exits
+pub async fn watch<F>(mut handler: F) -> Result<()>
+where
+    F: FnMut(SessionEvent) -> futures::future::BoxFuture<'static, ()>,
+{
+    let session = session_path().await;
+    match &session {
+        Some(path) => info!("Following lock/unlock of session {}", path),
+        None => info!("Session not found, following lock/unlock of every session"),
+    }
+
+    let mut child = Command::new("gdbus")
+        .args(["monitor", "--system", "--dest", LOGIN1])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute gdbus")?;
+    let stdout = child.stdout.take().context("gdbus has no stdout")?;
+
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Some(event) = parse_line(&line, session.as_deref()) {
+            debug!("Session event: {:?}", event);
+            handler(event).await;
+        }
+    }
+    anyhow::bail!("gdbus monitor exited")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let ours = "/org/freedesktop/login1/session/_32";
+        let lock = "/org/freedesktop/login1/session/_32: org.freedesktop.login1.Session.Lock ()";
+        let unlock = "/org/freedesktop/login1/session/_32: org.freedesktop.login1.Session.Unlock ()";
+        let other = "/org/freedesktop/login1/session/c1: org.freedesktop.login1.Session.Lock ()";
+
+        assert_eq!(parse_line(lock, Some(ours)), Some(SessionEvent::Lock));
+        assert_eq!(parse_line(unlock, Some(ours)), Some(SessionEvent::Unlock));
+        assert_eq!(parse_line(other, Some(ours)), None);
+        assert_eq!(parse_line(other, None), Some(SessionEvent::Lock));
+        assert_eq!(parse_line("Monitoring signals from all objects owned by org.freedesktop.login1", None), None);
+        assert_eq!(
+            parse_line("/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew ('3', objectpath '/x')", None),
+            None
+        );
+    }
+}
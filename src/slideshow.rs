@@ -0,0 +1,96 @@
+use crate::config::Config;
+use crate::i18n::t;
+use crate::scan::scan_dirs;
+use crate::wallpaper::expand_dirs;
+use anyhow::{Context, Result};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Images of `profile`, today's seasons included, in the order sequential
+/// rotation shows them
+pub fn images(config: &Config, profile: &str) -> Result<Vec<PathBuf>> {
+    let profile = config.profiles.get(profile)
+        .with_context(|| t!("profile-not-found", profile = profile))?;
+    let images = scan_dirs(&expand_dirs(&config.apply_seasons(profile)));
+    if images.is_empty() {
+        anyhow::bail!("{}", t!("no-wallpapers"));
+    }
+    Ok(images)
+}
+
+/// Extended M3U playlist, e.g. for `mpv --image-display-duration=5`
+pub fn playlist(images: &[PathBuf]) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+    for image in images {
+        let name = image.file_name().unwrap_or_default().to_string_lossy();
+        let _ = write!(m3u, "#EXTINF:-1,{}\n{}\n", name, image.display());
+    }
+    m3u
+}
+
+/// Input for ffmpeg's concat demuxer showing each image for `seconds`
+fn concat_list(images: &[PathBuf], seconds: f32) -> String {
+    let quote = |path: &Path| path.to_string_lossy().replace('\'', r"'\''");
+    let mut list = String::from("ffconcat version 1.0\n");
+    for image in images {
+        let _ = write!(list, "file '{}'\nduration {}\n", quote(image), seconds);
+    }
+    // The last duration only applies when another entry follows
+    if let Some(last) = images.last() {
+        let _ = writeln!(list, "file '{}'", quote(last));
+    }
+    list
+}
+
+/// Encode the images into a video with ffmpeg, each scaled to fit
+/// `width`x`height` and shown for `seconds`
+pub async fn video(images: &[PathBuf], output: &Path, seconds: f32, (width, height): (u32, u32)) -> Result<()> {
+    let list = std::env::temp_dir().join(format!("swww-manager-slideshow-{}.txt", std::process::id()));
+    fs::write(&list, concat_list(images, seconds))
+        .with_context(|| format!("Failed to write {:?}", list))?;
+
+    let filter = format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,format=yuv420p",
+        w = width,
+        h = height,
+    );
+    let status = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-stats", "-y", "-f", "concat", "-safe", "0", "-i"])
+        .arg(&list)
+        .args(["-vf", &filter, "-r", "30"])
+        .arg(output)
+        .status()
+        .await;
+    let _ = fs::remove_file(&list);
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => anyhow::bail!("ffmpeg failed ({})", status),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => anyhow::bail!("ffmpeg is not installed"),
+        Err(e) => Err(e).context("Failed to execute ffmpeg"),
+    }
+}
+
+/// Whether `output` names a playlist rather than a video
+pub fn is_playlist(output: &Path) -> bool {
+    output.extension().is_some_and(|e| e.eq_ignore_ascii_case("m3u") || e.eq_ignore_ascii_case("m3u8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slideshow_files() {
+        let images = [PathBuf::from("/w/a.png"), PathBuf::from("/w/it's.jpg")];
+        assert_eq!(playlist(&images), "#EXTM3U\n#EXTINF:-1,a.png\n/w/a.png\n#EXTINF:-1,it's.jpg\n/w/it's.jpg\n");
+        assert_eq!(
+            concat_list(&images, 2.5),
+            "ffconcat version 1.0\nfile '/w/a.png'\nduration 2.5\nfile '/w/it'\\''s.jpg'\nduration 2.5\nfile '/w/it'\\''s.jpg'\n"
+        );
+        assert!(is_playlist(Path::new("anime.M3U")));
+        assert!(!is_playlist(Path::new("anime.mp4")));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::protocol::VersionInfo;
+use clap::ValueEnum;
+
+/// Languages `client-snippet` writes a minimal client in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Lang {
+    /// Python 3.8+, standard library only
+    Python,
+    /// Bash, using socat for the socket
+    Bash,
+}
+
+impl Lang {
+    /// Client for this build's protocol, resolving the socket the same way
+    /// as the CLI
+    pub fn snippet(self) -> String {
+        let template = match self {
+            Lang::Python => PYTHON,
+            Lang::Bash => BASH,
+        };
+        template.replace("{VERSION}", &VersionInfo::current().version)
+    }
+}
+
+const PYTHON: &str = r#"#!/usr/bin/env python3
+"""Minimal swww-manager client (protocol of swww-manager {VERSION}).
+
+Requests and responses are single JSON values over a Unix socket: send one
+request, then read the response until the daemon closes the connection.
+`swww-manager protocol-schema` describes every message.
+"""
+import json
+import os
+import re
+import socket
+
+
+def socket_path():
+    runtime = os.environ.get("XDG_RUNTIME_DIR") or f"/run/user/{os.getuid()}"
+    tag = os.environ.get("HYPRLAND_INSTANCE_SIGNATURE") or os.environ.get("WAYLAND_DISPLAY")
+    if tag:
+        session = os.path.join(runtime, "swww-manager-%s.sock" % re.sub(r"[^A-Za-z0-9_-]", "_", tag))
+        if os.path.exists(session):
+            return session
+    return os.path.join(runtime, "swww-manager.sock")
+
+
+def request(message):
+    with socket.socket(socket.AF_UNIX, socket.SOCK_STREAM) as sock:
+        sock.settimeout(30)
+        sock.connect(socket_path())
+        sock.sendall(json.dumps(message).encode())
+        sock.shutdown(socket.SHUT_WR)
+        data = b""
+        while chunk := sock.recv(8192):
+            data += chunk
+    return json.loads(data)
+
+
+if __name__ == "__main__":
+    # Variants without fields are plain strings, the others objects
+    print(request("GetStatus"))
+    print(request({"Switch": {"profile": None}}))
+"#;
+
+const BASH: &str = r#"#!/usr/bin/env bash
+# Minimal swww-manager client (protocol of swww-manager {VERSION}), needs socat.
+#
+# Requests and responses are single JSON values over a Unix socket: send one
+# request, then read the response until the daemon closes the connection.
+# `swww-manager protocol-schema` describes every message.
+set -euo pipefail
+
+socket_path() {
+    local runtime="${XDG_RUNTIME_DIR:-/run/user/$(id -u)}"
+    local tag="${HYPRLAND_INSTANCE_SIGNATURE:-${WAYLAND_DISPLAY:-}}"
+    if [[ -n "$tag" ]]; then
+        local session="$runtime/swww-manager-${tag//[^A-Za-z0-9_-]/_}.sock"
+        if [[ -S "$session" ]]; then
+            echo "$session"
+            return
+        fi
+    fi
+    echo "$runtime/swww-manager.sock"
+}
+
+# request JSON: prints the daemon's JSON response
+request() {
+    printf '%s' "$1" | socat -t 30 - "UNIX-CONNECT:$(socket_path)"
+    echo
+}
+
+# Variants without fields are plain strings, the others objects
+request '"GetStatus"'
+request '{"Switch":{"profile":null}}'
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippets_name_this_version() {
+        for lang in Lang::value_variants() {
+            let snippet = lang.snippet();
+            assert!(snippet.contains(&VersionInfo::current().version), "{:?}", lang);
+            assert!(!snippet.contains("{VERSION}"), "{:?}", lang);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::hyprland_ipc::HyprlandIPC;
+use anyhow::Result;
+use std::path::PathBuf;
+use tokio::process::Command;
+use tokio::time::{sleep, timeout, Duration, Instant};
+use tracing::{debug, info};
+
+/// Wait until the compositor and swww-daemon both answer, retrying with
+/// exponential backoff for at most `limit`.
+pub async fn wait_for_session(limit: Duration) -> Result<()> {
+    let start = Instant::now();
+    let mut delay = Duration::from_millis(250);
+    let mut attempt = 1;
+
+    loop {
+        let compositor = compositor_available();
+        let swww = swww_ready().await;
+        if compositor && swww {
+            if attempt > 1 {
+                info!("Compositor and swww-daemon ready after {:.1}s", start.elapsed().as_secs_f64());
+            }
+            return Ok(());
+        }
+
+        if start.elapsed() >= limit {
+            anyhow::bail!(
+                "Gave up waiting after {}s (compositor: {}, swww-daemon: {})",
+                limit.as_secs(),
+                if compositor { "ready" } else { "missing" },
+                if swww { "ready" } else { "missing" }
+            );
+        }
+
+        info!(
+            "Waiting for {} (attempt {}, retry in {}ms)",
+            match (compositor, swww) {
+                (false, false) => "the compositor and swww-daemon",
+                (false, true) => "the compositor",
+                _ => "swww-daemon",
+            },
+            attempt,
+            delay.as_millis()
+        );
+        sleep(delay).await;
+        delay = (delay * 2).min(Duration::from_secs(4));
+        attempt += 1;
+    }
+}
+
+/// Whether a Wayland compositor is running right now, going by its socket.
+/// Works for Sway and other wlroots compositors too; Hyprland's request
+/// socket also counts, for daemons started without `WAYLAND_DISPLAY`.
+pub fn compositor_available() -> bool {
+    wayland_socket_present() || HyprlandIPC::new().is_ok()
+}
+
+/// The socket `WAYLAND_DISPLAY` names exists, or without the variable any
+/// `wayland-*` socket in the runtime directory, as swww falls back to those
+fn wayland_socket_present() -> bool {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+    match std::env::var_os("WAYLAND_DISPLAY") {
+        // An absolute path is used as is, `join` keeps it
+        Some(display) => runtime_dir.unwrap_or_default().join(display).exists(),
+        None => runtime_dir
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .is_some_and(|mut entries| entries.any(|entry| entry.is_ok_and(|e| {
+                let name = e.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("wayland-") && !name.ends_with(".lock")
+            }))),
+    }
+}
+
+/// The compositor and swww-daemon both answer, wallpapers can be applied
+pub async fn session_ready() -> bool {
+    compositor_available() && swww_ready().await
+}
+
+async fn swww_ready() -> bool {
+    let cmd = Command::new("swww").arg("query").output();
+    match timeout(Duration::from_secs(3), cmd).await {
+        Ok(Ok(output)) => output.status.success(),
+        Ok(Err(e)) => {
+            debug!("Failed to execute swww: {}", e);
+            false
+        }
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,127 @@
+use crate::protocol::StatusInfo;
+use anyhow::Result;
+use chrono::{Local, TimeZone};
+use std::path::Path;
+
+/// Placeholders `status --format` understands, with what they stand for.
+/// Ones without a value, such as `{channel}` outside a channel, are empty.
+pub const PLACEHOLDERS: &[(&str, &str)] = &[
+    ("profile", "current profile"),
+    ("channel", "channel rotation is limited to"),
+    ("wallpaper", "path of the current wallpaper"),
+    ("wallpaper_name", "file name of the current wallpaper"),
+    ("wallpaper_stem", "file name without extension"),
+    ("auto", "on or off"),
+    ("interval", "auto-switch interval in seconds"),
+    ("next_switch_secs", "seconds until the next automatic switch"),
+    ("next_switch_hm", "time until the next switch, e.g. 1h05m or 12m"),
+    ("next_switch_at", "local time of the next switch, e.g. 14:30"),
+    ("monitors", "connected monitors, comma separated"),
+    ("monitor_count", "number of connected monitors"),
+    ("uptime_secs", "seconds since the daemon started"),
+    ("version", "daemon version"),
+    ("pid", "daemon process id"),
+    ("offline", "\"offline\" while no compositor is reachable"),
+];
+
+/// Fill `template`'s `{placeholder}`s from `status`, `now` being the
+/// current Unix time in seconds. `{{` and `}}` stand for literal braces.
+pub fn render(template: &str, status: &StatusInfo, now: i64) -> Result<String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(pos) = rest.find(['{', '}']) {
+        rendered.push_str(&rest[..pos]);
+        let brace = &rest[pos..pos + 1];
+        rest = &rest[pos + 1..];
+        if let Some(after) = rest.strip_prefix(brace) {
+            rendered.push_str(brace);
+            rest = after;
+            continue;
+        }
+        if brace == "}" {
+            anyhow::bail!("Unmatched '}}' in format, write '}}}}' for a literal brace");
+        }
+        let Some(end) = rest.find('}') else {
+            anyhow::bail!("Unclosed '{{' in format, write '{{{{' for a literal brace");
+        };
+        rendered.push_str(&value(&rest[..end], status, now)?);
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+fn value(name: &str, status: &StatusInfo, now: i64) -> Result<String> {
+    let wallpaper = status.current_wallpaper.as_deref().map(Path::new);
+    let file_part = |part: fn(&Path) -> Option<&std::ffi::OsStr>| {
+        wallpaper.and_then(part).map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+    };
+    let remaining = status.next_switch_at.map(|at| (at - now).max(0));
+
+    Ok(match name {
+        "profile" => status.current_profile.clone(),
+        "channel" => status.channel.clone().unwrap_or_default(),
+        "wallpaper" => status.current_wallpaper.clone().unwrap_or_default(),
+        "wallpaper_name" => file_part(Path::file_name),
+        "wallpaper_stem" => file_part(Path::file_stem),
+        "auto" => if status.auto_switch_enabled { "on" } else { "off" }.to_string(),
+        "interval" => status.auto_switch_interval.map(|i| i.to_string()).unwrap_or_default(),
+        "next_switch_secs" => remaining.map(|s| s.to_string()).unwrap_or_default(),
+        "next_switch_hm" => remaining.map(hours_minutes).unwrap_or_default(),
+        "next_switch_at" => status.next_switch_at
+            .and_then(|at| Local.timestamp_opt(at, 0).single())
+            .map(|at| at.format("%H:%M").to_string())
+            .unwrap_or_default(),
+        "monitors" => status.monitors.join(", "),
+        "monitor_count" => status.monitors.len().to_string(),
+        "uptime_secs" => status.uptime_secs.to_string(),
+        "version" => status.version.as_ref().map(|v| v.version.clone()).unwrap_or_default(),
+        "pid" => status.pid.map(|p| p.to_string()).unwrap_or_default(),
+        "offline" => if status.offline { "offline" } else { "" }.to_string(),
+        _ => anyhow::bail!(
+            "Unknown placeholder {{{}}}, available: {}",
+            name,
+            PLACEHOLDERS.iter().map(|(p, _)| format!("{{{}}}", p)).collect::<Vec<_>>().join(" ")
+        ),
+    })
+}
+
+/// Whole minutes rounded up, so a switch is never shown as 0m away
+pub(crate) fn hours_minutes(secs: i64) -> String {
+    let minutes = (secs + 59) / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, m) => format!("{}h{:02}m", h, m),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let status = StatusInfo {
+            auto_switch_interval: Some(300),
+            current_profile: "laptop".into(),
+            current_wallpaper: Some("/w/lake.jpg".into()),
+            auto_switch_enabled: true,
+            monitors: vec!["eDP-1".into(), "DP-1".into()],
+            uptime_secs: 10,
+            pid: None,
+            version: None,
+            binary_updated: false,
+            started_at: None,
+            offline: false,
+            channel: None,
+            next_switch_at: Some(1_000 + 3_900),
+            output_wallpapers: Default::default(),
+        };
+        let line = render("{profile} | {wallpaper_stem} | next in {next_switch_hm} {{{monitor_count}}}{channel}", &status, 1_000);
+        assert_eq!(line.unwrap(), "laptop | lake | next in 1h05m {2}");
+        assert_eq!(render("{next_switch_hm}", &status, 4_880).unwrap(), "1m");
+        assert!(render("{nope}", &status, 0).is_err());
+        assert!(render("{profile", &status, 0).is_err());
+        assert!(render("profile}", &status, 0).is_err());
+    }
+}
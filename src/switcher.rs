@@ -0,0 +1,257 @@
+use crate::ambient::{self, Lighting};
+use crate::config::{Config, Profile, SwitchMode};
+use crate::content;
+use crate::convert;
+use crate::cooldown::SwitchCooldown;
+use crate::i18n::t;
+use crate::monitor::{self, MonitorManager};
+use crate::notify;
+use crate::profile::ProfileManager;
+use crate::wallpaper::{OutputTransition, WallpaperManager};
+use crate::weather::WeatherProvider;
+
+use anyhow::{Context, Result};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::Duration;
+use tracing::{debug, info, warn};
+
+/// How long a "switch profile?" notification waits for an answer
+const ASK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Picking and applying wallpapers, the same for the daemon and the
+/// embedded `Manager`: the parts of either one a switch needs, borrowed
+/// for its duration
+pub struct Switcher<'a> {
+    pub config: &'a Config,
+    pub wallpaper_manager: &'a mut WallpaperManager,
+    pub monitor_manager: &'a MonitorManager,
+    pub weather: &'a WeatherProvider,
+    /// Set while wallpapers are being analyzed in the background
+    pub analyzing: &'a Arc<AtomicBool>,
+}
+
+impl Switcher<'_> {
+    /// Pick the next wallpaper of `profile` by `mode` (default: the
+    /// configured one) and show it once the switch cooldown allows
+    pub async fn switch(&mut self, profile: &Profile, mode: Option<SwitchMode>, cooldown: &SwitchCooldown) -> Result<String> {
+        if mode.is_some() {
+            // Picked ahead of time by the configured mode
+            self.wallpaper_manager.discard_upcoming();
+        }
+        let (wallpaper, outputs) = self.pick(profile, mode).await?;
+
+        if self.config.switch_cooldown_ms > 0 {
+            cooldown.acquire(Duration::from_millis(self.config.switch_cooldown_ms)).await?;
+        }
+
+        info!("Switching to wallpaper: {}", wallpaper);
+
+        let config = self.config.with_switch_mode(mode);
+        self.wallpaper_manager.apply(&wallpaper, profile, &config, &outputs).await
+            .context("Failed to set wallpaper")?;
+        Ok(wallpaper)
+    }
+
+    /// Show the image at `path`, or a matched set of `profile` listed by
+    /// its wallpapers, as chosen by the user
+    pub async fn show(&mut self, path: &str, profile: &Profile) -> Result<()> {
+        if Path::new(path).is_dir() {
+            self.wallpaper_manager.ensure_cache(profile).await?;
+            if !self.wallpaper_manager.wallpapers().contains(Path::new(path)) {
+                anyhow::bail!("Not a matched set of the current profile: {}", path);
+            }
+            let outputs = self.prepare_outputs(profile).await;
+            return self.wallpaper_manager.apply(path, profile, self.config, &outputs).await;
+        }
+        if !Path::new(path).is_file() {
+            anyhow::bail!("Not a file: {}", path);
+        }
+        self.prepare_outputs(profile).await;
+        self.wallpaper_manager.set_wallpaper(path, profile).await
+    }
+
+    /// The next wallpaper of `profile` by `mode`, with the outputs to
+    /// spread it across as [`Self::prepare_outputs`] returns them
+    pub async fn pick(&mut self, profile: &Profile, mode: Option<SwitchMode>) -> Result<(String, Vec<String>)> {
+        if let Err(e) = self.wallpaper_manager.ensure_cache(profile).await {
+            warn!("Failed to ensure wallpaper cache: {}", e);
+        }
+
+        self.prepare_selection(profile).await;
+
+        let outputs = self.prepare_outputs(profile).await;
+        let config = self.config.with_switch_mode(mode);
+        let wallpaper = self.wallpaper_manager.get_wallpaper(profile, &config)
+            .context("Failed to get wallpaper")?;
+        Ok((wallpaper, outputs))
+    }
+
+    /// Give newly connected `outputs` `shown`, the wallpaper the others
+    /// already show, or a new pick when nothing is known to be shown,
+    /// leaving existing outputs untouched. Returns the wallpaper given.
+    pub async fn fill_outputs(&mut self, profile: &Profile, outputs: &[String], shown: Option<PathBuf>) -> Result<String> {
+        let wallpaper = match shown {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => {
+                self.wallpaper_manager.ensure_cache(profile).await?;
+                self.wallpaper_manager.get_wallpaper(profile, self.config)?
+            }
+        };
+
+        self.wallpaper_manager.set_outputs(&[(outputs.to_vec(), wallpaper.clone())], profile).await
+            .context("Failed to set wallpaper")?;
+        Ok(wallpaper)
+    }
+
+    /// Hand the wallpaper manager the current weather tags and room
+    /// lighting. When the profile's image filters, widget areas or ambient
+    /// light need them, images not measured or scanned yet are analyzed in
+    /// the background, one job at a time.
+    pub async fn prepare_selection(&mut self, profile: &Profile) {
+        let tags = self.weather.tags(&self.config.weather).await;
+        self.wallpaper_manager.set_weather_tags(tags);
+
+        let light = &self.config.ambient_light;
+        let lighting = if !light.enabled {
+            Lighting::Normal
+        } else {
+            match ambient::light_level().await {
+                Ok(lux) => {
+                    let lighting = Lighting::from_lux(lux, light);
+                    debug!("Ambient light: {:.0} lux ({:?})", lux, lighting);
+                    lighting
+                }
+                Err(e) => {
+                    debug!("Ambient light unavailable: {}", e);
+                    Lighting::Normal
+                }
+            }
+        };
+        self.wallpaper_manager.set_lighting(lighting);
+
+        let stats = light.enabled || profile.filters_stats() || self.config.readability.enabled();
+        let (text, faces) = (profile.max_text.is_some(), profile.skip_faces);
+        if (stats || text || faces) && !self.analyzing.swap(true, Ordering::SeqCst) {
+            let files = self.wallpaper_manager.wallpapers().to_vec();
+            let readability = self.config.readability.clone();
+            let analyzing = self.analyzing.clone();
+            tokio::spawn(async move {
+                if stats {
+                    convert::analyze(&files, &readability).await;
+                }
+                content::scan(&files, text, faces).await;
+                analyzing.store(false, Ordering::SeqCst);
+            });
+        }
+    }
+
+    /// Query the monitors when the next switch depends on them: returns the
+    /// outputs to spread images across when per-monitor offsets or matched
+    /// sets are in effect, and hands the wallpaper manager each output's
+    /// refresh-rate based fps and VRR transition, along with the current
+    /// evening tint and where to hand applied wallpapers off to
+    pub async fn prepare_outputs(&mut self, profile: &Profile) -> Vec<String> {
+        self.wallpaper_manager.set_tint(self.config.tint.strength_at(chrono::Local::now().time()));
+        self.wallpaper_manager.set_handoff(self.config.handoff.enabled.then(|| self.config.handoff.clone()));
+
+        let per_monitor = crate::wallpaper::per_monitor(self.config, profile);
+        let match_fps = self.config.refresh_rate_fps.enabled && profile.transition_fps.is_none();
+        let vrr = self.config.vrr_transition.as_ref();
+        let monitors = if per_monitor || match_fps || vrr.is_some() {
+            self.monitor_manager.get_monitor_details().await.unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let fps = &self.config.refresh_rate_fps;
+        let transitions = monitors
+            .iter()
+            .map(|m| {
+                let mut transition = OutputTransition {
+                    fps: match_fps.then(|| fps.fps_for(m.refreshRate)),
+                    ..Default::default()
+                };
+                if let Some(vrr) = vrr.filter(|_| m.vrr) {
+                    transition.transition = vrr.transition.clone();
+                    transition.duration = vrr.duration;
+                    transition.fps = vrr.fps.or(transition.fps);
+                }
+                (m.name.clone(), transition)
+            })
+            .collect();
+        self.wallpaper_manager.set_output_transitions(transitions);
+
+        let outputs = if per_monitor { monitor::names(&monitors) } else { Vec::new() };
+        self.wallpaper_manager.set_connected(&outputs);
+        outputs
+    }
+}
+
+/// The current profile with active seasons, its transition preset and
+/// `channel` applied
+pub fn active_profile(config: &Config, profile_manager: &ProfileManager, channel: Option<&str>) -> Result<Profile> {
+    let profile = profile_manager.current_profile()
+        .context("Failed to get current profile")?;
+    let mut profile = config.apply_seasons(profile);
+    if let Some(preset) = profile.transition_preset.clone()
+        && let Err(e) = config.apply_transition_preset(&mut profile, &preset) {
+            warn!("{}", e);
+        }
+    if let Some(channel) = channel
+        && let Some(dirs) = profile.channels.get(channel) {
+            profile.wallpaper_dirs = dirs.clone();
+        }
+    Ok(profile)
+}
+
+/// Offer `profile` with a notification and run `accept` with it only if
+/// accepted. `asking` holds the profile being asked about: a newer question
+/// replaces the question, late answers to the old one are ignored.
+pub fn ask_to_switch<F, Fut>(profile: &str, asking: &Arc<Mutex<Option<String>>>, accept: F)
+where
+    F: FnOnce(String) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    {
+        let mut asking = asking.lock().unwrap();
+        if asking.as_deref() == Some(profile) {
+            return;
+        }
+        *asking = Some(profile.to_string());
+    }
+
+    let asking = asking.clone();
+    let profile = profile.to_string();
+    tokio::spawn(async move {
+        let answer = notify::ask(
+            &t!("notify-ask-title"),
+            &t!("notify-ask-body", profile = profile),
+            &[("switch", &t!("notify-ask-switch")), ("keep", &t!("notify-ask-keep"))],
+            ASK_TIMEOUT,
+        ).await;
+        {
+            let mut asking = asking.lock().unwrap();
+            if asking.as_deref() != Some(profile.as_str()) {
+                return;
+            }
+            *asking = None;
+        }
+
+        match answer {
+            Ok(Some(answer)) if answer == "switch" => {
+                info!("Switch to {} accepted", profile);
+                if let Err(e) = accept(profile.clone()).await {
+                    warn!("Failed to switch to accepted profile {}: {}", profile, e);
+                }
+            }
+            Ok(_) => info!("Switch to {} declined or not answered", profile),
+            Err(e) => {
+                warn!("Could not ask about switching to {}: {:#}", profile, e);
+                notify::send(&t!("notify-ask-title"), &t!("notify-ask-fallback", profile = profile)).await.ok();
+            }
+        }
+    });
+}
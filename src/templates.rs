@@ -0,0 +1,179 @@
+use clap::ValueEnum;
+
+/// Starting points for `init --template`, matching profiles by monitor count
+/// and resolution instead of connector names so they work unedited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// Built-in screen plus a docked profile for external monitors
+    Laptop,
+    /// One large monitor, auto-switching enabled
+    Desktop,
+    /// Two monitors with matching images
+    Dual,
+    /// Three monitors, side pair synced around the center
+    Triple,
+    /// A single catch-all profile
+    Minimal,
+}
+
+impl Template {
+    pub fn content(self) -> &'static str {
+        match self {
+            Template::Laptop => LAPTOP,
+            Template::Desktop => DESKTOP,
+            Template::Dual => DUAL,
+            Template::Triple => TRIPLE,
+            Template::Minimal => MINIMAL,
+        }
+    }
+}
+
+const MINIMAL: &str = r#"# swww-manager: minimal configuration
+current_profile = "default"
+
+[auto_switch]
+enabled = false
+interval = 300
+mode = "random"          # "random", "sequential" or "daily"
+
+[monitor_detection]
+enabled = false          # One profile, nothing to detect
+
+[profiles.default]
+monitors = ["*"]         # Wildcard: any monitor setup
+wallpaper_dirs = ["~/Pictures/Wallpapers"]
+transition = "fade"
+transition_duration = 2
+"#;
+
+const LAPTOP: &str = r#"# swww-manager: laptop with an optional dock
+current_profile = "mobile"
+
+[auto_switch]
+enabled = true
+interval = 900           # Every 15 minutes, gentle on battery
+mode = "random"
+
+[monitor_detection]
+enabled = true
+fallback_profile = "mobile"
+debounce_ms = 1500       # Docks often report outputs one by one
+
+[startup]
+restore_wallpaper = true
+
+# Only the built-in screen
+[profiles.mobile]
+wallpaper_dirs = ["~/Pictures/Wallpapers/Laptop"]
+transition = "simple"
+transition_duration = 1
+requires = { max_count = 1 }
+
+# Docked: built-in screen plus one or more external monitors
+[profiles.docked]
+wallpaper_dirs = ["~/Pictures/Wallpapers/Desktop"]
+transition = "wipe"
+transition_duration = 2
+requires = { min_count = 2 }
+"#;
+
+const DESKTOP: &str = r#"# swww-manager: single desktop monitor
+current_profile = "desktop"
+
+[auto_switch]
+enabled = true
+interval = 300
+mode = "random"
+preload = true           # Read the next image ahead of time (large images)
+
+[monitor_detection]
+enabled = true
+fallback_profile = "desktop"
+
+[startup]
+restore_wallpaper = true
+
+[profiles.desktop]
+monitors = ["*"]
+wallpaper_dirs = ["~/Pictures/Wallpapers"]
+transition = "grow"
+transition_duration = 2
+
+# Used instead when a 4K (or larger) monitor is connected
+[profiles.hidpi]
+wallpaper_dirs = ["~/Pictures/Wallpapers/4K"]
+transition = "grow"
+transition_duration = 2
+any_monitor = { min_width = 3840 }
+"#;
+
+const DUAL: &str = r#"# swww-manager: two monitors
+current_profile = "dual"
+
+[auto_switch]
+enabled = true
+interval = 600
+mode = "sequential"
+per_monitor_offset = false   # true: each monitor shows the next image in order
+
+[monitor_detection]
+enabled = true
+fallback_profile = "single"
+
+[profiles.dual]
+wallpaper_dirs = ["~/Pictures/Wallpapers/Dual"]
+transition = "wipe"
+transition_duration = 2
+requires = { min_count = 2, max_count = 2 }
+
+# When one monitor is unplugged
+[profiles.single]
+monitors = ["*"]
+wallpaper_dirs = ["~/Pictures/Wallpapers"]
+transition = "fade"
+transition_duration = 1
+"#;
+
+const TRIPLE: &str = r#"# swww-manager: three monitors
+current_profile = "triple"
+
+[auto_switch]
+enabled = true
+interval = 600
+mode = "random"
+
+[monitor_detection]
+enabled = true
+fallback_profile = "fallback"
+debounce_ms = 1500
+
+[profiles.triple]
+wallpaper_dirs = ["~/Pictures/Wallpapers/Triple"]
+transition = "outer"
+transition_duration = 2
+requires = { min_count = 3 }
+# Replace with your side monitors (see `swww-manager monitors`) so they share
+# an image while the center display rotates on its own
+# sync_groups = [["DP-1", "DP-3"]]
+
+[profiles.fallback]
+monitors = ["*"]
+wallpaper_dirs = ["~/Pictures/Wallpapers"]
+transition = "fade"
+transition_duration = 1
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_templates_parse_and_validate() {
+        for template in Template::value_variants() {
+            let config: Config = toml::from_str(template.content())
+                .unwrap_or_else(|e| panic!("{:?}: {}", template, e));
+            config.validate().unwrap();
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tracing::debug;
+
+/// Rotate the log to `events.jsonl.1` once it grows past this size
+const MAX_BYTES: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Wallpaper,
+    Profile,
+    Hotplug,
+    Config,
+    Error,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            EventKind::Wallpaper => "wallpaper",
+            EventKind::Profile => "profile",
+            EventKind::Hotplug => "hotplug",
+            EventKind::Config => "config",
+            EventKind::Error => "error",
+        };
+        f.pad(name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub time: i64,
+    pub kind: EventKind,
+    pub message: String,
+}
+
+pub fn path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .map(|p| p.join("swww-manager/events.jsonl"))
+}
+
+/// Append an event to the log. Failures are only logged, the timeline is
+/// best effort and must never break a switch.
+pub fn record(kind: EventKind, message: impl Into<String>) {
+    let event = Event {
+        time: chrono::Utc::now().timestamp(),
+        kind,
+        message: message.into(),
+    };
+    if let Err(e) = append(&event) {
+        debug!("Failed to record event: {}", e);
+    }
+}
+
+fn append(event: &Event) -> Result<()> {
+    let Some(path) = path() else { return Ok(()) };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::metadata(&path).map(|m| m.len() > MAX_BYTES).unwrap_or(false) {
+        fs::rename(&path, path.with_extension("jsonl.1"))?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(event)?)?;
+    Ok(())
+}
+
+/// The last `count` events, oldest first
+pub fn recent(count: usize) -> Vec<Event> {
+    let Some(path) = path() else { return Vec::new() };
+
+    let mut events: Vec<Event> = [path.with_extension("jsonl.1"), path]
+        .iter()
+        .filter_map(|p| fs::read_to_string(p).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let skip = events.len().saturating_sub(count);
+    events.drain(..skip);
+    events
+}
@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const SERVICE: &str = "swww-manager.service";
+const SOCKET: &str = "swww-manager.socket";
+
+pub fn unit_dir() -> Result<PathBuf> {
+    dirs::config_dir()
+        .map(|p| p.join("systemd/user"))
+        .context("Could not determine systemd user unit directory")
+}
+
+pub fn service_unit(exe: &Path) -> String {
+    format!(
+        "[Unit]
+Description=SWWW Manager Service Instance
+Documentation=https://github.com/kaminzhi/swww-manager
+Requires={socket}
+After={socket} graphical-session.target
+PartOf=graphical-session.target
+
+[Service]
+Type=simple
+ExecStart={exe} serve
+Sockets={socket}
+StandardOutput=journal
+StandardError=journal
+
+# Environment
+Environment=\"RUST_LOG=info\"
+
+# Restart policy
+Restart=on-failure
+RestartSec=5s
+# Exit status used by 'swww-manager restart'
+RestartForceExitStatus=75
+
+# Security
+PrivateTmp=true
+NoNewPrivileges=true
+
+[Install]
+WantedBy=graphical-session.target
+",
+        socket = SOCKET,
+        exe = exe.display()
+    )
+}
+
+pub fn socket_unit() -> String {
+    "[Unit]
+Description=SWWW Manager IPC Socket
+Documentation=https://github.com/kaminzhi/swww-manager
+PartOf=graphical-session.target
+After=graphical-session.target
+
+[Socket]
+ListenStream=%t/swww-manager.sock
+SocketMode=0600
+Accept=false
+
+[Install]
+WantedBy=graphical-session.target
+"
+    .to_string()
+}
+
+pub fn install(force: bool) -> Result<()> {
+    let dir = unit_dir()?;
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create {:?}", dir))?;
+
+    let exe = std::env::current_exe().context("Could not determine executable path")?;
+    let units = [(SERVICE, service_unit(&exe)), (SOCKET, socket_unit())];
+
+    for (name, content) in &units {
+        let path = dir.join(name);
+        if path.exists() && !force {
+            println!("{:?} already exists, use --force to overwrite", path);
+            continue;
+        }
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        crate::output::success(&format!("Wrote {:?}", path));
+    }
+
+    systemctl(&["daemon-reload"]);
+    println!("\nEnable socket activation with:");
+    println!("  systemctl --user enable --now {}", SOCKET);
+    Ok(())
+}
+
+pub fn uninstall() -> Result<()> {
+    let dir = unit_dir()?;
+    systemctl(&["disable", "--now", SOCKET, SERVICE]);
+
+    for name in [SERVICE, SOCKET] {
+        let path = dir.join(name);
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {:?}", path))?;
+            crate::output::success(&format!("Removed {:?}", path));
+        }
+    }
+
+    systemctl(&["daemon-reload"]);
+    Ok(())
+}
+
+fn systemctl(args: &[&str]) {
+    let ok = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if !ok {
+        eprintln!("warning: 'systemctl --user {}' failed", args.join(" "));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::client::Client;
+use crate::protocol::StatusInfo;
+use crate::status_format::hours_minutes;
+use crate::thumbnail::{self, Thumbnail};
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::time::{sleep, Duration};
+use tracing::debug;
+
+/// What `widget-json` prints, one compact object per line, for eww
+/// `deflisten` and ags subscriptions. Everything but `running` is left
+/// empty while the daemon can't be reached.
+#[derive(Debug, Default, Serialize)]
+pub struct WidgetData {
+    pub running: bool,
+    pub profile: Option<String>,
+    pub channel: Option<String>,
+    pub wallpaper: Option<String>,
+    pub wallpaper_name: Option<String>,
+    /// Small PNG preview of the wallpaper
+    pub thumbnail: Option<PathBuf>,
+    /// Main colors of the wallpaper as `#rrggbb`, most common first
+    pub palette: Vec<String>,
+    pub auto_switch: bool,
+    /// Unix time in seconds of the next automatic switch
+    pub next_switch_at: Option<i64>,
+    /// Time until the next switch, e.g. `1h05m`; changes once a minute
+    pub countdown: Option<String>,
+    pub offline: bool,
+}
+
+impl WidgetData {
+    fn new(status: StatusInfo, preview: Option<&Thumbnail>, now: i64) -> Self {
+        Self {
+            running: true,
+            wallpaper_name: status.current_wallpaper.as_deref()
+                .and_then(|p| Path::new(p).file_name())
+                .map(|n| n.to_string_lossy().to_string()),
+            thumbnail: preview.map(|t| t.path.clone()),
+            palette: preview.map(|t| t.palette.clone()).unwrap_or_default(),
+            countdown: status.next_switch_at.map(|at| hours_minutes((at - now).max(0))),
+            next_switch_at: status.next_switch_at,
+            profile: Some(status.current_profile),
+            channel: status.channel,
+            wallpaper: status.current_wallpaper,
+            auto_switch: status.auto_switch_enabled,
+            offline: status.offline,
+        }
+    }
+}
+
+/// Print the widget data once, or with `follow` again whenever it changes
+/// until stdout is closed. Following, the daemon sends its status as it
+/// changes and only the countdown is brought up to date here.
+pub async fn run(follow: bool) -> Result<()> {
+    let mut output = Output::default();
+    if !follow {
+        let status = Client::connect().await?.status().await?;
+        output.show(Some(status)).await?;
+        return Ok(());
+    }
+
+    loop {
+        let watch = match Client::connect().await {
+            Ok(client) => client.watch_status().await,
+            Err(e) => Err(e),
+        };
+        if let Ok(mut watch) = watch {
+            loop {
+                let tick = output.countdown_tick();
+                tokio::select! {
+                    status = watch.next() => match status {
+                        Ok(Some(status)) => {
+                            if !output.show(Some(status)).await? {
+                                return Ok(());
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            debug!("Status updates ended: {}", e);
+                            break;
+                        }
+                    },
+                    _ = async {
+                        match tick {
+                            Some(tick) => sleep(tick).await,
+                            None => std::future::pending().await,
+                        }
+                    } => {
+                        if !output.print_countdown()? {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+        if !output.show(None).await? {
+            return Ok(());
+        }
+        sleep(RECONNECT_INTERVAL).await;
+    }
+}
+
+/// How long `run` waits before reconnecting to a daemon that went away
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What `run` printed last
+#[derive(Default)]
+struct Output {
+    data: WidgetData,
+    printed: String,
+    /// Preview of the last wallpaper seen, `None` when making it failed
+    preview: Option<(String, Option<Thumbnail>)>,
+}
+
+impl Output {
+    /// Print the data for `status`, or for an unreachable daemon. Returns
+    /// `false` once stdout is closed.
+    async fn show(&mut self, status: Option<StatusInfo>) -> Result<bool> {
+        let Some(status) = status else {
+            self.data = WidgetData::default();
+            return self.print();
+        };
+        if let Some(wallpaper) = &status.current_wallpaper
+            && self.preview.as_ref().is_none_or(|(seen, _)| seen != wallpaper) {
+                let made = thumbnail::thumbnail(Path::new(wallpaper)).await
+                    .inspect_err(|e| debug!("No preview of {}: {}", wallpaper, e))
+                    .ok();
+                self.preview = Some((wallpaper.clone(), made));
+            }
+        let current = self.preview.as_ref()
+            .filter(|(seen, _)| status.current_wallpaper.as_ref() == Some(seen))
+            .and_then(|(_, made)| made.as_ref());
+        self.data = WidgetData::new(status, current, chrono::Utc::now().timestamp());
+        self.print()
+    }
+
+    /// Time until the countdown shows the next minute
+    fn countdown_tick(&self) -> Option<Duration> {
+        let at = self.data.next_switch_at?;
+        let left = at - chrono::Utc::now().timestamp();
+        (left > 0).then(|| Duration::from_secs((left % 60) as u64 + 1))
+    }
+
+    fn print_countdown(&mut self) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+        self.data.countdown = self.data.next_switch_at.map(|at| hours_minutes((at - now).max(0)));
+        self.print()
+    }
+
+    /// Print the data unless it is what was printed last
+    fn print(&mut self) -> Result<bool> {
+        let line = serde_json::to_string(&self.data)?;
+        if line != self.printed {
+            let mut stdout = std::io::stdout().lock();
+            if writeln!(stdout, "{}", line).and_then(|_| stdout.flush()).is_err() {
+                return Ok(false);
+            }
+            self.printed = line;
+        }
+        Ok(true)
+    }
+}